@@ -0,0 +1,362 @@
+use std::{
+    collections::BTreeMap,
+    fmt,
+    fs::{self, File},
+    io,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    cards::HandRank,
+    combinatorics::FlushKind,
+    errors::LottoError,
+    highscores::{data_dir, sibling, HighScores, ScoreInfo},
+    lotto::RuleId,
+    store::Awards,
+};
+
+/// How many scored commits earn [`Achievement::Commits`].
+pub const COMMITS: usize = 100;
+/// How many commits in a row need pairs for [`Achievement::PairStreak`].
+pub const PAIR_STREAK: usize = 3;
+/// How long a run earns [`Achievement::LongStraight`].
+pub const LONG_STRAIGHT: usize = 7;
+
+/// A badge unlocked once per user by a scoring milestone.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Achievement {
+    FirstFullHouse,
+    LongStraight,
+    NumberFlush,
+    Commits,
+    PairStreak,
+}
+
+impl Achievement {
+    pub const ALL: [Achievement; 5] = [
+        Achievement::FirstFullHouse,
+        Achievement::LongStraight,
+        Achievement::NumberFlush,
+        Achievement::Commits,
+        Achievement::PairStreak,
+    ];
+
+    pub fn title(&self) -> String {
+        match self {
+            Achievement::FirstFullHouse => "First full house".into(),
+            Achievement::LongStraight => format!("Straight of {}", LONG_STRAIGHT),
+            Achievement::NumberFlush => "All-number flush".into(),
+            Achievement::Commits => format!("{} commits scored", COMMITS),
+            Achievement::PairStreak => "Pairs streak".into(),
+        }
+    }
+
+    pub fn description(&self) -> String {
+        match self {
+            Achievement::FirstFullHouse => "Score a full house, in the hash or the hand".into(),
+            Achievement::LongStraight => format!("Score a run of {} or more", LONG_STRAIGHT),
+            Achievement::NumberFlush => "Score a hash of only numbers".into(),
+            Achievement::Commits => format!("Score {} commits", COMMITS),
+            Achievement::PairStreak => {
+                format!("Score pairs in {} commits in a row", PAIR_STREAK)
+            }
+        }
+    }
+}
+
+impl fmt::Display for Achievement {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.title())
+    }
+}
+
+/// Whether a hash's rules have a pair, or more than one group of the same
+/// character.
+fn has_pairs(rules: &[RuleId]) -> bool {
+    rules.iter().any(|r| {
+        matches!(
+            r,
+            RuleId::OfAKind(2) | RuleId::Pairs(_) | RuleId::Kinds { .. } | RuleId::FullHouse
+        )
+    })
+}
+
+/// The achievements a new score earns, given the author's earlier scores.
+/// Scores that don't count earn nothing, and streaks only count commits to
+/// the same repository.
+pub fn earned(score: &ScoreInfo, history: &[ScoreInfo]) -> Vec<Achievement> {
+    if !score.counts() {
+        return vec![];
+    }
+    let mut history: Vec<&ScoreInfo> = history
+        .iter()
+        .filter(|s| s.counts() && s.commit != score.commit)
+        .collect();
+    history.sort_by_key(|s| s.date);
+
+    let mut earned = vec![];
+    let hit = |rule: &dyn Fn(&RuleId) -> bool| score.rules.iter().any(rule);
+    if hit(&|r| matches!(r, RuleId::FullHouse | RuleId::Poker(_, HandRank::FullHouse))) {
+        earned.push(Achievement::FirstFullHouse);
+    }
+    if hit(&|r| matches!(r, RuleId::Straight(run) if *run >= LONG_STRAIGHT)) {
+        earned.push(Achievement::LongStraight);
+    }
+    if hit(&|r| *r == RuleId::Flush(FlushKind::Numbers)) {
+        earned.push(Achievement::NumberFlush);
+    }
+    if history.len() + 1 >= COMMITS {
+        earned.push(Achievement::Commits);
+    }
+    let repo = score.key().0;
+    let streak = history
+        .iter()
+        .rev()
+        .filter(|s| s.key().0 == repo)
+        .take_while(|s| has_pairs(&s.rules))
+        .count();
+    if has_pairs(&score.rules) && streak + 1 >= PAIR_STREAK {
+        earned.push(Achievement::PairStreak);
+    }
+    earned
+}
+
+/// An achievement and the commit that unlocked it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Unlocked {
+    pub achievement: Achievement,
+    pub commit: String,
+    pub repo: String,
+    pub date: i64,
+}
+
+/// Unlock what newly saved scores earned for their authors, in the order they
+/// were committed, returning the achievements they didn't have yet. They are
+/// kept with the scores, by lowercased author email.
+pub fn unlock(
+    highscores: &mut (impl HighScores + ?Sized),
+    scores: &[ScoreInfo],
+) -> Result<Vec<Unlocked>, LottoError> {
+    let mut scores: Vec<&ScoreInfo> = scores.iter().filter(|s| s.counts()).collect();
+    scores.sort_by_key(|s| s.date);
+    let mut histories: BTreeMap<String, Vec<ScoreInfo>> = BTreeMap::new();
+    let mut earned = Awards::default();
+    for score in scores {
+        let Some(author) = &score.author else {
+            continue;
+        };
+        let email = author.email.to_lowercase();
+        if !histories.contains_key(&email) {
+            let history = highscores.load_by_author(&author.email)?;
+            histories.insert(email.clone(), history);
+        }
+        let earlier: Vec<ScoreInfo> = histories[&email]
+            .iter()
+            .filter(|s| s.date <= score.date)
+            .cloned()
+            .collect();
+        let unlocked = earned.achievements.entry(email).or_default();
+        for achievement in self::earned(score, &earlier) {
+            if !unlocked.iter().any(|u| u.achievement == achievement) {
+                unlocked.push(Unlocked {
+                    achievement,
+                    commit: score.commit.clone(),
+                    repo: score.repo.clone(),
+                    date: score.date,
+                });
+            }
+        }
+    }
+    earned
+        .achievements
+        .retain(|_, unlocked| !unlocked.is_empty());
+    if earned.achievements.is_empty() {
+        return Ok(vec![]);
+    }
+    let mut new = Some(earned.clone());
+    highscores.modify_awards(&mut |awards| awards.add_missing(new.take().unwrap_or_default()))?;
+    // what's kept, which may have been unlocked earlier by another process
+    let kept = highscores.load_awards()?.achievements;
+    Ok(earned
+        .achievements
+        .into_iter()
+        .flat_map(|(email, unlocked)| {
+            let kept = kept.get(&email).cloned().unwrap_or_default();
+            unlocked.into_iter().filter(move |u| kept.contains(u))
+        })
+        .collect())
+}
+
+/// Where achievements were kept before they were kept with the scores.
+pub fn legacy_path() -> Result<PathBuf, LottoError> {
+    Ok(data_dir()?.join("achievements.json"))
+}
+
+/// Move the achievements unlocked in a file on this machine into the store,
+/// renaming the file once they're all there.
+pub fn import_legacy(
+    path: &Path,
+    highscores: &mut (impl HighScores + ?Sized),
+) -> Result<(), LottoError> {
+    let unlocked: BTreeMap<String, Vec<Unlocked>> = match File::open(path) {
+        Ok(file) => serde_json::from_reader(io::BufReader::new(file))
+            .with_context(|| format!("reading {}", path.display()))
+            .map_err(LottoError::ApplicationDirError)?,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e.into()),
+    };
+    let mut missing = Some(Awards {
+        achievements: unlocked.clone(),
+        ..Default::default()
+    });
+    highscores
+        .modify_awards(&mut |awards| awards.add_missing(missing.take().unwrap_or_default()))?;
+    // a server unlocks achievements itself, so the file is kept if it can't
+    let kept = highscores.load_awards()?.achievements;
+    let all_kept = unlocked.iter().all(|(email, unlocked)| {
+        unlocked.iter().all(|u| {
+            kept.get(email)
+                .is_some_and(|k| k.iter().any(|k| k.achievement == u.achievement))
+        })
+    });
+    if all_kept {
+        fs::rename(path, sibling(path, "imported"))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{highscores::HighScoresImpl, lotto::LottoResult, test_util::score_info};
+
+    fn score(commit: &str, date: i64, rules: &[RuleId]) -> ScoreInfo {
+        ScoreInfo {
+            date,
+            rules: rules.to_vec(),
+            ..score_info(commit, 100)
+        }
+    }
+
+    fn earned_by(hash: &str, history: &[ScoreInfo]) -> Vec<Achievement> {
+        let result = LottoResult::new(hash);
+        let rules: Vec<RuleId> = result.rules.iter().map(|r| r.id()).collect();
+        earned(&score(hash, 100, &rules), history)
+    }
+
+    #[test]
+    fn test_earned() {
+        assert_eq!(
+            earned_by("1234567", &[]),
+            vec![Achievement::LongStraight, Achievement::NumberFlush]
+        );
+        assert_eq!(earned_by("aaabb1f", &[]), vec![Achievement::FirstFullHouse]);
+        // a run of six, and not a flush
+        assert!(earned_by("abcdef0", &[]).is_empty());
+
+        let history = vec![
+            score("0000001", 1, &[RuleId::Pairs(2)]),
+            score("0000002", 2, &[RuleId::Straight(4)]),
+            score("0000003", 3, &[RuleId::FullHouse]),
+            score("0000004", 4, &[RuleId::OfAKind(2)]),
+        ];
+        assert!(earned_by("aabbc12", &history).contains(&Achievement::PairStreak));
+        assert!(!earned_by("aabbc12", &history[..3]).contains(&Achievement::PairStreak));
+        // a single pair is enough
+        assert!(earned_by("a0bc1a5", &history).contains(&Achievement::PairStreak));
+
+        // commits to other repositories don't add to the streak, or break it
+        let elsewhere = |mut score: ScoreInfo| {
+            score.repo = "/elsewhere".into();
+            score
+        };
+        let mut mixed = history.clone();
+        mixed[3] = elsewhere(mixed[3].clone());
+        assert!(!earned_by("aabbc12", &mixed).contains(&Achievement::PairStreak));
+        let mut mixed = history.clone();
+        mixed.push(elsewhere(score("0000005", 5, &[])));
+        assert!(earned_by("aabbc12", &mixed).contains(&Achievement::PairStreak));
+
+        let history: Vec<ScoreInfo> = (0..COMMITS as i64 - 1)
+            .map(|i| score(&format!("{:07}", i), i, &[]))
+            .collect();
+        assert_eq!(earned_by("abc0123", &history), vec![Achievement::Commits]);
+        assert!(earned_by("abc0123", &history[1..]).is_empty());
+    }
+
+    #[test]
+    fn test_unlock() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut highscores = HighScoresImpl::new(&dir.path().join("scores.json")).unwrap();
+        let flush = [RuleId::Flush(FlushKind::Numbers)];
+        let pairs = [RuleId::OfAKind(2)];
+        let scores = vec![
+            score("aaaaaaa", 1, &flush),
+            score("bbbbbbb", 2, &pairs),
+            score("ccccccc", 3, &pairs),
+        ];
+        highscores.save_all(scores.clone()).unwrap();
+        let unlocked = unlock(&mut highscores, &scores).unwrap();
+        assert_eq!(unlocked.len(), 1);
+        assert_eq!(unlocked[0].commit, "aaaaaaa");
+
+        // only once per user, and in the order they were committed
+        let new = vec![score("ddddddd", 4, &pairs), score("eeeeeee", 5, &flush)];
+        highscores.save_all(new.clone()).unwrap();
+        let unlocked = unlock(&mut highscores, &new).unwrap();
+        assert_eq!(unlocked.len(), 1);
+        assert_eq!(unlocked[0].achievement, Achievement::PairStreak);
+        assert_eq!(unlocked[0].commit, "ddddddd");
+
+        let mut other = score("fffffff", 6, &flush);
+        other.author.as_mut().unwrap().email = "Other@example.com".into();
+        highscores.save(other.clone()).unwrap();
+        assert_eq!(unlock(&mut highscores, &[other]).unwrap().len(), 1);
+
+        let all = highscores.load_awards().unwrap().achievements;
+        assert_eq!(all["someone@example.com"].len(), 2);
+        assert_eq!(all["other@example.com"].len(), 1);
+    }
+
+    #[test]
+    fn test_import_legacy() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut highscores = HighScoresImpl::new(&dir.path().join("scores.json")).unwrap();
+        let path = dir.path().join("achievements.json");
+        let unlocked = |achievement, date| Unlocked {
+            achievement,
+            commit: "aaaaaaa".into(),
+            repo: "/repo".into(),
+            date,
+        };
+        let legacy: BTreeMap<String, Vec<Unlocked>> = [(
+            "someone@example.com".to_string(),
+            vec![
+                unlocked(Achievement::Commits, 1),
+                unlocked(Achievement::NumberFlush, 1),
+            ],
+        )]
+        .into();
+        highscores
+            .modify_awards(&mut |awards| {
+                awards.achievements.insert(
+                    "someone@example.com".into(),
+                    vec![unlocked(Achievement::Commits, 2)],
+                );
+                true
+            })
+            .unwrap();
+        fs::write(&path, serde_json::to_string(&legacy).unwrap()).unwrap();
+
+        import_legacy(&path, &mut highscores).unwrap();
+        // the earlier unlock wins
+        let all = highscores.load_awards().unwrap().achievements;
+        assert_eq!(all, legacy);
+        assert!(!path.exists());
+        import_legacy(&path, &mut highscores).unwrap();
+    }
+}
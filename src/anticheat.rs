@@ -0,0 +1,244 @@
+use std::fmt;
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+
+use crate::{config::AntiCheatConfig, git::Commit, highscores::ScoreInfo};
+
+/// How far clocks can be off before dates look tampered with.
+const CLOCK_SKEW: i64 = 5 * 60;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum Flag {
+    /// The same tree had already been scored this many times.
+    Rerolled { times: usize },
+    /// The committer date is this many seconds after the author date, or
+    /// before it if negative.
+    DateSkew { seconds: i64 },
+    /// The committer date is this many seconds in the future.
+    FutureDate { seconds: i64 },
+    /// Another commit has exactly the same tree.
+    IdenticalTree { commit: String },
+}
+
+impl fmt::Display for Flag {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Flag::Rerolled { times } => write!(f, "tree already scored {} times", times),
+            Flag::DateSkew { seconds } if *seconds < 0 => {
+                write!(f, "committed {} before it was authored", duration(-seconds))
+            }
+            Flag::DateSkew { seconds } => {
+                write!(f, "committed {} after it was authored", duration(*seconds))
+            }
+            Flag::FutureDate { seconds } => {
+                write!(f, "committed {} in the future", duration(*seconds))
+            }
+            Flag::IdenticalTree { commit } => write!(f, "same tree as {}", commit),
+        }
+    }
+}
+
+fn duration(seconds: i64) -> String {
+    let (amount, unit) = match seconds {
+        s if s >= 86400 => (s / 86400, "day"),
+        s if s >= 3600 => (s / 3600, "hour"),
+        s if s >= 60 => (s / 60, "minute"),
+        s => (s, "second"),
+    };
+    format!("{} {}{}", amount, unit, if amount == 1 { "" } else { "s" })
+}
+
+/// Flag a new score if its commit looks like it was made over and over until
+/// it scored well, disqualifying it if the config says so. `scores` are the
+/// scores already recorded for the repository.
+pub fn check(
+    score: &mut ScoreInfo,
+    commit: &Commit,
+    scores: &[ScoreInfo],
+    config: &AntiCheatConfig,
+) {
+    score.flags = inspect(commit, scores, config, Utc::now().timestamp());
+    score.disqualified = config.disqualify && !score.flags.is_empty();
+}
+
+pub fn inspect(
+    commit: &Commit,
+    scores: &[ScoreInfo],
+    config: &AntiCheatConfig,
+    now: i64,
+) -> Vec<Flag> {
+    let mut flags = vec![];
+    let provenance = &commit.provenance;
+    if provenance.tree.is_empty() {
+        return flags;
+    }
+
+    let same_tree: Vec<&ScoreInfo> = scores
+        .iter()
+        .filter(|s| s.tree.as_ref() == Some(&provenance.tree) && s.commit != commit.hash)
+        .collect();
+    if same_tree.len() >= config.max_rerolls {
+        flags.push(Flag::Rerolled {
+            times: same_tree.len(),
+        });
+    }
+    // merges can have the same tree as a parent, and root commits have none
+    let identical = match provenance.parents.as_slice() {
+        [(parent, tree)] if *tree == provenance.tree => Some(parent.clone()),
+        [_] => same_tree
+            .iter()
+            .find(|s| s.counts() && s.rewritten_as.is_none())
+            .map(|s| s.commit.clone()),
+        _ => None,
+    };
+    if let Some(commit) = identical {
+        flags.push(Flag::IdenticalTree { commit });
+    }
+
+    let committed: i64 = commit.date.parse().unwrap_or(now);
+    let skew = committed - provenance.author_date;
+    // rebasing an old commit is bound to move the committer date a long way
+    if !provenance.rewritten && (skew < -CLOCK_SKEW || skew > config.max_date_skew_hours * 3600) {
+        flags.push(Flag::DateSkew { seconds: skew });
+    }
+    if committed > now + CLOCK_SKEW {
+        flags.push(Flag::FutureDate {
+            seconds: committed - now,
+        });
+    }
+    flags
+}
+
+/// The flags that can be checked from a score alone, as the score server does
+/// without the commit: its tree already scored too often, or a date in the
+/// future. `scores` are the scores already recorded for the repository.
+pub fn inspect_score(
+    score: &ScoreInfo,
+    scores: &[ScoreInfo],
+    config: &AntiCheatConfig,
+    now: i64,
+) -> Vec<Flag> {
+    let mut flags = vec![];
+    if let Some(tree) = &score.tree {
+        let times = scores
+            .iter()
+            .filter(|s| s.tree.as_ref() == Some(tree) && s.key() != score.key())
+            .count();
+        if times >= config.max_rerolls {
+            flags.push(Flag::Rerolled { times });
+        }
+    }
+    if score.date > now + CLOCK_SKEW {
+        flags.push(Flag::FutureDate {
+            seconds: score.date - now,
+        });
+    }
+    flags
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        git::{Person, Provenance, RepoId},
+        lotto::LottoResult,
+    };
+
+    fn commit(hash: &str, tree: &str, parent_tree: &str, authored: i64, committed: i64) -> Commit {
+        commit_with_parents(hash, tree, &[parent_tree], authored, committed)
+    }
+
+    fn commit_with_parents(
+        hash: &str,
+        tree: &str,
+        parent_trees: &[&str],
+        authored: i64,
+        committed: i64,
+    ) -> Commit {
+        let person = Person {
+            name: "Someone".into(),
+            email: "a@example.com".into(),
+        };
+        let repo = RepoId {
+            root: Some("root".into()),
+            remote: None,
+            path: "/repo".into(),
+        };
+        Commit::new(
+            repo,
+            hash.into(),
+            hash.into(),
+            committed.to_string(),
+            None,
+            person.clone(),
+            person,
+        )
+        .with_provenance(Provenance {
+            tree: tree.into(),
+            parents: parent_trees
+                .iter()
+                .map(|tree| ("parent".into(), tree.to_string()))
+                .collect(),
+            author_date: authored,
+            rewritten: false,
+        })
+    }
+
+    fn scored(commit: &Commit) -> ScoreInfo {
+        ScoreInfo::new(&LottoResult::new(&commit.hash), commit)
+    }
+
+    #[test]
+    fn test_inspect() {
+        let config = AntiCheatConfig::default();
+        let now = 1_000_000;
+        let honest = commit("aaaaaaa", "tree", "other", now - 100, now - 50);
+        assert_eq!(inspect(&honest, &[], &config, now), vec![]);
+
+        let empty = commit("bbbbbbb", "tree", "tree", now, now);
+        assert_eq!(
+            inspect(&empty, &[], &config, now),
+            vec![Flag::IdenticalTree {
+                commit: "parent".into()
+            }]
+        );
+
+        let merge = commit_with_parents("fffffff", "tree", &["tree", "other"], now, now);
+        assert_eq!(inspect(&merge, &[scored(&honest)], &config, now), vec![]);
+        let root = commit_with_parents("0000000", "tree", &[], now, now);
+        assert_eq!(inspect(&root, &[scored(&honest)], &config, now), vec![]);
+
+        let rerolls: Vec<ScoreInfo> = ["1111111", "2222222", "3333333"]
+            .iter()
+            .map(|hash| {
+                let mut score = scored(&commit(hash, "tree", "other", now, now));
+                score.superseded = true;
+                score
+            })
+            .collect();
+        assert_eq!(
+            inspect(&honest, &rerolls, &config, now),
+            vec![Flag::Rerolled { times: 3 }]
+        );
+
+        let backdated = commit("ccccccc", "new", "other", now, now - 3600);
+        let future = commit("ddddddd", "new", "other", now + 7200, now + 7200);
+        assert_eq!(
+            inspect(&backdated, &[], &config, now),
+            vec![Flag::DateSkew { seconds: -3600 }]
+        );
+        assert_eq!(
+            inspect(&future, &[], &config, now),
+            vec![Flag::FutureDate { seconds: 7200 }]
+        );
+        let mut rebased = commit("eeeeeee", "new", "other", now - 30 * 86400, now);
+        rebased.provenance.rewritten = true;
+        assert_eq!(inspect(&rebased, &[], &config, now), vec![]);
+        assert_eq!(
+            Flag::DateSkew { seconds: -3600 }.to_string(),
+            "committed 1 hour before it was authored"
+        );
+    }
+}
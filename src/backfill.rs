@@ -0,0 +1,125 @@
+use std::collections::HashSet;
+
+use git2::{Repository, Sort};
+
+use crate::{
+    achievements, anticheat,
+    config::{AntiCheatConfig, RulesConfig},
+    errors::LottoError,
+    git::{Commit, RepoId},
+    highscores::{HighScores, ScoreInfo},
+    scoring::score,
+};
+
+#[derive(Debug, Default)]
+pub struct BackfillOptions {
+    /// Only score commits made at or after this unix timestamp.
+    pub since: Option<i64>,
+    /// Only score commits whose author has this email address.
+    pub author: Option<String>,
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct BackfillSummary {
+    pub scored: usize,
+    pub skipped: usize,
+    /// How many achievements the new scores unlocked.
+    pub unlocked: usize,
+}
+
+/// Score every commit reachable from HEAD that isn't already recorded, and
+/// unlock the achievements they earn.
+pub fn backfill(
+    repo: &Repository,
+    highscores: &mut impl HighScores,
+    rules: &RulesConfig,
+    anti_cheat: &AntiCheatConfig,
+    options: &BackfillOptions,
+) -> Result<BackfillSummary, LottoError> {
+    let repo_id = RepoId::for_repo(repo)?;
+    let mut scores = highscores.load_all(Some(&repo_id))?;
+    let recorded: HashSet<String> = scores.iter().map(|s| s.commit.clone()).collect();
+    let already = scores.len();
+
+    let mut walk = repo.revwalk()?;
+    walk.set_sorting(Sort::TIME | Sort::REVERSE)?;
+    walk.push_head()?;
+
+    let mut summary = BackfillSummary::default();
+    for oid in walk {
+        let oid = oid?;
+        let git_commit = repo.find_commit(oid)?;
+        if options
+            .since
+            .is_some_and(|since| git_commit.time().seconds() < since)
+        {
+            continue;
+        }
+        if let Some(author) = &options.author {
+            if git_commit.author().email() != Some(author.as_str()) {
+                continue;
+            }
+        }
+        let commit = Commit::from_repo_and_commit(&repo_id, git_commit)?;
+        if recorded.contains(&commit.hash) {
+            summary.skipped += 1;
+            continue;
+        }
+        let mut new_score = ScoreInfo::new(&score(&commit, rules), &commit);
+        anticheat::check(&mut new_score, &commit, &scores, anti_cheat);
+        scores.push(new_score);
+        summary.scored += 1;
+    }
+    let new = scores.split_off(already);
+    highscores.save_all(new.clone())?;
+    summary.unlocked = achievements::unlock(highscores, &new)?.len();
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{highscores::HighScoresImpl, test_util::commit};
+
+    #[test]
+    fn test_backfill() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path().join("repo")).unwrap();
+        commit(&repo, "a@example.com", 1_000);
+        commit(&repo, "b@example.com", 2_000);
+        commit(&repo, "a@example.com", 3_000);
+        let mut highscores = HighScoresImpl::new(&dir.path().join("scores.json")).unwrap();
+        let rules = RulesConfig::default();
+
+        let options = BackfillOptions {
+            since: Some(1_500),
+            author: Some("a@example.com".into()),
+        };
+        let anti_cheat = AntiCheatConfig::default();
+        let first = backfill(&repo, &mut highscores, &rules, &anti_cheat, &options).unwrap();
+        assert_eq!((first.scored, first.skipped), (1, 0));
+
+        let summary = backfill(
+            &repo,
+            &mut highscores,
+            &rules,
+            &anti_cheat,
+            &Default::default(),
+        )
+        .unwrap();
+        assert_eq!((summary.scored, summary.skipped), (2, 1));
+        let scores = highscores
+            .load(Some(&RepoId::for_repo(&repo).unwrap()))
+            .unwrap();
+        assert_eq!(scores.len(), 3);
+        // the achievements the scores earned are kept with them
+        let unlocked: usize = highscores
+            .load_awards()
+            .unwrap()
+            .achievements
+            .values()
+            .map(Vec::len)
+            .sum();
+        assert_eq!(unlocked, first.unlocked + summary.unlocked);
+    }
+}
@@ -1,35 +1,70 @@
-use commit_poker::git::{git_commit, Commit};
-use commit_poker::highscores::{HighScores, HighScoresImpl, ScoreInfo};
-use commit_poker::lotto::LottoResult;
-use commit_poker::output::{TerminalOutputer, TerminalOutputerImpl};
+use commit_poker::config::Config;
+use commit_poker::git::commit;
+use commit_poker::highscores;
+use commit_poker::output::{Format, JsonOutputer, TerminalOutputer, TerminalOutputerImpl};
+use commit_poker::scoring::record;
 
 use commit_poker::errors::LottoError;
-use std::env;
+use std::{env, process::ExitCode};
 
-fn main() -> Result<(), LottoError> {
+/// Exits like `git commit` would, so scripts can't tell the difference.
+fn main() -> ExitCode {
+    match run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            // git has already said what went wrong
+            if !matches!(e, LottoError::CommandFailed { .. }) {
+                eprintln!("Error: {}", e);
+            }
+            ExitCode::from(e.exit_code())
+        }
+    }
+}
+
+fn run() -> Result<(), LottoError> {
     let commit_args: Vec<String> = env::args().skip(1).collect();
-    let output = TerminalOutputerImpl::new();
-    let highscores = HighScoresImpl::standard()?;
-    commit_lotto(output, highscores, commit_args)
+    let config = Config::load()?;
+    match config.output.format {
+        Format::Terminal => commit_lotto(
+            TerminalOutputerImpl::from_config(config.output.clone()),
+            &config,
+            commit_args,
+        ),
+        Format::Json => commit_lotto(JsonOutputer::new(), &config, commit_args),
+    }
 }
 
+/// The store is only opened once the commit is made, so a store that can't be
+/// opened doesn't stop anyone committing.
 fn commit_lotto(
     output: impl TerminalOutputer,
-    mut highscores: impl HighScores,
+    config: &Config,
     commit_args: Vec<String>,
 ) -> Result<(), LottoError> {
     output.pre_commit();
-    git_commit(commit_args)?;
-    let commit = Commit::latest()?;
-    let results = LottoResult::new(&commit.hash);
-    let scores = highscores.load(Some(&commit.repo))?;
-    let score = ScoreInfo::new(&results, &commit);
-    output.post_commit(&results);
-    if let Some(old_score) = scores.first() {
-        if score.score > old_score.score {
-            output.high_score(&score, old_score);
+    let commit = match commit(config.commit.backend, config.output.format, commit_args) {
+        Ok(commit) => commit,
+        Err(e) => {
+            output.failed();
+            return Err(e);
         }
+    };
+    let recorded = highscores::open(&config.storage).and_then(|mut highscores| {
+        record(
+            &output,
+            &mut highscores,
+            &config.rules,
+            &config.anti_cheat,
+            &config.seasons,
+            &commit,
+        )
+    });
+    // the commit has been made, so this still exits like git commit did
+    if let Err(e) = recorded {
+        eprintln!(
+            "Committed {}, but its score wasn't saved: {}",
+            commit.hash, e
+        );
     }
-    highscores.save(score)?;
     Ok(())
 }
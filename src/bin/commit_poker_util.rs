@@ -1,11 +1,36 @@
-use std::path::PathBuf;
+use std::{
+    collections::{BTreeMap, HashSet},
+    env,
+    io::{stdin, stdout, IsTerminal, Read},
+    path::{Path, PathBuf},
+};
 
-use chrono::{Local, LocalResult, TimeZone, Utc};
-use clap::Parser;
+use chrono::{Local, LocalResult, NaiveDate, TimeZone, Utc};
+use clap::{Parser, Subcommand};
+use colored::Colorize;
 use commit_poker::{
+    achievements::{self, Achievement, Unlocked},
+    anticheat,
+    backfill::{backfill, BackfillOptions},
+    cards::Deal,
+    config::{Backend, Config, RewritePolicy, SeasonConfig},
     errors::LottoError,
-    highscores::{HighScores, HighScoresImpl, ScoreInfo},
+    git::{resolve_commits, Commit, ObjectFormat, RepoId},
+    highscores::{self, by_author, AuthorStats, HighScores, ScoreInfo},
+    hook::{Hook, HookKind, HookStatus, WRAPPED_ENV},
+    mine::{mine, rewrite_head, MineOptions, Nonce, Target},
+    output::{Format, JsonOutputer, TerminalOutputer, TerminalOutputerImpl},
+    period::{local_date, midnight, season_at, Period, Window},
+    refstore::{add_missing, RefHighScores},
+    rewrite::{apply, parse_rewrites, prune, rewritten},
+    scoring::{record, score},
+    seasons::{self, winners},
+    server::ScoreServer,
+    simulation::{simulate, RuleFrequency},
+    stats::{stats, Stats},
 };
+use git2::Repository;
+use rand::{rngs::StdRng, SeedableRng};
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
@@ -15,30 +40,809 @@ enum Cli {
         n: usize,
         #[clap(short, long)]
         repo: Option<PathBuf>,
+        /// Rank authors by their best score
+        #[clap(long)]
+        by_author: bool,
+        /// Only show scores by the author with this email
+        #[clap(long)]
+        author: Option<String>,
+        /// Show mined commits, which aren't on the leaderboard
+        #[clap(long)]
+        mined: bool,
+        /// Show scores of hashes this long instead of the configured length
+        #[clap(long)]
+        length: Option<usize>,
+        /// Only scores committed on or after this day, e.g. 2026-10-01
+        #[clap(long, value_parser = parse_day)]
+        since: Option<NaiveDate>,
+        /// Only scores committed on or before this day
+        #[clap(long, value_parser = parse_day)]
+        until: Option<NaiveDate>,
+        /// Only scores committed today
+        #[clap(long, conflicts_with_all = ["since", "until", "week", "month", "season"])]
+        today: bool,
+        /// Only scores committed this week, from Monday
+        #[clap(long, conflicts_with_all = ["since", "until", "month", "season"])]
+        week: bool,
+        /// Only scores committed this month
+        #[clap(long, conflicts_with_all = ["since", "until", "season"])]
+        month: bool,
+        /// Only scores from a season, the current one if no name is given
+        #[clap(long, num_args = 0..=1, default_missing_value = "", conflicts_with_all = ["since", "until"])]
+        season: Option<String>,
+    },
+    /// List the seasons, archiving the winners of those that have ended
+    Seasons,
+    /// Summarise the scores: points, how often each rule hit against how
+    /// often it should, the rarest hand and the longest run without points
+    Stats {
+        #[clap(short, long)]
+        repo: Option<PathBuf>,
+        /// Only scores by the author with this email
+        #[clap(long)]
+        author: Option<String>,
+        /// Summarise scores of hashes this long instead of the configured length
+        #[clap(long)]
+        length: Option<usize>,
+        /// terminal or json
+        #[clap(long)]
+        format: Option<Format>,
+    },
+    /// List the achievements each author has unlocked
+    Achievements {
+        /// Only show the author with this email
+        #[clap(long)]
+        author: Option<String>,
+        /// terminal or json
+        #[clap(long)]
+        format: Option<Format>,
+    },
+    /// Check each rule's probability against randomly generated hashes
+    Simulate {
+        /// Defaults to the configured length
+        #[clap(short, long)]
+        length: Option<usize>,
+        #[clap(short, default_value = "1000000")]
+        n: u64,
+        #[clap(long)]
+        seed: Option<u64>,
+        /// Width of the confidence interval in standard deviations
+        #[clap(short, default_value = "4.0")]
+        z: f64,
+        /// Deal seven cards instead of five
+        #[clap(long)]
+        holdem: bool,
+    },
+    /// Score commits already in a repository's history
+    Backfill {
+        #[clap(short, long, default_value = ".")]
+        repo: PathBuf,
+        /// Only score commits made on or after this date (YYYY-MM-DD)
+        #[clap(long, value_parser = parse_date)]
+        since: Option<i64>,
+        /// Only score commits by this author email
+        #[clap(long)]
+        author: Option<String>,
+    },
+    /// Show what a revision, or each commit in a range, scores
+    Score {
+        /// A revision such as HEAD~2 or v1.0, or a range such as main..feature
+        revision: String,
+        /// Don't record the scores
+        #[clap(long)]
+        no_save: bool,
+        /// terminal or json
+        #[clap(long)]
+        format: Option<Format>,
+    },
+    /// Score every commit in the current repository with a post-commit hook
+    Hook {
+        #[command(subcommand)]
+        action: HookAction,
+    },
+    /// Drop the scores of commits that are no longer reachable from any ref
+    Prune {
+        #[clap(short, long, default_value = ".")]
+        repo: PathBuf,
+    },
+    /// Check or upgrade the format of the high score store
+    Store {
+        #[command(subcommand)]
+        action: StoreAction,
+    },
+    /// Rewrite HEAD until its hash scores at least a number of points, or
+    /// hits a rule such as "flush". Mined scores don't count.
+    Mine {
+        #[clap(long)]
+        target: Target,
+        /// Move the committer date back instead of adding a trailer
+        #[clap(long)]
+        timestamp: bool,
+        /// How many seconds the committer date can move back
+        #[clap(long, default_value = "3600")]
+        window: u64,
+        /// Defaults to the number of CPUs
+        #[clap(long)]
+        threads: Option<usize>,
+        #[clap(long, default_value = "10000000")]
+        max_tries: u64,
+    },
+    /// Share scores with a team through refs/commit-poker/scores on a remote
+    Sync {
+        #[command(subcommand)]
+        action: SyncAction,
+        #[clap(long, default_value = "origin")]
+        remote: String,
+    },
+    /// Serve the leaderboard as JSON and a web page, and take scores from
+    /// machines using the remote backend
+    Serve {
+        #[clap(long, default_value = "127.0.0.1:8080")]
+        bind: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum SyncAction {
+    /// Merge in the remote's scores, then push the result
+    Push,
+    /// Merge in the remote's scores
+    Pull,
+}
+
+#[derive(Subcommand)]
+enum StoreAction {
+    /// Upgrade the high score store to the current format
+    Migrate,
+    /// Show the format version and size of the high score store
+    Check,
+}
+
+#[derive(Subcommand)]
+enum HookAction {
+    Install,
+    Uninstall,
+    Status,
+    /// Score the latest commit, run by the hook itself
+    #[command(hide = true)]
+    Run,
+    /// Record amended or rebased commits, run by the post-rewrite hook
+    #[command(hide = true)]
+    Rewritten {
+        /// `amend` or `rebase`
+        command: Option<String>,
     },
 }
 
 fn main() -> Result<(), LottoError> {
     let args = Cli::parse();
+    let config = Config::load()?;
     match args {
-        Cli::Highscores { n, repo } => {
-            HighScoresImpl::standard()?
-                .load(repo.as_deref())?
-                .into_iter()
-                .take(n)
-                .for_each(|s| println!("{}", format_score(&s)));
+        Cli::Highscores {
+            n,
+            repo,
+            by_author: group,
+            author,
+            mined,
+            length,
+            since,
+            until,
+            today,
+            week,
+            month,
+            season,
+        } => {
+            let repo = repo.map(|r| RepoId::discover(&r)).transpose()?;
+            let length = length.unwrap_or(config.rules.length);
+            let window = match (today, week, month, season) {
+                (true, ..) => Period::Day.around(Utc::now().timestamp(), &[]).unwrap(),
+                (_, true, ..) => Period::Week.around(Utc::now().timestamp(), &[]).unwrap(),
+                (_, _, true, _) => Period::Month.around(Utc::now().timestamp(), &[]).unwrap(),
+                (.., Some(name)) => find_season(&config.seasons, &name)?.window(),
+                _ => Window {
+                    since: since.map(midnight),
+                    until: until.and_then(|d| d.succ_opt()).map(midnight),
+                },
+            };
+            let mut highscores = highscores::open(&config.storage)?;
+            if mined {
+                let mut scores = highscores.load_all(repo.as_ref())?;
+                scores.retain(|s| s.mined && s.is_listed(length, &window, author.as_deref()));
+                scores.sort_by_key(|s| std::cmp::Reverse(s.score));
+                scores
+                    .iter()
+                    .take(n)
+                    .for_each(|s| println!("{}", format_score(s)));
+                return Ok(());
+            }
+            if group || author.is_some() {
+                let filled = highscores.migrate_authors()?;
+                if filled > 0 {
+                    eprintln!("Filled in the authors of {} older scores", filled);
+                }
+            }
+            let mut scores = match (&repo, &author) {
+                (None, Some(author)) => highscores.load_by_author(author)?,
+                (repo, author) => {
+                    let mut scores = highscores.load(repo.as_ref())?;
+                    if let Some(author) = author {
+                        scores.retain(|s| s.is_by(author));
+                    }
+                    scores
+                }
+            };
+            // points at different lengths aren't comparable
+            scores.retain(|s| s.is_listed(length, &window, None));
+            if group {
+                by_author(&scores)
+                    .iter()
+                    .take(n)
+                    .for_each(|a| println!("{}", format_author(a)));
+            } else {
+                scores
+                    .iter()
+                    .take(n)
+                    .for_each(|s| println!("{}", format_score(s)));
+                let mut disqualified = highscores.load_all(repo.as_ref())?;
+                disqualified
+                    .retain(|s| s.disqualified && s.is_listed(length, &window, author.as_deref()));
+                if !disqualified.is_empty() {
+                    println!("{}", "Disqualified".bold());
+                    disqualified
+                        .iter()
+                        .take(n)
+                        .for_each(|s| println!("{}", format_score(s)));
+                }
+            }
+        }
+        Cli::Simulate {
+            length,
+            n,
+            seed,
+            z,
+            holdem,
+        } => {
+            let mut rng = match seed {
+                Some(seed) => StdRng::seed_from_u64(seed),
+                None => StdRng::from_entropy(),
+            };
+            let mut rules = config.rules;
+            if holdem {
+                rules.deal = Deal::Holdem;
+            }
+            let length = length.unwrap_or(rules.length);
+            let simulation = simulate(length, n, &rules, &mut rng)?;
+            for rule in &simulation.rules {
+                println!("{}", format_frequency(rule, z));
+            }
+            let failures = simulation.failures(z);
+            if !failures.is_empty() {
+                return Err(LottoError::SimulationFailed(
+                    failures
+                        .iter()
+                        .map(|r| format!("{} ({})", r.name, r.id))
+                        .collect(),
+                ));
+            }
+        }
+        Cli::Backfill {
+            repo,
+            since,
+            author,
+        } => {
+            let repo = Repository::discover(repo)?;
+            let mut highscores = highscores::open(&config.storage)?;
+            let summary = backfill(
+                &repo,
+                &mut highscores,
+                &config.rules,
+                &config.anti_cheat,
+                &BackfillOptions { since, author },
+            )?;
+            archive_seasons(&config, &mut highscores)?;
+            println!(
+                "Scored {} commits, {} already recorded, {} achievements unlocked",
+                summary.scored, summary.skipped, summary.unlocked
+            );
+        }
+        Cli::Score {
+            revision,
+            no_save,
+            format,
+        } => {
+            let repo = Repository::discover(".")?;
+            let commits = resolve_commits(&repo, &revision)?;
+            let mut config = config;
+            config.output.format = format.unwrap_or(config.output.format);
+            let mut output = config.output.clone();
+            if commits.len() > 1 {
+                output.delay_ms = 0;
+            }
+            match config.output.format {
+                Format::Terminal => score_commits(
+                    &repo,
+                    &commits,
+                    !no_save,
+                    &config,
+                    &TerminalOutputerImpl::from_config(output),
+                )?,
+                Format::Json => {
+                    score_commits(&repo, &commits, !no_save, &config, &JsonOutputer::new())?
+                }
+            }
+        }
+        Cli::Seasons => list_seasons(&config)?,
+        Cli::Stats {
+            repo,
+            author,
+            length,
+            format,
+        } => {
+            let repo = repo.map(|r| RepoId::discover(&r)).transpose()?;
+            let mut highscores = highscores::open(&config.storage)?;
+            let mut scores = highscores.load(repo.as_ref())?;
+            if let Some(author) = &author {
+                // only in memory, since stats never write
+                for score in scores.iter_mut().filter(|s| s.author.is_none()) {
+                    score.fill_people();
+                }
+                scores.retain(|s| s.is_by(author));
+            }
+            let length = length.unwrap_or(config.rules.length);
+            let stats = stats(&scores, length, &config.rules);
+            match format.unwrap_or(config.output.format) {
+                Format::Terminal => print_stats(&stats),
+                Format::Json => println!("{}", serde_json::to_string(&stats).unwrap_or_default()),
+            }
+        }
+        Cli::Achievements { author, format } => {
+            let mut highscores = highscores::open(&config.storage)?;
+            achievements::import_legacy(&achievements::legacy_path()?, &mut highscores)?;
+            let mut all = highscores.load_awards()?.achievements;
+            if let Some(author) = author {
+                let unlocked = all.remove(&author.to_lowercase()).unwrap_or_default();
+                all = [(author, unlocked)].into();
+            }
+            match format.unwrap_or(config.output.format) {
+                Format::Terminal => print_achievements(&all),
+                Format::Json => println!("{}", serde_json::to_string(&all).unwrap_or_default()),
+            }
+        }
+        Cli::Hook { action } => hook(action, config)?,
+        Cli::Prune { repo } => {
+            let repo = Repository::discover(repo)?;
+            let pruned = prune(&repo, &mut highscores::open(&config.storage)?)?;
+            println!("Dropped {} scores of unreachable commits", pruned);
+        }
+        Cli::Store { action } => {
+            let mut highscores = highscores::open(&config.storage)?;
+            let status = match action {
+                StoreAction::Migrate => highscores.migrate()?,
+                StoreAction::Check => highscores.check()?,
+            };
+            println!("{}", status);
+        }
+        Cli::Mine {
+            target,
+            timestamp,
+            window,
+            threads,
+            max_tries,
+        } => {
+            let options = MineOptions {
+                target,
+                nonce: if timestamp {
+                    Nonce::Timestamp { window }
+                } else {
+                    Nonce::Trailer
+                },
+                threads: threads
+                    .unwrap_or_else(|| std::thread::available_parallelism().map_or(1, |n| n.get())),
+                max_tries,
+            };
+            mine_head(&config, &options)?;
+        }
+        Cli::Sync { action, remote } => sync(action, &remote, &config)?,
+        Cli::Serve { bind } => {
+            if config.storage.backend == Backend::Remote {
+                return Err(LottoError::ConfigError(anyhow::anyhow!(
+                    "the server needs a local backend to keep scores in"
+                )));
+            }
+            let mut highscores = highscores::open(&config.storage)?;
+            let server = ScoreServer::bind(&bind, &config)?;
+            eprintln!("Serving scores on http://{}", server.addr());
+            server.run(&mut highscores)?;
         }
     }
     Ok(())
 }
 
+/// Push or pull the shared scores. With another backend configured, this
+/// repository's local scores are copied into the shared ones before pushing,
+/// and back out after pulling.
+fn sync(action: SyncAction, remote: &str, config: &Config) -> Result<(), LottoError> {
+    let repo = Repository::discover(".")?;
+    let repo_id = RepoId::for_repo(&repo)?;
+    let mut shared = RefHighScores::new(repo);
+    let mut local = match config.storage.backend {
+        Backend::Git => None,
+        _ => Some(highscores::open(&config.storage)?),
+    };
+    match action {
+        SyncAction::Push => {
+            if let Some(local) = &mut local {
+                let mut ours = Some(local.load_all(Some(&repo_id))?);
+                shared.modify(&mut |scores| {
+                    add_missing(scores, ours.take().unwrap_or_default()) > 0
+                })?;
+            }
+            shared.push(remote)?;
+            println!("Pushed scores to {}", remote);
+        }
+        SyncAction::Pull => {
+            let mut added = shared.pull(remote)?;
+            if let Some(local) = &mut local {
+                let mut theirs = Some(shared.load_all(Some(&repo_id))?);
+                local.modify(&mut |scores| {
+                    added = add_missing(scores, theirs.take().unwrap_or_default());
+                    added > 0
+                })?;
+            }
+            println!("Pulled {} new scores from {}", added, remote);
+        }
+    }
+    Ok(())
+}
+
+fn mine_head(config: &Config, options: &MineOptions) -> Result<(), LottoError> {
+    let repo = Repository::discover(".")?;
+    let head = Commit::latest()?;
+    if repo
+        .head()
+        .and_then(|h| h.peel_to_commit())
+        .is_ok_and(|c| c.header_field_bytes("gpgsig").is_ok())
+    {
+        eprintln!("{}", "Warning: mining drops HEAD's signature".yellow());
+    }
+    eprintln!(
+        "Mining for {} on {} threads",
+        options.target, options.threads
+    );
+    let Some(mined) = mine(&repo, &config.rules, options)? else {
+        println!("Nothing found in {} tries", options.max_tries);
+        return Ok(());
+    };
+    rewrite_head(&repo, &mined)?;
+    let commit = Commit::latest()?;
+    println!(
+        "Rewrote {} as {} after {} tries",
+        head.hash, commit.hash, mined.tries
+    );
+    let result = score(&commit, &config.rules);
+    let output = TerminalOutputerImpl::from_config(config.output.clone());
+    output.result(&result);
+    output.finished();
+
+    let mut new = ScoreInfo::new(&result, &commit);
+    new.mined = true;
+    let repo_id = RepoId::for_repo(&repo)?;
+    highscores::open(&config.storage)?.modify(&mut |scores| {
+        // the original keeps counting, however well the mined commit did
+        if !apply(
+            scores,
+            &repo_id,
+            &head.full_hash,
+            new.clone(),
+            RewritePolicy::ForbidRerolls,
+        ) {
+            scores.push(new.clone());
+        }
+        true
+    })
+}
+
+fn score_commits(
+    repo: &Repository,
+    commits: &[Commit],
+    save: bool,
+    config: &Config,
+    output: &impl TerminalOutputer,
+) -> Result<(), LottoError> {
+    let mut highscores = highscores::open(&config.storage)?;
+    let mut scores = if save {
+        highscores.load_all(Some(&RepoId::for_repo(repo)?))?
+    } else {
+        vec![]
+    };
+    let recorded: HashSet<String> = scores.iter().map(|s| s.commit.clone()).collect();
+    let already = scores.len();
+
+    for commit in commits {
+        if config.output.format == Format::Terminal {
+            println!(
+                "{} {}",
+                commit.hash.bold(),
+                commit.summary.as_deref().unwrap_or_default()
+            );
+        }
+        let result = score(commit, &config.rules);
+        output.result(&result);
+        output.finished();
+        if save && !recorded.contains(&commit.hash) {
+            let mut new_score = ScoreInfo::new(&result, commit);
+            anticheat::check(&mut new_score, commit, &scores, &config.anti_cheat);
+            scores.push(new_score);
+        }
+    }
+    let new_scores = scores.split_off(already);
+    if new_scores.is_empty() {
+        return Ok(());
+    }
+    highscores.save_all(new_scores.clone())?;
+    for unlocked in achievements::unlock(&mut highscores, &new_scores)? {
+        output.achievement(&unlocked);
+    }
+    archive_seasons(config, &mut highscores)
+}
+
+/// Archive the seasons that have ended, now that there are new scores.
+fn archive_seasons(config: &Config, highscores: &mut dyn HighScores) -> Result<(), LottoError> {
+    let today = local_date(Utc::now().timestamp());
+    seasons::archive_ended(&config.seasons, highscores, config.rules.length, today)?;
+    Ok(())
+}
+
+fn hook(action: HookAction, config: Config) -> Result<(), LottoError> {
+    match action {
+        HookAction::Install => {
+            let program = env::current_exe()?;
+            for kind in HookKind::ALL {
+                let hook = Hook::discover(kind)?;
+                hook.install(&program)?;
+                println!("Installed {}", hook.path().display());
+            }
+        }
+        HookAction::Uninstall => {
+            for kind in HookKind::ALL {
+                let hook = Hook::discover(kind)?;
+                if hook.uninstall()? {
+                    println!("Removed {}", hook.path().display());
+                } else {
+                    println!("No hook installed at {}", hook.path().display());
+                }
+            }
+        }
+        HookAction::Status => {
+            for kind in HookKind::ALL {
+                let hook = Hook::discover(kind)?;
+                let status = match hook.status() {
+                    HookStatus::NotInstalled => "not installed".to_string(),
+                    HookStatus::Installed { chained: false } => "installed".to_string(),
+                    HookStatus::Installed { chained: true } => {
+                        "installed, running the previous hook first".to_string()
+                    }
+                    HookStatus::Foreign => {
+                        format!("another {} hook is installed", hook.kind().name())
+                    }
+                };
+                println!("{}: {}", hook.path().display(), status);
+            }
+        }
+        HookAction::Rewritten { .. } => {
+            let mut input = String::new();
+            stdin().read_to_string(&mut input)?;
+            // only asking git when libgit2 fails, since this runs on every
+            // amend and rebase
+            let repo = match Repository::discover(".") {
+                Ok(repo) => repo,
+                // libgit2 can't read the rewritten commits
+                Err(_) if ObjectFormat::detect(Path::new("."))? == ObjectFormat::Sha256 => {
+                    return Ok(());
+                }
+                Err(e) => return Err(e.into()),
+            };
+            let mut highscores = highscores::open(&config.storage)?;
+            rewritten(
+                &repo,
+                &mut highscores,
+                &config.rules,
+                &config.anti_cheat,
+                config.storage.rewrites,
+                &parse_rewrites(&input),
+            )?;
+        }
+        HookAction::Run => {
+            if env::var_os(WRAPPED_ENV).is_some() {
+                return Ok(());
+            }
+            let mut output = config.output;
+            if !stdout().is_terminal() {
+                output.delay_ms = 0;
+            }
+            let mut highscores = highscores::open(&config.storage)?;
+            let commit = Commit::latest()?;
+            match output.format {
+                Format::Terminal => record(
+                    &TerminalOutputerImpl::from_config(output),
+                    &mut highscores,
+                    &config.rules,
+                    &config.anti_cheat,
+                    &config.seasons,
+                    &commit,
+                )?,
+                Format::Json => record(
+                    &JsonOutputer::new(),
+                    &mut highscores,
+                    &config.rules,
+                    &config.anti_cheat,
+                    &config.seasons,
+                    &commit,
+                )?,
+            }
+        }
+    }
+    Ok(())
+}
+
+fn parse_date(date: &str) -> Result<i64, String> {
+    parse_day(date).map(midnight)
+}
+
+fn parse_day(date: &str) -> Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(date, "%Y-%m-%d").map_err(|e| e.to_string())
+}
+
+/// The season with this name, or the current one if the name is empty.
+fn find_season<'a>(
+    seasons: &'a [SeasonConfig],
+    name: &str,
+) -> Result<&'a SeasonConfig, LottoError> {
+    let season = match name {
+        "" => season_at(seasons, Utc::now().timestamp()),
+        name => seasons.iter().find(|s| s.name == name),
+    };
+    season.ok_or_else(|| {
+        LottoError::ConfigError(match name {
+            "" => anyhow::anyhow!("no season is running"),
+            name => anyhow::anyhow!("no season is called {}", name),
+        })
+    })
+}
+
+fn list_seasons(config: &Config) -> Result<(), LottoError> {
+    let mut highscores = highscores::open(&config.storage)?;
+    seasons::import_legacy(&seasons::legacy_path()?, &mut highscores)?;
+    let today = local_date(Utc::now().timestamp());
+    seasons::archive_ended(&config.seasons, &mut highscores, config.rules.length, today)?;
+    let print_winners = |winners: &[ScoreInfo]| {
+        for (i, score) in winners.iter().enumerate() {
+            println!("    {}. {}", i + 1, format_score(score));
+        }
+    };
+    for season in highscores.load_awards()?.seasons {
+        println!(
+            "{} ({} to {}), ended",
+            season.name, season.start, season.end
+        );
+        print_winners(&season.winners);
+    }
+    let scores = highscores.load(None)?;
+    for season in config.seasons.iter().filter(|s| s.end >= today) {
+        if season.start > today {
+            println!(
+                "{} ({} to {}), upcoming",
+                season.name, season.start, season.end
+            );
+        } else {
+            println!(
+                "{} ({} to {}), running",
+                season.name, season.start, season.end
+            );
+            print_winners(&winners(&scores, season, config.rules.length));
+        }
+    }
+    Ok(())
+}
+
+fn print_achievements(all: &BTreeMap<String, Vec<Unlocked>>) {
+    if all.is_empty() {
+        println!("No achievements unlocked yet");
+    }
+    for (author, unlocked) in all {
+        println!(
+            "{}: {} of {}",
+            author.bold(),
+            unlocked.len(),
+            Achievement::ALL.len()
+        );
+        for achievement in Achievement::ALL {
+            match unlocked.iter().find(|u| u.achievement == achievement) {
+                Some(u) => println!(
+                    "    [x] {} - {} ({} on {})",
+                    achievement.title().bold(),
+                    achievement.description(),
+                    u.commit,
+                    local_date(u.date)
+                ),
+                None => println!(
+                    "    [ ] {} - {}",
+                    achievement.title(),
+                    achievement.description()
+                ),
+            }
+        }
+    }
+}
+
+fn print_stats(stats: &Stats) {
+    println!(
+        "{} commits scored at length {}",
+        stats.commits.to_string().bold(),
+        stats.length
+    );
+    if stats.commits == 0 {
+        return;
+    }
+    println!(
+        "Points: {} total, {:.1} mean, {} median",
+        stats.total, stats.mean, stats.median
+    );
+    if let Some(rarest) = &stats.rarest {
+        println!("Rarest hand: {}", format_score(rarest));
+    }
+    if let Some(dry_spell) = &stats.dry_spell {
+        println!(
+            "Longest dry spell: {} commits without points, {} to {}",
+            dry_spell.commits,
+            local_date(dry_spell.from),
+            local_date(dry_spell.until)
+        );
+    }
+    println!("Rule hits:");
+    for rule in &stats.rules {
+        let expected = match rule.theoretical {
+            Some(p) => format!("expected {:.2}%", p * 100.0),
+            None => "expected rate unknown".into(),
+        };
+        println!(
+            "    {}: {} ({:.2}%), {}",
+            rule.name,
+            rule.hits,
+            rule.observed * 100.0,
+            expected
+        );
+    }
+}
+
+fn format_frequency(rule: &RuleFrequency, z: f64) -> String {
+    let (low, high) = rule.interval(z);
+    format!(
+        "{} ({}): observed {:.6} [{:.6}, {:.6}] from {} hits, claimed {:.6} {}",
+        rule.name,
+        rule.id,
+        rule.observed(),
+        low,
+        high,
+        rule.hits,
+        rule.claimed,
+        if rule.within_tolerance(z) {
+            "ok"
+        } else {
+            "FAILED"
+        }
+    )
+}
+
+fn format_author(stats: &AuthorStats) -> String {
+    format!(
+        "{}: best {} points ({}), {} points from {} commits",
+        stats.author, stats.best.score, stats.best.commit, stats.total, stats.commits
+    )
+}
+
 fn format_score(score: &ScoreInfo) -> String {
-    let rules = score
-        .rules
-        .iter()
-        .map(|r| r.to_string())
-        .collect::<Vec<String>>()
-        .join(", ");
+    let rules = score.rule_names();
     let datetime = match Local.timestamp_opt(score.date, 0) {
         LocalResult::Single(dt) => dt.format("%Y-%m-%d %H:%M:%S %z"),
         LocalResult::Ambiguous(dt, _) => dt.format("%Y-%m-%d %H:%M:%S %z"),
@@ -47,8 +851,17 @@ fn format_score(score: &ScoreInfo) -> String {
             .unwrap()
             .format("%Y-%m-%d %H:%M:%S %z"),
     };
-    format!(
+    let mut line = format!(
         "{}: {} points ({}) scored on {}",
         score.commit, score.score, rules, datetime
-    )
+    );
+    if !score.flags.is_empty() {
+        let flags: Vec<String> = score.flags.iter().map(|f| f.to_string()).collect();
+        line.push_str(
+            &format!(" [flagged: {}]", flags.join(", "))
+                .yellow()
+                .to_string(),
+        );
+    }
+    line
 }
@@ -0,0 +1,335 @@
+use std::fmt;
+
+use num_bigint::BigUint;
+use num_traits::ToPrimitive;
+use serde::{Deserialize, Serialize};
+
+const RANKS: &[u8; 13] = b"23456789TJQKA";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Suit {
+    Clubs,
+    Diamonds,
+    Hearts,
+    Spades,
+}
+
+impl Suit {
+    const ALL: [Suit; 4] = [Suit::Clubs, Suit::Diamonds, Suit::Hearts, Suit::Spades];
+
+    pub fn is_red(&self) -> bool {
+        matches!(self, Suit::Diamonds | Suit::Hearts)
+    }
+
+    fn symbol(&self) -> char {
+        match self {
+            Suit::Clubs => '♣',
+            Suit::Diamonds => '♦',
+            Suit::Hearts => '♥',
+            Suit::Spades => '♠',
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Card {
+    /// 2 to 14, aces high.
+    pub rank: u8,
+    pub suit: Suit,
+}
+
+impl fmt::Display for Card {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}{}",
+            RANKS[self.rank as usize - 2] as char,
+            self.suit.symbol()
+        )
+    }
+}
+
+/// How many cards are dealt from the hash.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Deal {
+    FiveCard,
+    /// Seven cards, of which the best five count.
+    Holdem,
+}
+
+impl Deal {
+    pub fn cards(&self) -> usize {
+        match self {
+            Deal::FiveCard => 5,
+            Deal::Holdem => 7,
+        }
+    }
+
+    /// The number of different deals.
+    pub fn hands(&self) -> u64 {
+        match self {
+            Deal::FiveCard => 2_598_960,
+            Deal::Holdem => 133_784_560,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum HandRank {
+    HighCard,
+    Pair,
+    TwoPair,
+    ThreeOfAKind,
+    Straight,
+    Flush,
+    FullHouse,
+    FourOfAKind,
+    StraightFlush,
+    RoyalFlush,
+}
+
+impl HandRank {
+    pub const ALL: [HandRank; 10] = [
+        HandRank::HighCard,
+        HandRank::Pair,
+        HandRank::TwoPair,
+        HandRank::ThreeOfAKind,
+        HandRank::Straight,
+        HandRank::Flush,
+        HandRank::FullHouse,
+        HandRank::FourOfAKind,
+        HandRank::StraightFlush,
+        HandRank::RoyalFlush,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            HandRank::HighCard => "High card",
+            HandRank::Pair => "Pair",
+            HandRank::TwoPair => "Two pair",
+            HandRank::ThreeOfAKind => "Three of a kind",
+            HandRank::Straight => "Straight",
+            HandRank::Flush => "Flush",
+            HandRank::FullHouse => "Full house",
+            HandRank::FourOfAKind => "Four of a kind",
+            HandRank::StraightFlush => "Straight flush",
+            HandRank::RoyalFlush => "Royal flush",
+        }
+    }
+
+    /// The number of deals whose best hand has this rank.
+    pub fn count(&self, deal: Deal) -> u64 {
+        match deal {
+            Deal::FiveCard => match self {
+                HandRank::HighCard => 1_302_540,
+                HandRank::Pair => 1_098_240,
+                HandRank::TwoPair => 123_552,
+                HandRank::ThreeOfAKind => 54_912,
+                HandRank::Straight => 10_200,
+                HandRank::Flush => 5_108,
+                HandRank::FullHouse => 3_744,
+                HandRank::FourOfAKind => 624,
+                HandRank::StraightFlush => 36,
+                HandRank::RoyalFlush => 4,
+            },
+            Deal::Holdem => match self {
+                HandRank::HighCard => 23_294_460,
+                HandRank::Pair => 58_627_800,
+                HandRank::TwoPair => 31_433_400,
+                HandRank::ThreeOfAKind => 6_461_620,
+                HandRank::Straight => 6_180_020,
+                HandRank::Flush => 4_047_644,
+                HandRank::FullHouse => 3_473_184,
+                HandRank::FourOfAKind => 224_848,
+                HandRank::StraightFlush => 37_260,
+                HandRank::RoyalFlush => 4_324,
+            },
+        }
+    }
+
+    pub fn probability(&self, deal: Deal) -> f64 {
+        self.count(deal) as f64 / deal.hands() as f64
+    }
+}
+
+impl fmt::Display for HandRank {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+fn deck() -> Vec<Card> {
+    Suit::ALL
+        .iter()
+        .flat_map(|suit| (2..=14).map(move |rank| Card { rank, suit: *suit }))
+        .collect()
+}
+
+/// Deal cards from a full hex hash without replacement.
+///
+/// The hash is read as one big number and used as a mixed radix index into
+/// the possible deals, so every card uses all of the hash.
+pub fn deal(full_hash: &str, deal: Deal) -> Option<Vec<Card>> {
+    let mut index = BigUint::parse_bytes(full_hash.as_bytes(), 16)?;
+    let mut deck = deck();
+    let mut cards = vec![];
+    for _ in 0..deal.cards() {
+        let remaining = BigUint::from(deck.len());
+        let card = (&index % &remaining).to_usize()?;
+        index /= remaining;
+        cards.push(deck.remove(card));
+    }
+    Some(cards)
+}
+
+/// The best five card hand that can be made from the cards.
+pub fn best_hand(cards: &[Card]) -> (HandRank, Vec<Card>) {
+    let mut best: Option<(Score, Vec<Card>)> = None;
+    for hand in combinations(cards, 5) {
+        let score = evaluate(&hand);
+        if best.as_ref().is_none_or(|(b, _)| score > *b) {
+            best = Some((score, hand));
+        }
+    }
+    let ((rank, _), hand) = best.expect("at least five cards are needed for a hand");
+    (rank, hand)
+}
+
+/// The rank of a hand, along with the card ranks used to break ties.
+type Score = (HandRank, Vec<u8>);
+
+fn evaluate(hand: &[Card]) -> Score {
+    let mut counts = [0usize; 15];
+    for card in hand {
+        counts[card.rank as usize] += 1;
+    }
+    let mut groups: Vec<(usize, u8)> = (2..=14u8)
+        .filter(|r| counts[*r as usize] > 0)
+        .map(|r| (counts[r as usize], r))
+        .collect();
+    groups.sort_by(|a, b| b.cmp(a));
+    let kickers: Vec<u8> = groups.iter().map(|(_, r)| *r).collect();
+
+    let flush = hand.iter().all(|c| c.suit == hand[0].suit);
+    let straight = if groups.len() == 5 && kickers[0] - kickers[4] == 4 {
+        Some(kickers[0])
+    } else if kickers == [14, 5, 4, 3, 2] {
+        Some(5)
+    } else {
+        None
+    };
+
+    let rank = match (straight, flush, groups[0].0, groups.get(1).map(|g| g.0)) {
+        (Some(14), true, _, _) => HandRank::RoyalFlush,
+        (Some(_), true, _, _) => HandRank::StraightFlush,
+        (_, _, 4, _) => HandRank::FourOfAKind,
+        (_, _, 3, Some(2)) => HandRank::FullHouse,
+        (_, true, _, _) => HandRank::Flush,
+        (Some(_), _, _, _) => HandRank::Straight,
+        (_, _, 3, _) => HandRank::ThreeOfAKind,
+        (_, _, 2, Some(2)) => HandRank::TwoPair,
+        (_, _, 2, _) => HandRank::Pair,
+        _ => HandRank::HighCard,
+    };
+    match straight {
+        Some(high) => (rank, vec![high]),
+        None => (rank, kickers),
+    }
+}
+
+fn combinations(cards: &[Card], k: usize) -> Vec<Vec<Card>> {
+    if k == 0 {
+        return vec![vec![]];
+    }
+    if cards.len() < k || cards.is_empty() {
+        return vec![];
+    }
+    let mut with_first: Vec<Vec<Card>> = combinations(&cards[1..], k - 1)
+        .into_iter()
+        .map(|mut rest| {
+            rest.insert(0, cards[0]);
+            rest
+        })
+        .collect();
+    with_first.extend(combinations(&cards[1..], k));
+    with_first
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn cards(hand: &str) -> Vec<Card> {
+        hand.split(' ')
+            .map(|c| {
+                let mut chars = c.chars();
+                let rank = chars.next().unwrap();
+                let suit = match chars.next().unwrap() {
+                    'c' => Suit::Clubs,
+                    'd' => Suit::Diamonds,
+                    'h' => Suit::Hearts,
+                    _ => Suit::Spades,
+                };
+                Card {
+                    rank: RANKS.iter().position(|r| *r as char == rank).unwrap() as u8 + 2,
+                    suit,
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_evaluate() {
+        assert_eq!(best_hand(&cards("Ts Js Qs Ks As")).0, HandRank::RoyalFlush);
+        assert_eq!(best_hand(&cards("Ah 2s 3s 4d 5c")).0, HandRank::Straight);
+        assert_eq!(best_hand(&cards("Qh Ks As 2d 3c")).0, HandRank::HighCard);
+        assert_eq!(best_hand(&cards("9h 9s 9d 2d 2c")).0, HandRank::FullHouse);
+        assert_eq!(best_hand(&cards("9h 9s 4d 2d 2c")).0, HandRank::TwoPair);
+    }
+
+    #[test]
+    fn test_best_of_seven() {
+        let (rank, hand) = best_hand(&cards("2h 7h 9s 9h Kh 9d Ah"));
+        assert_eq!(rank, HandRank::Flush);
+        assert_eq!(hand, cards("2h 7h 9h Kh Ah"));
+        let (rank, hand) = best_hand(&cards("2h 2s 9s 9h Kh 9d Kd"));
+        assert_eq!(rank, HandRank::FullHouse);
+        assert_eq!(hand, cards("9s 9h Kh 9d Kd"));
+    }
+
+    #[test]
+    fn test_deal() {
+        let hash = "a1d218bf111892cef34f6a94399fabf7de6fa502";
+        let hand = deal(hash, Deal::Holdem).unwrap();
+        assert_eq!(hand.len(), 7);
+        assert_eq!(hand[..5], deal(hash, Deal::FiveCard).unwrap()[..]);
+        let mut unique = hand.clone();
+        unique.sort();
+        unique.dedup();
+        assert_eq!(unique.len(), 7);
+        assert!(deal("not hex", Deal::FiveCard).is_none());
+    }
+
+    #[test]
+    fn test_counts() {
+        for deal in [Deal::FiveCard, Deal::Holdem] {
+            let total: u64 = [
+                HandRank::HighCard,
+                HandRank::Pair,
+                HandRank::TwoPair,
+                HandRank::ThreeOfAKind,
+                HandRank::Straight,
+                HandRank::Flush,
+                HandRank::FullHouse,
+                HandRank::FourOfAKind,
+                HandRank::StraightFlush,
+                HandRank::RoyalFlush,
+            ]
+            .iter()
+            .map(|r| r.count(deal))
+            .sum();
+            assert_eq!(total, deal.hands());
+        }
+    }
+}
@@ -0,0 +1,319 @@
+use std::{
+    collections::BTreeMap,
+    fmt,
+    sync::{Mutex, OnceLock},
+};
+
+use num_bigint::BigInt;
+use num_integer::binomial;
+use num_rational::BigRational;
+use num_traits::{cast::ToPrimitive, One, Pow, Zero};
+
+use crate::cards::{Deal, HandRank};
+
+const SYMBOLS: usize = 16;
+const LETTERS: u32 = 0b1111_1100_0000_0000;
+const NUMBERS: u32 = 0b0000_0011_1111_1111;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum FlushKind {
+    Letters,
+    Numbers,
+}
+
+/// The constraints a hash has to satisfy to count as a given hand.
+///
+/// Every rule that fires on a hash adds its own constraint, so the probability
+/// of a hand is the exact probability of all of those rules firing together
+/// over all 16^length hashes.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Hand {
+    pub length: usize,
+    /// How many times each distinct character appears, largest first.
+    pub kinds: Option<Vec<usize>>,
+    pub flush: Option<FlushKind>,
+    /// Length of the longest run of consecutive characters.
+    pub straight: Option<usize>,
+    /// The poker hand dealt from the full hash.
+    pub cards: Option<(Deal, HandRank)>,
+}
+
+impl Hand {
+    pub fn new(length: usize) -> Self {
+        Hand {
+            length,
+            kinds: None,
+            flush: None,
+            straight: None,
+            cards: None,
+        }
+    }
+
+    /// Constrain the hand to the given repeated characters, every other
+    /// character appearing only once.
+    pub fn with_kinds(mut self, repeated: impl IntoIterator<Item = usize>) -> Self {
+        let mut kinds: Vec<usize> = repeated.into_iter().collect();
+        let singles = self.length.saturating_sub(kinds.iter().sum());
+        kinds.extend(std::iter::repeat_n(1, singles));
+        kinds.sort_by(|a, b| b.cmp(a));
+        self.kinds = Some(kinds);
+        self
+    }
+
+    pub fn with_flush(mut self, flush: FlushKind) -> Self {
+        self.flush = Some(flush);
+        self
+    }
+
+    pub fn with_straight(mut self, run: usize) -> Self {
+        self.straight = Some(run);
+        self
+    }
+
+    pub fn with_cards(mut self, deal: Deal, rank: HandRank) -> Self {
+        self.cards = Some((deal, rank));
+        self
+    }
+
+    /// The number of hashes of this length that make up the hand.
+    pub fn count(&self) -> BigInt {
+        let subsets = subsets();
+        (1..=SYMBOLS.min(self.length))
+            .map(|k| {
+                let strings = match &self.kinds {
+                    Some(kinds) if kinds.len() == k => arrangements(kinds),
+                    Some(_) => return BigInt::zero(),
+                    None => surjections(self.length, k),
+                };
+                strings * subsets.count(k, self.flush, self.straight)
+            })
+            .sum()
+    }
+
+    /// The cards are taken to be independent of the characters of the short
+    /// hash. They are dealt from the full hash modulo the number of ordered
+    /// deals, fewer than 52^7, and fixing the first `length` of a SHA-1 hash's
+    /// 40 characters leaves 16^(40 - length) equally likely values for the
+    /// rest. So the chance of any hand is off by at most
+    /// 52^7 / 16^(40 - length), under 10^-24 for the lengths up to
+    /// [`MAX_LENGTH`](crate::config::MAX_LENGTH) that can be scored.
+    pub fn exact_probability(&self) -> BigRational {
+        let probability =
+            BigRational::new(self.count(), BigInt::from(SYMBOLS).pow(self.length as u32));
+        match self.cards {
+            Some((deal, rank)) => {
+                probability
+                    * BigRational::new(BigInt::from(rank.count(deal)), BigInt::from(deal.hands()))
+            }
+            None => probability,
+        }
+    }
+
+    /// The probability of the hand, cached per hash length.
+    pub fn probability(&self) -> f64 {
+        static CACHE: Mutex<BTreeMap<usize, BTreeMap<Hand, f64>>> = Mutex::new(BTreeMap::new());
+
+        let mut cache = CACHE.lock().unwrap();
+        let by_length = cache.entry(self.length).or_default();
+        if let Some(probability) = by_length.get(self) {
+            return *probability;
+        }
+        let probability = self.exact_probability().to_f64().unwrap();
+        by_length.insert(self.clone(), probability);
+        probability
+    }
+}
+
+impl fmt::Display for Hand {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut parts = vec![];
+        if let Some(kinds) = &self.kinds {
+            let repeated: Vec<String> = kinds
+                .iter()
+                .filter(|k| **k > 1)
+                .map(|k| k.to_string())
+                .collect();
+            parts.push(format!("kinds {}", repeated.join("+")));
+        }
+        match self.flush {
+            Some(FlushKind::Letters) => parts.push("all letters".into()),
+            Some(FlushKind::Numbers) => parts.push("all numbers".into()),
+            None => {}
+        }
+        if let Some(run) = self.straight {
+            parts.push(format!("run of {}", run));
+        }
+        if let Some((_, rank)) = self.cards {
+            parts.push(rank.name().to_lowercase());
+        }
+        if parts.is_empty() {
+            parts.push("anything".into());
+        }
+        write!(f, "{}", parts.join(", "))
+    }
+}
+
+/// Number of strings using every one of k fixed characters, where the counts
+/// of the characters are given by `kinds`.
+fn arrangements(kinds: &[usize]) -> BigInt {
+    let length: usize = kinds.iter().sum();
+    let mut strings = factorial(length);
+    for kind in kinds {
+        strings /= factorial(*kind);
+    }
+    // characters with the same count can be swapped without changing the hand
+    let mut assignments = factorial(kinds.len());
+    let mut same = kinds.iter().fold(BTreeMap::new(), |mut same, kind| {
+        *same.entry(kind).or_insert(0) += 1;
+        same
+    });
+    for count in same.values_mut() {
+        assignments /= factorial(*count);
+    }
+    strings * assignments
+}
+
+/// Number of strings of the given length using every one of k fixed characters.
+fn surjections(length: usize, k: usize) -> BigInt {
+    (0..=k)
+        .map(|j| {
+            let term =
+                binomial(BigInt::from(k), BigInt::from(j)) * BigInt::from(k - j).pow(length as u32);
+            if j % 2 == 0 {
+                term
+            } else {
+                -term
+            }
+        })
+        .sum()
+}
+
+fn factorial(n: usize) -> BigInt {
+    (1..=n).fold(BigInt::one(), |acc, i| acc * i)
+}
+
+/// Counts of the sets of hex characters, by size, flush and longest run.
+struct Subsets {
+    counts: Vec<[[u64; SYMBOLS + 1]; 3]>,
+}
+
+impl Subsets {
+    fn build() -> Self {
+        let mut counts = vec![[[0; SYMBOLS + 1]; 3]; SYMBOLS + 1];
+        for set in 1u32..(1 << SYMBOLS) {
+            let flush = if set & !LETTERS == 0 {
+                0
+            } else if set & !NUMBERS == 0 {
+                1
+            } else {
+                2
+            };
+            counts[set.count_ones() as usize][flush][longest_run(set)] += 1;
+        }
+        Subsets { counts }
+    }
+
+    fn count(&self, k: usize, flush: Option<FlushKind>, straight: Option<usize>) -> u64 {
+        let flushes = match flush {
+            Some(FlushKind::Letters) => 0..1,
+            Some(FlushKind::Numbers) => 1..2,
+            None => 0..3,
+        };
+        let runs = match straight {
+            Some(run) if run <= SYMBOLS => run..run + 1,
+            Some(_) => 0..0,
+            None => 0..SYMBOLS + 1,
+        };
+        flushes
+            .flat_map(|f| runs.clone().map(move |r| (f, r)))
+            .map(|(f, r)| self.counts[k][f][r])
+            .sum()
+    }
+}
+
+fn subsets() -> &'static Subsets {
+    static SUBSETS: OnceLock<Subsets> = OnceLock::new();
+    SUBSETS.get_or_init(Subsets::build)
+}
+
+fn longest_run(set: u32) -> usize {
+    let mut set = set;
+    let mut run = 0;
+    while set != 0 {
+        set &= set << 1;
+        run += 1;
+    }
+    run
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{config::MAX_LENGTH, git::ObjectFormat};
+
+    fn all_hashes(length: usize) -> impl Iterator<Item = String> {
+        (0..SYMBOLS.pow(length as u32)).map(move |i| format!("{:0width$x}", i, width = length))
+    }
+
+    #[test]
+    fn test_cards_independent_of_short_hash() {
+        let deals = 52f64.powi(7);
+        let rest = 16f64.powi((ObjectFormat::Sha1.hex_len() - MAX_LENGTH) as i32);
+        assert!(deals / rest < 1e-24);
+    }
+
+    #[test]
+    fn test_counts_partition_all_hashes() {
+        let mut by_kinds = BTreeMap::new();
+        for hash in all_hashes(4) {
+            let kinds = hash.chars().fold(BTreeMap::new(), |mut kinds, c| {
+                *kinds.entry(c).or_insert(0) += 1;
+                kinds
+            });
+            let repeated: Vec<usize> = kinds.into_values().filter(|v| *v > 1).collect();
+            *by_kinds
+                .entry(Hand::new(4).with_kinds(repeated))
+                .or_insert(0u64) += 1;
+        }
+        for (hand, count) in &by_kinds {
+            assert_eq!(hand.count(), BigInt::from(*count), "{:?}", hand);
+        }
+        assert_eq!(by_kinds.values().sum::<u64>(), 16u64.pow(4));
+    }
+
+    #[test]
+    fn test_joint_counts() {
+        let letters = all_hashes(4)
+            .filter(|h| h.chars().all(|c| c.is_ascii_alphabetic()))
+            .count();
+        assert_eq!(
+            Hand::new(4).with_flush(FlushKind::Letters).count(),
+            BigInt::from(letters)
+        );
+
+        let pair_of_numbers_with_run_of_three = all_hashes(4)
+            .filter(|h| h.chars().all(|c| c.is_ascii_digit()))
+            .filter(|h| {
+                let mut chars: Vec<u32> = h.chars().map(|c| c.to_digit(16).unwrap()).collect();
+                chars.sort();
+                chars.dedup();
+                chars.len() == 3 && chars[2] - chars[0] == 2
+            })
+            .count();
+        let hand = Hand::new(4)
+            .with_kinds([2])
+            .with_flush(FlushKind::Numbers)
+            .with_straight(3);
+        assert_eq!(
+            hand.count(),
+            BigInt::from(pair_of_numbers_with_run_of_three)
+        );
+    }
+
+    #[test]
+    fn test_longest_run() {
+        assert_eq!(longest_run(0), 0);
+        assert_eq!(longest_run(0b1011_1001), 3);
+        assert_eq!(longest_run(0xffff), 16);
+    }
+}
@@ -0,0 +1,334 @@
+use std::{
+    io::Write,
+    process::{Command, Stdio},
+};
+
+use git2::{Config, Oid, Repository, RepositoryState, Signature};
+
+use crate::errors::LottoError;
+
+/// The `git commit` arguments that can be committed in-process. Anything
+/// else, including opening an editor for the message, needs git itself.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CommitOptions {
+    pub message: String,
+    /// Stage every change to a tracked file first, like `-a`.
+    pub all: bool,
+    pub allow_empty: bool,
+    /// Set by `-S` or `--no-gpg-sign`, otherwise `commit.gpgsign` decides.
+    pub sign: Option<bool>,
+    pub signing_key: Option<String>,
+}
+
+impl CommitOptions {
+    /// Parse `git commit` arguments, or `None` if git has to handle them.
+    pub fn parse(args: &[String]) -> Option<Self> {
+        let mut options = CommitOptions::default();
+        let mut messages = vec![];
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--all" => options.all = true,
+                "--allow-empty" => options.allow_empty = true,
+                "--quiet" => {}
+                "--message" => messages.push(args.next()?.clone()),
+                "--gpg-sign" => options.sign = Some(true),
+                "--no-gpg-sign" => options.sign = Some(false),
+                long if long.starts_with("--") => {
+                    if let Some(message) = long.strip_prefix("--message=") {
+                        messages.push(message.to_string());
+                    } else if let Some(key) = long.strip_prefix("--gpg-sign=") {
+                        options.sign = Some(true);
+                        options.signing_key = Some(key.to_string());
+                    } else {
+                        return None;
+                    }
+                }
+                short if short.starts_with('-') && short.len() > 1 => {
+                    // clusters like -am "message"
+                    let flags = &short[1..];
+                    for (i, flag) in flags.char_indices() {
+                        let rest = &flags[i + 1..];
+                        match flag {
+                            'a' => options.all = true,
+                            'q' => {}
+                            'm' if rest.is_empty() => messages.push(args.next()?.clone()),
+                            'm' => {
+                                messages.push(rest.to_string());
+                                break;
+                            }
+                            'S' => {
+                                options.sign = Some(true);
+                                options.signing_key =
+                                    Some(rest.to_string()).filter(|k| !k.is_empty());
+                                break;
+                            }
+                            _ => return None,
+                        }
+                    }
+                }
+                _ => return None,
+            }
+        }
+        if messages.is_empty() {
+            return None;
+        }
+        options.message = messages.join("\n\n");
+        Some(options)
+    }
+}
+
+/// Commit the index on top of HEAD with libgit2, returning the new commit.
+/// Unlike `git commit` this doesn't run the pre-commit or commit-msg hooks.
+/// Returns `None` if a merge, cherry-pick, rebase or the like is in progress,
+/// since only git knows how to finish those.
+pub fn commit_in_process(
+    repo: &Repository,
+    options: &CommitOptions,
+) -> Result<Option<Oid>, LottoError> {
+    if repo.state() != RepositoryState::Clean {
+        return Ok(None);
+    }
+    let git = LottoError::Git;
+    let mut index = repo.index().map_err(git)?;
+    if options.all {
+        index.update_all(["*"], None).map_err(git)?;
+        index.write().map_err(git)?;
+    }
+    let tree = repo
+        .find_tree(index.write_tree().map_err(git)?)
+        .map_err(git)?;
+    let parent = match repo.head() {
+        Ok(head) => Some(head.peel_to_commit().map_err(git)?),
+        Err(e) if e.code() == git2::ErrorCode::UnbornBranch => None,
+        Err(e) => return Err(LottoError::Git(e)),
+    };
+    if !options.allow_empty && parent.as_ref().map(|p| p.tree_id()) == Some(tree.id()) {
+        return Err(LottoError::NothingToCommit);
+    }
+
+    let config = repo.config().map_err(git)?;
+    let author = signature(repo, "AUTHOR")?;
+    let committer = signature(repo, "COMMITTER")?;
+    // `git commit -m` keeps lines starting with '#'
+    let message = git2::message_prettify(&options.message, None).map_err(git)?;
+    let parents: Vec<&git2::Commit> = parent.iter().collect();
+    let sign = options
+        .sign
+        .unwrap_or_else(|| config.get_bool("commit.gpgsign").unwrap_or(false));
+    let reflog = format!(
+        "commit{}: {}",
+        if parent.is_none() { " (initial)" } else { "" },
+        message.lines().next().unwrap_or_default()
+    );
+    if !sign {
+        return repo
+            .commit(Some("HEAD"), &author, &committer, &message, &tree, &parents)
+            .map(Some)
+            .map_err(git);
+    }
+
+    let buffer = repo
+        .commit_create_buffer(&author, &committer, &message, &tree, &parents)
+        .map_err(git)?;
+    let buffer = buffer.as_str().ok_or(LottoError::GitFailed)?;
+    let key = match &options.signing_key {
+        Some(key) => key.clone(),
+        None => config
+            .get_string("user.signingkey")
+            .unwrap_or_else(|_| committer.to_string()),
+    };
+    let signature = sign_buffer(&config, buffer, &key)?;
+    let oid = repo.commit_signed(buffer, &signature, None).map_err(git)?;
+    // commit_signed doesn't move HEAD
+    let head = repo.find_reference("HEAD").map_err(git)?;
+    match head.symbolic_target() {
+        Some(branch) => {
+            repo.reference(branch, oid, true, &reflog).map_err(git)?;
+        }
+        None => repo.set_head_detached(oid).map_err(git)?,
+    }
+    Ok(Some(oid))
+}
+
+/// The configured identity, overridden by `GIT_AUTHOR_*` or `GIT_COMMITTER_*`
+/// like git does.
+fn signature(repo: &Repository, role: &str) -> Result<Signature<'static>, LottoError> {
+    let default = repo.signature()?;
+    let var = |field: &str| std::env::var(format!("GIT_{}_{}", role, field)).ok();
+    match (var("NAME"), var("EMAIL")) {
+        (None, None) => Ok(default.to_owned()),
+        (name, email) => Signature::now(
+            &name.unwrap_or_else(|| default.name().unwrap_or_default().to_string()),
+            &email.unwrap_or_else(|| default.email().unwrap_or_default().to_string()),
+        )
+        .map_err(LottoError::Git),
+    }
+}
+
+/// Sign a commit with the program git is configured to use.
+fn sign_buffer(config: &Config, buffer: &str, key: &str) -> Result<String, LottoError> {
+    let format = config
+        .get_string("gpg.format")
+        .unwrap_or_else(|_| "openpgp".into());
+    let program = |name: &str, default: &str| {
+        config
+            .get_string(&format!("gpg.{}.program", name))
+            .or_else(|_| match name {
+                "openpgp" => config.get_string("gpg.program"),
+                _ => Err(git2::Error::from_str("no program")),
+            })
+            .unwrap_or_else(|_| default.into())
+    };
+    let mut command = match format.as_str() {
+        "ssh" => {
+            let mut command = Command::new(program("ssh", "ssh-keygen"));
+            command.args(["-Y", "sign", "-n", "git", "-f", key]);
+            command
+        }
+        "x509" => {
+            let mut command = Command::new(program("x509", "gpgsm"));
+            command.args(["--status-fd=2", "-bsau", key]);
+            command
+        }
+        _ => {
+            let mut command = Command::new(program("openpgp", "gpg"));
+            command.args(["--status-fd=2", "-bsau", key]);
+            command
+        }
+    };
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::inherit())
+        .spawn()?;
+    child
+        .stdin
+        .take()
+        .ok_or(LottoError::GitFailed)?
+        .write_all(buffer.as_bytes())?;
+    let output = child.wait_with_output()?;
+    if !output.status.success() || output.stdout.is_empty() {
+        return Err(LottoError::command_failed(
+            command.get_program().to_string_lossy(),
+            output.status,
+            // it went straight to the terminal
+            String::new(),
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_util::commit;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|a| a.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse() {
+        let options = CommitOptions::parse(&args(&["-am", "subject", "-m", "body"])).unwrap();
+        assert!(options.all);
+        assert_eq!(options.message, "subject\n\nbody");
+        assert_eq!(options.sign, None);
+
+        let options =
+            CommitOptions::parse(&args(&["--message=fix", "-SABCD", "--allow-empty"])).unwrap();
+        assert_eq!(options.signing_key.as_deref(), Some("ABCD"));
+        assert!(options.allow_empty);
+
+        // git opens an editor, or knows flags we don't
+        assert_eq!(CommitOptions::parse(&args(&["-a"])), None);
+        assert_eq!(CommitOptions::parse(&args(&["-m", "x", "--amend"])), None);
+        assert_eq!(CommitOptions::parse(&args(&["-m", "x", "file.txt"])), None);
+    }
+
+    fn repo_with_file() -> (tempfile::TempDir, Repository) {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        let mut config = repo.config().unwrap();
+        config.set_str("user.name", "Someone").unwrap();
+        config.set_str("user.email", "a@example.com").unwrap();
+        std::fs::write(dir.path().join("file"), "one").unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_path(std::path::Path::new("file")).unwrap();
+        index.write().unwrap();
+        commit(&repo, "a@example.com", 1_000);
+        (dir, repo)
+    }
+
+    #[test]
+    fn test_commit_in_process() {
+        let (dir, repo) = repo_with_file();
+        let options = CommitOptions::parse(&args(&["-am", "change file"])).unwrap();
+        // nothing staged or changed yet
+        assert!(matches!(
+            commit_in_process(&repo, &options),
+            Err(LottoError::NothingToCommit)
+        ));
+
+        std::fs::write(dir.path().join("file"), "two").unwrap();
+        let oid = commit_in_process(&repo, &options).unwrap().unwrap();
+        assert_eq!(repo.head().unwrap().target(), Some(oid));
+        let commit = repo.find_commit(oid).unwrap();
+        assert_eq!(commit.message(), Some("change file\n"));
+        assert_eq!(commit.author().email(), Some("a@example.com"));
+        let blob = commit.tree().unwrap().get_name("file").unwrap().id();
+        assert_eq!(repo.find_blob(blob).unwrap().content(), b"two");
+    }
+
+    #[test]
+    fn test_message_keeps_hashes() {
+        let (_dir, repo) = repo_with_file();
+        let options =
+            CommitOptions::parse(&args(&["--allow-empty", "-m", "Fix #12\n#13 too"])).unwrap();
+        let oid = commit_in_process(&repo, &options).unwrap().unwrap();
+        assert_eq!(
+            repo.find_commit(oid).unwrap().message(),
+            Some("Fix #12\n#13 too\n")
+        );
+    }
+
+    #[test]
+    fn test_merge_left_to_git() {
+        let (_dir, repo) = repo_with_file();
+        let head = repo.head().unwrap().target().unwrap();
+        std::fs::write(repo.path().join("MERGE_HEAD"), format!("{}\n", head)).unwrap();
+        assert_eq!(repo.state(), RepositoryState::Merge);
+        let options = CommitOptions::parse(&args(&["--allow-empty", "-m", "merge"])).unwrap();
+        assert_eq!(commit_in_process(&repo, &options).unwrap(), None);
+        assert_eq!(repo.head().unwrap().target(), Some(head));
+    }
+
+    #[test]
+    fn test_signed_commit() {
+        let (dir, repo) = repo_with_file();
+        let gpg = dir.path().join("fake-gpg");
+        std::fs::write(
+            &gpg,
+            "#!/bin/sh\ncat > /dev/null\necho '-----BEGIN PGP SIGNATURE-----'\necho '-----END PGP SIGNATURE-----'\n",
+        )
+        .unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&gpg, std::fs::Permissions::from_mode(0o755)).unwrap();
+        }
+        repo.config()
+            .unwrap()
+            .set_str("gpg.program", gpg.to_str().unwrap())
+            .unwrap();
+
+        let options =
+            CommitOptions::parse(&args(&["-S", "--allow-empty", "-m", "signed"])).unwrap();
+        let oid = commit_in_process(&repo, &options).unwrap().unwrap();
+        assert_eq!(repo.head().unwrap().target(), Some(oid));
+        let commit = repo.find_commit(oid).unwrap();
+        let signature = commit.header_field_bytes("gpgsig").unwrap();
+        assert!(signature.starts_with(b"-----BEGIN PGP SIGNATURE-----"));
+        assert_eq!(commit.parent_count(), 1);
+    }
+}
@@ -0,0 +1,428 @@
+use std::{
+    collections::BTreeMap,
+    env,
+    path::{Path, PathBuf},
+};
+
+use anyhow::anyhow;
+use chrono::NaiveDate;
+use directories::ProjectDirs;
+use serde::{de::Error as _, Deserialize, Deserializer, Serialize};
+use toml::Value;
+
+use crate::{
+    cards::Deal,
+    errors::LottoError,
+    git::{toplevel, ObjectFormat},
+    output::{Colour, Format},
+    period::Window,
+};
+
+/// The name of the config file at the root of a repository.
+pub const REPO_CONFIG: &str = ".commit-poker.toml";
+/// The most characters of a hash that can be scored. Past this nearly every
+/// hash repeats characters in a full house, so it stops being rare.
+pub const MAX_LENGTH: usize = 10;
+// so no object format's hashes are too short to score
+const _: () = assert!(MAX_LENGTH <= ObjectFormat::Sha1.hex_len());
+/// Overrides the configured output format.
+pub const FORMAT_ENV: &str = "COMMIT_POKER_FORMAT";
+/// The tables only the user config can set.
+const USER_ONLY: &[&str] = &["storage", "anti_cheat", "commit"];
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub rules: RulesConfig,
+    pub output: OutputConfig,
+    pub storage: StorageConfig,
+    pub anti_cheat: AntiCheatConfig,
+    pub commit: CommitConfig,
+    pub seasons: Vec<SeasonConfig>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RulesConfig {
+    pub n_of_a_kind: bool,
+    pub flush: bool,
+    pub straight: bool,
+    pub poker: bool,
+    /// The shortest run of characters that counts as a straight.
+    pub min_straight: usize,
+    pub deal: Deal,
+    /// How many characters of the hash are scored, up to [`MAX_LENGTH`].
+    pub length: usize,
+}
+
+impl Default for RulesConfig {
+    fn default() -> Self {
+        RulesConfig {
+            n_of_a_kind: true,
+            flush: true,
+            straight: true,
+            poker: true,
+            min_straight: 4,
+            deal: Deal::FiveCard,
+            length: 7,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OutputConfig {
+    pub format: Format,
+    /// Delay between each character of the hash being revealed.
+    pub delay_ms: u64,
+    pub base_colour: Colour,
+    /// Colours used to highlight the rules, in order.
+    pub colours: Vec<Colour>,
+    pub shuffle_colours: bool,
+}
+
+impl Default for OutputConfig {
+    fn default() -> Self {
+        OutputConfig {
+            format: Format::Terminal,
+            delay_ms: 200,
+            base_colour: Colour::Black,
+            colours: vec![
+                Colour::Red,
+                Colour::Green,
+                Colour::Yellow,
+                Colour::Blue,
+                Colour::Magenta,
+                Colour::Cyan,
+            ],
+            shuffle_colours: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StorageConfig {
+    pub backend: Backend,
+    /// Where high scores are kept, defaults to the local data directory, or
+    /// the current repository for the git backend. The SQLite backend copies
+    /// the JSON file here in when its database is first opened.
+    pub path: Option<PathBuf>,
+    /// Where the SQLite backend keeps its database, defaults to the local
+    /// data directory.
+    pub sqlite_path: Option<PathBuf>,
+    /// Which score counts when a commit is amended or rebased.
+    pub rewrites: RewritePolicy,
+    /// The server the remote backend uses, e.g. `http://dashboard:8080`.
+    pub url: Option<String>,
+    /// The token the remote backend saves scores with.
+    pub token: Option<String>,
+    /// The token of each author, by email, that `serve` lets save scores.
+    pub tokens: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RewritePolicy {
+    /// The best score of the original commit and everything it became.
+    #[default]
+    KeepBest,
+    /// The score of the newest version of the commit.
+    KeepLatest,
+    /// The score of the original commit, so amending can't reroll it.
+    ForbidRerolls,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+    /// One JSON file, fine for a single person.
+    #[default]
+    Json,
+    /// An SQLite database, for teams with a lot of history.
+    Sqlite,
+    /// Committed to `refs/commit-poker/scores` in the repository, so it can
+    /// be shared with `commit-poker-util sync`.
+    Git,
+    /// A `commit-poker-util serve` server at `storage.url`.
+    Remote,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CommitConfig {
+    pub backend: CommitBackend,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CommitBackend {
+    /// Run `git commit`.
+    #[default]
+    Git,
+    /// Commit with libgit2 when the arguments allow it, skipping git's
+    /// commit hooks.
+    Libgit2,
+}
+
+/// A named stretch of days with a leaderboard of its own.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SeasonConfig {
+    pub name: String,
+    #[serde(deserialize_with = "date")]
+    pub start: NaiveDate,
+    /// The last day of the season.
+    #[serde(deserialize_with = "date")]
+    pub end: NaiveDate,
+}
+
+impl SeasonConfig {
+    pub fn window(&self) -> Window {
+        Window::days(self.start, self.end)
+    }
+}
+
+/// A date written as a TOML date or a string.
+fn date<'de, D: Deserializer<'de>>(deserializer: D) -> Result<NaiveDate, D::Error> {
+    let text = match Value::deserialize(deserializer)? {
+        Value::String(text) => text,
+        Value::Datetime(date) => date.to_string(),
+        _ => return Err(D::Error::custom("expected a date like 2026-09-01")),
+    };
+    NaiveDate::parse_from_str(&text, "%Y-%m-%d").map_err(D::Error::custom)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AntiCheatConfig {
+    /// How many times the same tree can be scored before it looks rerolled.
+    pub max_rerolls: usize,
+    /// How far apart the author and committer dates can be.
+    pub max_date_skew_hours: i64,
+    /// Whether flagged scores stop counting, rather than just being marked.
+    pub disqualify: bool,
+}
+
+impl Default for AntiCheatConfig {
+    fn default() -> Self {
+        AntiCheatConfig {
+            max_rerolls: 3,
+            max_date_skew_hours: 24,
+            disqualify: false,
+        }
+    }
+}
+
+impl Config {
+    /// Load the user config, overridden by the config of the repository the
+    /// current directory is in.
+    pub fn load() -> Result<Self, LottoError> {
+        let repo_config = toplevel(Path::new(".")).map(|w| w.join(REPO_CONFIG));
+        let mut config = Config::load_from(user_config_path().as_deref(), repo_config.as_deref())?;
+        if let Ok(format) = env::var(FORMAT_ENV) {
+            config.output.format = format.parse().map_err(LottoError::ConfigError)?;
+        }
+        Ok(config)
+    }
+
+    /// A repository's config can't set `[storage]`, `[anti_cheat]` or
+    /// `[commit]`, so a cloned repository can't choose which file the scores
+    /// are written to, turn off the checks or how commits are made.
+    pub fn load_from(user: Option<&Path>, repo: Option<&Path>) -> Result<Self, LottoError> {
+        let mut config = Value::Table(Default::default());
+        for (path, is_repo) in [(user, false), (repo, true)] {
+            let Some(path) = path.filter(|p| p.exists()) else {
+                continue;
+            };
+            let text = std::fs::read_to_string(path)
+                .map_err(|e| LottoError::ConfigError(anyhow!("{}: {}", path.display(), e)))?;
+            let mut value = parse(&text)
+                .map_err(|e| LottoError::ConfigError(anyhow!("{}: {}", path.display(), e)))?;
+            if is_repo {
+                if let Some(table) = value.as_table_mut() {
+                    for key in USER_ONLY {
+                        if table.remove(*key).is_some() {
+                            eprintln!(
+                                "{}: ignoring [{}], which can only be set in the user config",
+                                path.display(),
+                                key
+                            );
+                        }
+                    }
+                }
+            }
+            merge(&mut config, value);
+        }
+        let config: Config = config
+            .try_into()
+            .map_err(|e| LottoError::ConfigError(anyhow!(e)))?;
+        if !(1..=MAX_LENGTH).contains(&config.rules.length) {
+            return Err(LottoError::ConfigError(anyhow!(
+                "rules.length must be between 1 and {}",
+                MAX_LENGTH
+            )));
+        }
+        for (i, season) in config.seasons.iter().enumerate() {
+            if season.end < season.start {
+                return Err(LottoError::ConfigError(anyhow!(
+                    "season {} ends before it starts",
+                    season.name
+                )));
+            }
+            if config.seasons[..i].iter().any(|s| s.name == season.name) {
+                return Err(LottoError::ConfigError(anyhow!(
+                    "there are two seasons called {}",
+                    season.name
+                )));
+            }
+        }
+        Ok(config)
+    }
+}
+
+pub fn user_config_path() -> Option<PathBuf> {
+    ProjectDirs::from("com", "joelynch", "CommitLotto").map(|d| d.config_dir().join("config.toml"))
+}
+
+fn parse(text: &str) -> anyhow::Result<Value> {
+    Ok(Value::Table(toml::from_str(text)?))
+}
+
+/// Merge `over` into `base`, tables being merged key by key.
+fn merge(base: &mut Value, over: Value) {
+    match (base, over) {
+        (Value::Table(base), Value::Table(over)) => {
+            for (key, value) in over {
+                match base.get_mut(&key) {
+                    Some(existing) => merge(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, over) => *base = over,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_merge() {
+        let mut config = parse(
+            r#"
+            [rules]
+            flush = false
+            min_straight = 5
+
+            [output]
+            delay_ms = 0
+            "#,
+        )
+        .unwrap();
+        merge(
+            &mut config,
+            parse(
+                r#"
+                [rules]
+                min_straight = 3
+                deal = "holdem"
+
+                [storage]
+                path = "/tmp/scores.json"
+                "#,
+            )
+            .unwrap(),
+        );
+        let config: Config = config.try_into().unwrap();
+        assert!(!config.rules.flush);
+        assert!(config.rules.straight);
+        assert_eq!(config.rules.min_straight, 3);
+        assert_eq!(config.rules.deal, Deal::Holdem);
+        assert_eq!(config.output.delay_ms, 0);
+        assert_eq!(config.output.colours.len(), 6);
+        assert_eq!(config.storage.path, Some(PathBuf::from("/tmp/scores.json")));
+    }
+
+    #[test]
+    fn test_seasons() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        std::fs::write(
+            &path,
+            r#"
+            [[seasons]]
+            name = "Autumn"
+            start = 2026-09-01
+            end = "2026-11-30"
+            "#,
+        )
+        .unwrap();
+        let config = Config::load_from(Some(&path), None).unwrap();
+        assert_eq!(config.seasons[0].end.to_string(), "2026-11-30");
+
+        std::fs::write(
+            &path,
+            "[[seasons]]\nname = \"Backwards\"\nstart = 2026-09-01\nend = 2026-08-01\n",
+        )
+        .unwrap();
+        assert!(Config::load_from(Some(&path), None).is_err());
+    }
+
+    #[test]
+    fn test_repo_storage_ignored() {
+        let dir = tempfile::tempdir().unwrap();
+        let user = dir.path().join("config.toml");
+        let repo = dir.path().join(REPO_CONFIG);
+        std::fs::write(&user, "[storage]\npath = \"/home/me/scores.json\"\n").unwrap();
+        std::fs::write(
+            &repo,
+            "[rules]\nlength = 8\n\n[storage]\npath = \"/etc/passwd\"\nbackend = \"sqlite\"\n",
+        )
+        .unwrap();
+        let config = Config::load_from(Some(&user), Some(&repo)).unwrap();
+        assert_eq!(config.rules.length, 8);
+        assert_eq!(
+            config.storage.path,
+            Some(PathBuf::from("/home/me/scores.json"))
+        );
+        assert_eq!(config.storage.backend, Backend::Json);
+    }
+
+    #[test]
+    fn test_repo_anti_cheat_and_commit_ignored() {
+        let dir = tempfile::tempdir().unwrap();
+        let user = dir.path().join("config.toml");
+        let repo = dir.path().join(REPO_CONFIG);
+        std::fs::write(
+            &user,
+            "[anti_cheat]
+disqualify = true
+",
+        )
+        .unwrap();
+        std::fs::write(
+            &repo,
+            "[anti_cheat]
+disqualify = false
+max_rerolls = 1000
+
+[commit]
+backend = \"libgit2\"
+",
+        )
+        .unwrap();
+        let config = Config::load_from(Some(&user), Some(&repo)).unwrap();
+        assert!(config.anti_cheat.disqualify);
+        assert_eq!(config.anti_cheat.max_rerolls, 3);
+        assert_eq!(config.commit.backend, CommitBackend::Git);
+    }
+
+    #[test]
+    fn test_missing_files() {
+        let config = Config::load_from(Some(Path::new("/does/not/exist.toml")), None).unwrap();
+        assert_eq!(config.rules.min_straight, 4);
+    }
+}
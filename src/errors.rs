@@ -1,10 +1,60 @@
-use std::io;
+use std::{io, process::ExitStatus};
 
 #[derive(Debug)]
 pub enum LottoError {
     GitNotPresent(io::Error),
     GitFailed,
+    /// A git command exited unsuccessfully. `status` is its exit code, or
+    /// `signal` the signal that killed it, and `stderr` what it printed there
+    /// if it was captured.
+    CommandFailed {
+        command: String,
+        status: Option<i32>,
+        signal: Option<i32>,
+        stderr: String,
+    },
+    /// The index is the same as HEAD's tree, so committing in-process would
+    /// make an empty commit.
+    NothingToCommit,
+    Git(git2::Error),
     ApplicationDirError(anyhow::Error),
+    SimulationFailed(Vec<String>),
+    ConfigError(anyhow::Error),
+    HookError(anyhow::Error),
+    MiningFailed(anyhow::Error),
+    ServerError(anyhow::Error),
+}
+
+impl LottoError {
+    pub fn command_failed(command: impl Into<String>, status: ExitStatus, stderr: String) -> Self {
+        #[cfg(unix)]
+        let signal = std::os::unix::process::ExitStatusExt::signal(&status);
+        #[cfg(not(unix))]
+        let signal = None;
+        LottoError::CommandFailed {
+            command: command.into(),
+            status: status.code(),
+            signal,
+            stderr,
+        }
+    }
+
+    /// The status to exit with, git's own if a git command failed, so
+    /// wrapping `git commit` doesn't change what scripts see. Like a shell,
+    /// a command killed by a signal gives 128 plus the signal.
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            LottoError::CommandFailed {
+                status: Some(status),
+                ..
+            } => u8::try_from(*status).unwrap_or(1),
+            LottoError::CommandFailed {
+                signal: Some(signal),
+                ..
+            } => u8::try_from(128 + signal).unwrap_or(1),
+            _ => 1,
+        }
+    }
 }
 
 impl From<io::Error> for LottoError {
@@ -13,16 +63,62 @@ impl From<io::Error> for LottoError {
     }
 }
 
+impl From<git2::Error> for LottoError {
+    fn from(err: git2::Error) -> LottoError {
+        LottoError::Git(err)
+    }
+}
+
 impl std::fmt::Display for LottoError {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         match self {
             LottoError::GitNotPresent(_) => write!(f, "Could not find git on path"),
             LottoError::GitFailed => write!(f, "Git commit failed"),
+            LottoError::CommandFailed {
+                command,
+                status,
+                signal,
+                stderr,
+            } => {
+                match (status, signal) {
+                    (Some(status), _) => write!(f, "`{}` exited with status {}", command, status)?,
+                    (None, Some(signal)) => {
+                        write!(f, "`{}` was killed by signal {}", command, signal)?
+                    }
+                    (None, None) => write!(f, "`{}` was killed", command)?,
+                }
+                match stderr.trim().lines().last() {
+                    Some(line) => write!(f, ": {}", line),
+                    None => Ok(()),
+                }
+            }
+            LottoError::NothingToCommit => write!(f, "Nothing to commit"),
+            LottoError::Git(e) => write!(f, "Git error: {}", e.message()),
             LottoError::ApplicationDirError(e) => {
                 write!(f, "Error using application data directory: {}", e)
             }
+            LottoError::SimulationFailed(rules) => {
+                write!(f, "Rules out of tolerance: {}", rules.join(", "))
+            }
+            LottoError::ConfigError(e) => write!(f, "Error reading config: {}", e),
+            LottoError::HookError(e) => write!(f, "Error managing post-commit hook: {}", e),
+            LottoError::MiningFailed(e) => write!(f, "Error mining commit: {}", e),
+            LottoError::ServerError(e) => write!(f, "Error talking to the score server: {}", e),
         }
     }
 }
 
-impl std::error::Error for LottoError {}
+impl std::error::Error for LottoError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            LottoError::GitNotPresent(e) => Some(e),
+            LottoError::Git(e) => Some(e),
+            LottoError::ApplicationDirError(e)
+            | LottoError::ConfigError(e)
+            | LottoError::HookError(e)
+            | LottoError::MiningFailed(e)
+            | LottoError::ServerError(e) => Some(e.as_ref()),
+            _ => None,
+        }
+    }
+}
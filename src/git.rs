@@ -1,71 +1,656 @@
-use std::{path::PathBuf, process::Command};
+use std::{
+    io::{self, stdout, IsTerminal},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
 
-use git2::Repository;
+use git2::{Repository, RevparseMode, Signature, Sort};
+use serde::{Deserialize, Serialize};
 
-use crate::errors::LottoError;
+use crate::{
+    commit::{commit_in_process, CommitOptions},
+    config::CommitBackend,
+    errors::LottoError,
+    hook::WRAPPED_ENV,
+    output::Format,
+};
+
+/// Identifies a repository across clones, moves and worktrees.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RepoId {
+    /// The first commit in the default branch's first-parent history, or
+    /// `None` before anything is committed.
+    pub root: Option<String>,
+    pub remote: Option<String>,
+    /// Where the repository was found. Only used for display, and to match
+    /// scores recorded before repositories had an id.
+    pub path: PathBuf,
+}
+
+impl RepoId {
+    /// Walks the default branch's history, so callers scoring many commits
+    /// should only do it once.
+    pub fn for_repo(repo: &Repository) -> Result<Self, LottoError> {
+        let path = repo.workdir().unwrap_or(repo.path()).to_path_buf();
+        let configured = repo
+            .config()
+            .and_then(|c| c.get_string("init.defaultBranch"))
+            .ok();
+        let start = default_branches(configured)
+            .into_iter()
+            .find_map(|name| repo.revparse_single(&name).ok()?.peel_to_commit().ok());
+        let root = match start {
+            Some(start) => {
+                let mut walk = repo.revwalk()?;
+                walk.simplify_first_parent()?;
+                walk.push(start.id())?;
+                Some(walk.last().ok_or(LottoError::GitFailed)??.to_string())
+            }
+            None => None,
+        };
+        let remote = repo
+            .find_remote("origin")
+            .ok()
+            .or_else(|| {
+                let names = repo.remotes().ok()?;
+                let name = names.iter().flatten().next()?;
+                repo.find_remote(name).ok()
+            })
+            .and_then(|r| r.url().map(normalise_remote));
+        Ok(RepoId { root, remote, path })
+    }
+
+    pub fn discover(path: &Path) -> Result<Self, LottoError> {
+        match Repository::discover(path) {
+            Ok(repo) => RepoId::for_repo(&repo),
+            Err(_) if ObjectFormat::detect(path)? == ObjectFormat::Sha256 => RepoId::from_git(path),
+            Err(e) => Err(LottoError::Git(e)),
+        }
+    }
+
+    /// Identify a repository with the git command line.
+    fn from_git(path: &Path) -> Result<Self, LottoError> {
+        let toplevel = git_output(path, &["rev-parse", "--show-toplevel"])?;
+        let configured = git_output(path, &["config", "init.defaultBranch"])
+            .ok()
+            .map(|name| name.trim_end().to_string());
+        let root = default_branches(configured).into_iter().find_map(|name| {
+            let roots = git_output(
+                path,
+                &["rev-list", "--first-parent", "--max-parents=0", &name, "--"],
+            )
+            .ok()?;
+            roots.lines().last().map(str::to_string)
+        });
+        let remotes = git_output(path, &["remote"])?;
+        let remote = remotes
+            .lines()
+            .find(|r| *r == "origin")
+            .or_else(|| remotes.lines().next())
+            .and_then(|name| git_output(path, &["remote", "get-url", name]).ok())
+            .map(|url| normalise_remote(&url));
+        Ok(RepoId {
+            root,
+            remote,
+            path: PathBuf::from(toplevel.trim_end()),
+        })
+    }
+
+    /// Whether a repository with this root and remote is this repository.
+    /// Remotes only count when both sides have one.
+    pub fn is(&self, root: &str, remote: Option<&str>) -> bool {
+        self.root.as_deref() == Some(root)
+            && match (&self.remote, remote) {
+                (Some(ours), Some(theirs)) => *ours == normalise_remote(theirs),
+                _ => true,
+            }
+    }
+}
+
+/// Where to look for the default branch, so a repository's root doesn't
+/// change with the branch that is checked out. HEAD is the last resort.
+fn default_branches(configured: Option<String>) -> Vec<String> {
+    let mut names = vec!["refs/remotes/origin/HEAD".to_string()];
+    names.extend(configured.map(|name| format!("refs/heads/{}", name)));
+    names.extend(["refs/heads/main".into(), "refs/heads/master".into()]);
+    names.push("HEAD".into());
+    names
+}
+
+/// Reduce the different ways of writing a remote URL to `host/path`, so ssh
+/// and https clones of the same repository are the same.
+pub fn normalise_remote(url: &str) -> String {
+    let mut url = url.trim().trim_end_matches('/');
+    url = url.strip_suffix(".git").unwrap_or(url);
+    let url = match url.split_once("://") {
+        Some((_, rest)) => rest.to_string(),
+        // scp-like syntax, e.g. git@github.com:owner/repo
+        None => match url.split_once(':') {
+            Some((host, path)) if !host.contains('/') => format!("{}/{}", host, path),
+            _ => url.to_string(),
+        },
+    };
+    match url.split_once('@') {
+        Some((user, rest)) if !user.contains('/') => rest.to_string(),
+        _ => url,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Person {
+    pub name: String,
+    pub email: String,
+}
+
+impl From<Signature<'_>> for Person {
+    fn from(signature: Signature) -> Self {
+        Person {
+            name: String::from_utf8_lossy(signature.name_bytes()).to_string(),
+            email: String::from_utf8_lossy(signature.email_bytes()).to_string(),
+        }
+    }
+}
+
+impl std::fmt::Display for Person {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{} <{}>", self.name, self.email)
+    }
+}
+
+/// How a commit was made, for spotting commits made over and over until they
+/// scored well.
+#[derive(Debug, Clone, Default)]
+pub struct Provenance {
+    pub tree: String,
+    /// The short hash and tree of each parent.
+    pub parents: Vec<(String, String)>,
+    pub author_date: i64,
+    /// Made by amending or rebasing, which moves the committer date on but
+    /// keeps the author date.
+    pub rewritten: bool,
+}
+
+/// The hash function a repository names its objects with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ObjectFormat {
+    Sha1,
+    Sha256,
+}
+
+impl ObjectFormat {
+    /// The number of hex characters in a full hash.
+    pub const fn hex_len(self) -> usize {
+        match self {
+            ObjectFormat::Sha1 => 40,
+            ObjectFormat::Sha256 => 64,
+        }
+    }
+
+    pub fn of_hash(full_hash: &str) -> Self {
+        if full_hash.len() == ObjectFormat::Sha256.hex_len() {
+            ObjectFormat::Sha256
+        } else {
+            ObjectFormat::Sha1
+        }
+    }
+
+    /// Ask git, since libgit2 can't open SHA-256 repositories.
+    pub fn detect(path: &Path) -> Result<Self, LottoError> {
+        match git_output(path, &["rev-parse", "--show-object-format"])?.trim() {
+            "sha1" => Ok(ObjectFormat::Sha1),
+            "sha256" => Ok(ObjectFormat::Sha256),
+            _ => Err(LottoError::GitFailed),
+        }
+    }
+}
 
 pub struct Commit {
-    pub repo: PathBuf,
+    pub repo: RepoId,
     pub hash: String,
     pub full_hash: String,
+    pub format: ObjectFormat,
     pub date: String,
     pub summary: Option<String>,
+    pub author: Person,
+    pub committer: Person,
+    pub provenance: Provenance,
 }
 
 impl Commit {
     pub fn new(
-        repo: String,
+        repo: RepoId,
         hash: String,
         full_hash: String,
         date: String,
         summary: Option<String>,
+        author: Person,
+        committer: Person,
     ) -> Self {
         Commit {
-            repo: repo.into(),
+            repo,
             hash,
+            format: ObjectFormat::of_hash(&full_hash),
             full_hash,
             date,
             summary,
+            author,
+            committer,
+            provenance: Provenance::default(),
         }
     }
 
-    pub fn from_repo_and_commit(
-        repo: &Repository,
-        commit: git2::Commit,
-    ) -> Result<Self, LottoError> {
-        let repo_str = repo
-            .workdir()
-            .ok_or(LottoError::GitFailed)?
-            .to_str()
-            .ok_or(LottoError::GitFailed)?
-            .to_string();
+    pub fn with_provenance(self, provenance: Provenance) -> Self {
+        Commit { provenance, ..self }
+    }
+
+    /// A commit read with libgit2 from the repository `repo` identifies.
+    pub fn from_repo_and_commit(repo: &RepoId, commit: git2::Commit) -> Result<Self, LottoError> {
         let hash = commit
             .as_object()
-            .short_id()
-            .map_err(|_| LottoError::GitFailed)?
+            .short_id()?
             .as_str()
             .ok_or(LottoError::GitFailed)?
             .to_string();
         let full_hash = commit.id().to_string();
         let date = commit.time().seconds().to_string();
         let summary = commit.summary().map(|s| s.to_string());
-        Ok(Commit::new(repo_str, hash, full_hash, date, summary))
+        let provenance = Provenance {
+            tree: commit.tree_id().to_string(),
+            parents: commit
+                .parents()
+                .map(|p| {
+                    let hash = p.as_object().short_id().ok();
+                    let hash = hash.as_ref().and_then(|h| h.as_str()).unwrap_or_default();
+                    (hash.to_string(), p.tree_id().to_string())
+                })
+                .collect(),
+            author_date: commit.author().when().seconds(),
+            rewritten: false,
+        };
+        Ok(Commit::new(
+            repo.clone(),
+            hash,
+            full_hash,
+            date,
+            summary,
+            commit.author().into(),
+            commit.committer().into(),
+        )
+        .with_provenance(provenance))
     }
 
     pub fn latest() -> Result<Self, LottoError> {
-        let repo = Repository::discover(".").map_err(|_| LottoError::GitFailed)?;
-        let head = repo.head().map_err(|_| LottoError::GitFailed)?;
-        let commit = head.peel_to_commit().map_err(|_| LottoError::GitFailed)?;
-        Self::from_repo_and_commit(&repo, commit)
+        Self::latest_in(Path::new("."))
+    }
+
+    /// The HEAD commit of the repository `path` is in.
+    pub fn latest_in(path: &Path) -> Result<Self, LottoError> {
+        let repo = match Repository::discover(path) {
+            Ok(repo) => repo,
+            Err(_) if ObjectFormat::detect(path)? == ObjectFormat::Sha256 => {
+                return Self::from_git(path, "HEAD");
+            }
+            Err(e) => return Err(LottoError::Git(e)),
+        };
+        let head = repo.head()?;
+        let commit = head.peel_to_commit()?;
+        Self::from_repo_and_commit(&RepoId::for_repo(&repo)?, commit)
+    }
+
+    /// Read a commit with the git command line.
+    fn from_git(path: &Path, revision: &str) -> Result<Self, LottoError> {
+        let output = git_output(
+            path,
+            &[
+                "log",
+                "-1",
+                "--format=%H%x00%h%x00%ct%x00%s%x00%an%x00%ae%x00%cn%x00%ce%x00%T%x00%P%x00%at",
+                revision,
+                "--",
+            ],
+        )?;
+        let fields: Vec<&str> = output.trim_end_matches('\n').split('\0').collect();
+        let [full_hash, hash, date, summary, author_name, author_email, committer_name, committer_email, tree, parents, author_date] =
+            fields[..]
+        else {
+            return Err(LottoError::GitFailed);
+        };
+        let parents: Vec<&str> = parents.split_whitespace().collect();
+        let parents = if parents.is_empty() {
+            vec![]
+        } else {
+            let mut args = vec!["log", "--no-walk=unsorted", "--format=%h %T"];
+            args.extend(&parents);
+            git_output(path, &args)?
+                .lines()
+                .filter_map(|line| line.split_once(' '))
+                .map(|(hash, tree)| (hash.to_string(), tree.to_string()))
+                .collect()
+        };
+        let person = |name: &str, email: &str| Person {
+            name: name.to_string(),
+            email: email.to_string(),
+        };
+        Ok(Commit::new(
+            RepoId::from_git(path)?,
+            hash.to_string(),
+            full_hash.to_string(),
+            date.to_string(),
+            Some(summary.to_string()).filter(|s| !s.is_empty()),
+            person(author_name, author_email),
+            person(committer_name, committer_email),
+        )
+        .with_provenance(Provenance {
+            tree: tree.to_string(),
+            parents,
+            author_date: author_date.parse().map_err(|_| LottoError::GitFailed)?,
+            rewritten: false,
+        }))
     }
 }
 
-pub fn git_commit(args: Vec<String>) -> Result<(), LottoError> {
-    let mut cmd = Command::new("git");
-    if cmd.arg("commit").args(args).status()?.success() {
-        print!("\x1B[A\x1B[2K");
+/// Resolve a revision like `HEAD~2` or a range like `main..feature` to the
+/// commits it refers to, oldest first.
+pub fn resolve_commits(repo: &Repository, spec: &str) -> Result<Vec<Commit>, LottoError> {
+    let revspec = repo.revparse(spec)?;
+    let repo_id = RepoId::for_repo(repo)?;
+    if revspec.mode().contains(RevparseMode::SINGLE) {
+        let commit = revspec
+            .from()
+            .ok_or(LottoError::GitFailed)?
+            .peel_to_commit()?;
+        return Ok(vec![Commit::from_repo_and_commit(&repo_id, commit)?]);
+    }
+
+    let from = revspec.from().ok_or(LottoError::GitFailed)?.id();
+    let to = revspec.to().ok_or(LottoError::GitFailed)?.id();
+    let mut walk = repo.revwalk()?;
+    walk.set_sorting(Sort::TOPOLOGICAL | Sort::REVERSE)?;
+    walk.push(to)?;
+    if revspec.mode().contains(RevparseMode::MERGE_BASE) {
+        // a...b is everything reachable from either side but not both
+        walk.push(from)?;
+        let base = repo.merge_base(from, to)?;
+        walk.hide(base)?;
     } else {
-        return Err(LottoError::GitFailed);
+        walk.hide(from)?;
+    }
+    walk.map(|oid| {
+        let oid = oid?;
+        let commit = repo.find_commit(oid)?;
+        Commit::from_repo_and_commit(&repo_id, commit)
+    })
+    .collect()
+}
+
+/// The working directory of the repository `path` is in.
+pub fn toplevel(path: &Path) -> Option<PathBuf> {
+    match Repository::discover(path) {
+        Ok(repo) => repo.workdir().map(Path::to_path_buf),
+        Err(_) => git_output(path, &["rev-parse", "--show-toplevel"])
+            .ok()
+            .map(|dir| PathBuf::from(dir.trim_end())),
+    }
+}
+
+pub(crate) fn git_output(path: &Path, args: &[&str]) -> Result<String, LottoError> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(path)
+        .args(args)
+        .output()?;
+    if !output.status.success() {
+        return Err(LottoError::command_failed(
+            format!("git {}", args.join(" ")),
+            output.status,
+            String::from_utf8_lossy(&output.stderr).to_string(),
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Run `git commit`. With JSON output git's summary goes to stderr, so
+/// stdout only has the JSON document.
+pub fn git_commit(args: Vec<String>, format: Format) -> Result<(), LottoError> {
+    let summary = match format {
+        Format::Terminal => Stdio::inherit(),
+        Format::Json => io::stderr().into(),
+    };
+    let status = Command::new("git")
+        .env(WRAPPED_ENV, "1")
+        .arg("commit")
+        .args(args)
+        .stdout(summary)
+        .status()?;
+    // git, its hooks and the editor keep the terminal on stderr, and say
+    // themselves what went wrong
+    if !status.success() {
+        return Err(LottoError::command_failed(
+            "git commit",
+            status,
+            String::new(),
+        ));
+    }
+    // erase git's summary line, leaving only ours
+    if format == Format::Terminal && stdout().is_terminal() {
+        print!("\x1B[A\x1B[2K");
     }
     Ok(())
 }
+
+/// Commit with the configured backend, falling back to `git commit` for
+/// arguments libgit2 can't handle, and read back the new commit.
+pub fn commit(
+    backend: CommitBackend,
+    format: Format,
+    args: Vec<String>,
+) -> Result<Commit, LottoError> {
+    let options = match backend {
+        CommitBackend::Libgit2 => CommitOptions::parse(&args),
+        CommitBackend::Git => None,
+    };
+    // libgit2 can't open SHA-256 repositories, so git commits in those
+    let committed = match (options, Repository::discover(".")) {
+        (Some(options), Ok(repo)) => commit_in_process(&repo, &options)?.map(|oid| (repo, oid)),
+        _ => None,
+    };
+    let Some((repo, oid)) = committed else {
+        git_commit(args, format)?;
+        return Commit::latest();
+    };
+    let commit = repo.find_commit(oid)?;
+    Commit::from_repo_and_commit(&RepoId::for_repo(&repo)?, commit)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_util::commit;
+
+    #[test]
+    fn test_resolve_commits() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        let first = commit(&repo, "a@example.com", 1_000);
+        let second = commit(&repo, "a@example.com", 2_000);
+        let third = commit(&repo, "a@example.com", 3_000);
+
+        let head = resolve_commits(&repo, "HEAD~1").unwrap();
+        assert_eq!(head.len(), 1);
+        assert_eq!(head[0].full_hash, second.to_string());
+
+        let range = resolve_commits(&repo, &format!("{}..HEAD", first)).unwrap();
+        let hashes: Vec<String> = range.into_iter().map(|c| c.full_hash).collect();
+        assert_eq!(hashes, vec![second.to_string(), third.to_string()]);
+
+        assert!(resolve_commits(&repo, "no-such-branch").is_err());
+    }
+
+    #[test]
+    fn test_repo_id_survives_clone() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path().join("origin")).unwrap();
+        let root = commit(&repo, "a@example.com", 1_000);
+        commit(&repo, "a@example.com", 2_000);
+        let id = RepoId::for_repo(&repo).unwrap();
+        assert_eq!(id.root, Some(root.to_string()));
+        assert_eq!(id.remote, None);
+
+        let clone =
+            Repository::clone(repo.path().to_str().unwrap(), dir.path().join("clone")).unwrap();
+        let clone_id = RepoId::for_repo(&clone).unwrap();
+        assert_ne!(clone_id.path, id.path);
+        let root = root.to_string();
+        assert!(clone_id.is(&root, id.remote.as_deref()));
+        assert!(id.is(&root, clone_id.remote.as_deref()));
+        assert!(!clone_id.is(&root, Some("https://example.com/other.git")));
+    }
+
+    #[test]
+    fn test_repo_id_ignores_checked_out_branch() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        let unborn = RepoId::for_repo(&repo).unwrap();
+        assert_eq!(unborn.root, None);
+        assert!(!unborn.is("abc", None));
+
+        let root = commit(&repo, "a@example.com", 1_000);
+        let main = repo.head().unwrap().name().unwrap().to_string();
+        // an orphan branch, like gh-pages
+        repo.set_head("refs/heads/pages").unwrap();
+        commit(&repo, "a@example.com", 2_000);
+        assert_eq!(
+            RepoId::for_repo(&repo).unwrap().root,
+            Some(root.to_string())
+        );
+        repo.set_head(&main).unwrap();
+        assert_eq!(
+            RepoId::for_repo(&repo).unwrap().root,
+            Some(root.to_string())
+        );
+    }
+
+    /// Make a repository with git itself, since libgit2 can't.
+    fn sha256_repo(dir: &Path, commits: &[&str]) {
+        let git = |args: &[&str]| {
+            let status = Command::new("git")
+                .arg("-C")
+                .arg(dir)
+                .args(args)
+                .env("GIT_CONFIG_NOSYSTEM", "1")
+                .env("GIT_AUTHOR_NAME", "Someone")
+                .env("GIT_AUTHOR_EMAIL", "a@example.com")
+                .env("GIT_COMMITTER_NAME", "Someone")
+                .env("GIT_COMMITTER_EMAIL", "a@example.com")
+                .status()
+                .unwrap();
+            assert!(status.success(), "git {:?}", args);
+        };
+        git(&["init", "-q", "--object-format=sha256"]);
+        for (i, message) in commits.iter().enumerate() {
+            std::fs::write(dir.join("file"), i.to_string()).unwrap();
+            git(&["add", "file"]);
+            git(&["commit", "-q", "--no-verify", "-m", message]);
+        }
+    }
+
+    #[test]
+    fn test_sha256_commit() {
+        let dir = tempfile::tempdir().unwrap();
+        sha256_repo(dir.path(), &["first", "second"]);
+        assert_eq!(
+            ObjectFormat::detect(dir.path()).unwrap(),
+            ObjectFormat::Sha256
+        );
+
+        let commit = Commit::latest_in(dir.path()).unwrap();
+        assert_eq!(commit.format, ObjectFormat::Sha256);
+        assert_eq!(commit.full_hash.len(), 64);
+        assert!(commit.full_hash.starts_with(&commit.hash));
+        assert_eq!(commit.summary.as_deref(), Some("second"));
+        assert_eq!(commit.author.email, "a@example.com");
+        assert_eq!(commit.provenance.tree.len(), 64);
+        assert_eq!(commit.provenance.parents.len(), 1);
+        let root = commit.repo.root.clone().unwrap();
+        assert_eq!(root.len(), 64);
+        assert_ne!(root, commit.full_hash);
+        assert_eq!(RepoId::discover(dir.path()).unwrap(), commit.repo);
+
+        for length in [7, 10] {
+            let rules = crate::config::RulesConfig {
+                length,
+                ..Default::default()
+            };
+            let result = crate::scoring::score(&commit, &rules);
+            assert_eq!(result.hash, &commit.full_hash[..length]);
+            assert!(result.probability() > 0.0 && result.probability() <= 1.0);
+        }
+    }
+
+    #[test]
+    fn test_sha1_format() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        commit(&repo, "a@example.com", 1_000);
+        assert_eq!(
+            ObjectFormat::detect(dir.path()).unwrap(),
+            ObjectFormat::Sha1
+        );
+        assert_eq!(
+            Commit::latest_in(dir.path()).unwrap().format,
+            ObjectFormat::Sha1
+        );
+    }
+
+    #[test]
+    fn test_command_failed() {
+        let dir = tempfile::tempdir().unwrap();
+        let error = git_output(dir.path(), &["rev-parse", "HEAD"]).unwrap_err();
+        let LottoError::CommandFailed {
+            command,
+            status,
+            stderr,
+            ..
+        } = &error
+        else {
+            panic!("{:?}", error);
+        };
+        assert_eq!(command, "git rev-parse HEAD");
+        assert_eq!(*status, Some(128));
+        assert!(stderr.contains("not a git repository"), "{}", stderr);
+        assert_eq!(error.exit_code(), 128);
+        assert!(error.to_string().contains("not a git repository"));
+
+        let error = LottoError::from(Repository::open(dir.path()).err().unwrap());
+        assert!(std::error::Error::source(&error).is_some());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_killed_by_signal() {
+        use std::os::unix::process::ExitStatusExt;
+        // SIGKILL
+        let error = LottoError::command_failed(
+            "git commit",
+            std::process::ExitStatus::from_raw(9),
+            String::new(),
+        );
+        assert_eq!(error.exit_code(), 137);
+        assert_eq!(error.to_string(), "`git commit` was killed by signal 9");
+    }
+
+    #[test]
+    fn test_normalise_remote() {
+        for url in [
+            "git@github.com:joelynch/git-commit-poker.git",
+            "https://github.com/joelynch/git-commit-poker",
+            "ssh://git@github.com/joelynch/git-commit-poker.git/",
+        ] {
+            assert_eq!(
+                normalise_remote(url),
+                "github.com/joelynch/git-commit-poker"
+            );
+        }
+        assert_eq!(normalise_remote("/srv/git/repo.git"), "/srv/git/repo");
+    }
+}
@@ -1,56 +1,363 @@
 use std::{
-    fs::{File, OpenOptions},
-    io::{Seek, Write},
-    path::Path,
+    collections::BTreeMap,
+    fs::{self, File, OpenOptions},
+    io,
+    path::{Path, PathBuf},
 };
 
 use anyhow::anyhow;
+use chrono::Utc;
 use directories::ProjectDirs;
 use fs4::FileExt;
+use git2::Repository;
 use serde::{Deserialize, Serialize};
 
-use crate::{errors::LottoError, git::Commit, lotto::LottoResult};
+use crate::{
+    anticheat::Flag,
+    config::{Backend, StorageConfig},
+    errors::LottoError,
+    git::{Commit, Person, RepoId},
+    lotto::{LottoResult, RuleId},
+    period::Window,
+    refstore::RefHighScores,
+    remote::RemoteHighScores,
+    scoring::legacy_score,
+    sqlite::SqliteHighScores,
+    store::{self, Awards, StoreStatus, CURRENT_VERSION},
+};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ScoreInfo {
+    /// The path of the repository when the commit was scored.
     pub repo: String,
+    /// The root commit of the repository, see [`RepoId`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub root: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub remote: Option<String>,
     pub commit: String,
+    /// The commit's full hash, which unlike the short one can't collide.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub full_hash: Option<String>,
     pub score: u64,
     pub date: i64,
-    pub rules: Vec<String>,
+    pub rules: Vec<RuleId>,
+    /// How many characters of the hash were scored. Points are only
+    /// comparable between scores of the same length.
+    #[serde(default)]
+    pub length: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author: Option<Person>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub committer: Option<Person>,
+    /// The commit this one became when it was amended or rebased.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rewritten_as: Option<String>,
+    /// Whether another score in the commit's rewrite history counts instead.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub superseded: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tree: Option<String>,
+    /// Why the commit looks like it was made over and over until it scored.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub flags: Vec<Flag>,
+    /// Whether the score was flagged and doesn't count.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub disqualified: bool,
+    /// Whether the commit was rewritten by `commit-poker-util mine`.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub mined: bool,
 }
 
 impl ScoreInfo {
     pub fn new(result: &LottoResult, commit: &Commit) -> Self {
         ScoreInfo {
-            repo: commit.repo.to_str().unwrap().to_string(),
+            repo: commit.repo.path.to_string_lossy().to_string(),
+            root: commit.repo.root.clone(),
+            remote: commit.repo.remote.clone(),
             commit: commit.hash.clone(),
+            full_hash: Some(commit.full_hash.clone()).filter(|h| !h.is_empty()),
             score: result.total_points(),
             date: commit.date.parse().unwrap(),
-            rules: result.rules.iter().map(|r| r.name()).collect(),
+            rules: result.rules.iter().map(|r| r.id()).collect(),
+            length: result.hash.len(),
+            author: Some(commit.author.clone()),
+            committer: Some(commit.committer.clone()),
+            rewritten_as: None,
+            superseded: false,
+            tree: Some(commit.provenance.tree.clone()).filter(|t| !t.is_empty()),
+            flags: vec![],
+            disqualified: false,
+            mined: false,
         }
     }
+
+    /// Identifies the score across clones and machines: the repository's
+    /// root commit, or its path if it has none, and the commit's full hash,
+    /// or its short one for scores recorded before full hashes were kept.
+    pub fn key(&self) -> (String, String) {
+        let repo = self.root.clone().unwrap_or_else(|| self.repo.clone());
+        let commit = self
+            .full_hash
+            .clone()
+            .unwrap_or_else(|| self.commit.clone());
+        (repo, commit)
+    }
+
+    /// The names of the rules the commit hit, as they're shown.
+    pub fn rule_names(&self) -> String {
+        self.rules
+            .iter()
+            .map(|r| r.name(self.length))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// Whether the score is on the leaderboard.
+    pub fn counts(&self) -> bool {
+        !self.superseded && !self.disqualified && !self.mined
+    }
+
+    pub fn is_by(&self, email: &str) -> bool {
+        self.author
+            .as_ref()
+            .is_some_and(|a| a.email.eq_ignore_ascii_case(email))
+    }
+
+    /// Look up the author and committer of a score recorded before they were
+    /// kept, if its repository is still where it was.
+    pub fn fill_people(&mut self) -> bool {
+        let Ok(repo) = Repository::open(&self.repo) else {
+            return false;
+        };
+        let Ok(commit) = repo
+            .revparse_single(&self.commit)
+            .and_then(|o| o.peel_to_commit())
+        else {
+            return false;
+        };
+        self.author = Some(commit.author().into());
+        self.committer = Some(commit.committer().into());
+        true
+    }
+
+    pub fn is_from(&self, repo: &RepoId) -> bool {
+        match &self.root {
+            Some(root) => repo.is(root, self.remote.as_deref()),
+            None => same_path(Path::new(&self.repo), &repo.path),
+        }
+    }
+
+    /// Whether a leaderboard of hashes this long, for the window and author
+    /// if any, lists the score, counting or not.
+    pub fn is_listed(&self, length: usize, window: &Window, author: Option<&str>) -> bool {
+        self.length == length && window.contains(self.date) && author.is_none_or(|a| self.is_by(a))
+    }
 }
 
+/// How many previous versions of the JSON file are kept.
+const BACKUPS: usize = 3;
+
 pub trait HighScores {
     fn save(&mut self, result: ScoreInfo) -> Result<(), LottoError>;
-    fn load(&mut self, repo: Option<&Path>) -> Result<Vec<ScoreInfo>, LottoError>;
+    /// The scores that count, best first.
+    fn load(&mut self, repo: Option<&RepoId>) -> Result<Vec<ScoreInfo>, LottoError> {
+        let mut scores = self.load_all(repo)?;
+        scores.retain(ScoreInfo::counts);
+        Ok(scores)
+    }
+    /// Every score, including superseded ones, best first.
+    fn load_all(&mut self, repo: Option<&RepoId>) -> Result<Vec<ScoreInfo>, LottoError>;
+    /// The repository's best score that counts for hashes this long committed
+    /// in the window, other than the score of `except`.
+    fn best_in(
+        &mut self,
+        repo: &RepoId,
+        length: usize,
+        window: &Window,
+        except: &str,
+    ) -> Result<Option<ScoreInfo>, LottoError> {
+        Ok(self
+            .load(Some(repo))?
+            .into_iter()
+            .find(|s| s.length == length && s.commit != except && window.contains(s.date)))
+    }
+    /// Change every score at once, saving them if `f` returns true.
+    fn modify(&mut self, f: &mut dyn FnMut(&mut Vec<ScoreInfo>) -> bool) -> Result<(), LottoError>;
+    fn save_all(&mut self, results: Vec<ScoreInfo>) -> Result<(), LottoError> {
+        for result in results {
+            self.save(result)?;
+        }
+        Ok(())
+    }
+    /// Scores by the author with this email, best first.
+    fn load_by_author(&mut self, email: &str) -> Result<Vec<ScoreInfo>, LottoError> {
+        let mut scores = self.load(None)?;
+        scores.retain(|s| s.is_by(email));
+        Ok(scores)
+    }
+    /// Fill in the authors of scores recorded before authors were kept,
+    /// returning how many were filled in.
+    fn migrate_authors(&mut self) -> Result<usize, LottoError>;
+    /// The awards kept with the scores.
+    fn load_awards(&mut self) -> Result<Awards, LottoError>;
+    /// Change the awards, saving them if `f` returns true.
+    fn modify_awards(&mut self, f: &mut dyn FnMut(&mut Awards) -> bool) -> Result<(), LottoError>;
+    /// Report the schema version and size of the store.
+    fn check(&mut self) -> Result<StoreStatus, LottoError>;
+    /// Upgrade the store to the current schema, keeping a copy of the old one.
+    fn migrate(&mut self) -> Result<StoreStatus, LottoError>;
+}
+
+impl<T: HighScores + ?Sized> HighScores for Box<T> {
+    fn save(&mut self, result: ScoreInfo) -> Result<(), LottoError> {
+        (**self).save(result)
+    }
+
+    fn load(&mut self, repo: Option<&RepoId>) -> Result<Vec<ScoreInfo>, LottoError> {
+        (**self).load(repo)
+    }
+
+    fn load_all(&mut self, repo: Option<&RepoId>) -> Result<Vec<ScoreInfo>, LottoError> {
+        (**self).load_all(repo)
+    }
+
+    fn best_in(
+        &mut self,
+        repo: &RepoId,
+        length: usize,
+        window: &Window,
+        except: &str,
+    ) -> Result<Option<ScoreInfo>, LottoError> {
+        (**self).best_in(repo, length, window, except)
+    }
+
+    fn modify(&mut self, f: &mut dyn FnMut(&mut Vec<ScoreInfo>) -> bool) -> Result<(), LottoError> {
+        (**self).modify(f)
+    }
+
+    fn save_all(&mut self, results: Vec<ScoreInfo>) -> Result<(), LottoError> {
+        (**self).save_all(results)
+    }
+
+    fn load_by_author(&mut self, email: &str) -> Result<Vec<ScoreInfo>, LottoError> {
+        (**self).load_by_author(email)
+    }
+
+    fn migrate_authors(&mut self) -> Result<usize, LottoError> {
+        (**self).migrate_authors()
+    }
+
+    fn load_awards(&mut self) -> Result<Awards, LottoError> {
+        (**self).load_awards()
+    }
+
+    fn modify_awards(&mut self, f: &mut dyn FnMut(&mut Awards) -> bool) -> Result<(), LottoError> {
+        (**self).modify_awards(f)
+    }
+
+    fn check(&mut self) -> Result<StoreStatus, LottoError> {
+        (**self).check()
+    }
+
+    fn migrate(&mut self) -> Result<StoreStatus, LottoError> {
+        (**self).migrate()
+    }
 }
 
+/// Open the high score store chosen in the config.
+pub fn open(config: &StorageConfig) -> Result<Box<dyn HighScores>, LottoError> {
+    Ok(match config.backend {
+        Backend::Json => Box::new(HighScoresImpl::from_config(config)?),
+        Backend::Sqlite => Box::new(SqliteHighScores::from_config(config)?),
+        Backend::Git => Box::new(RefHighScores::from_config(config)?),
+        Backend::Remote => Box::new(RemoteHighScores::from_config(config)?),
+    })
+}
+
+/// The local data directory, where high scores are kept by default.
+pub(crate) fn data_dir() -> Result<PathBuf, LottoError> {
+    let dirs = ProjectDirs::from("com", "joelynch", "CommitLotto").ok_or(
+        LottoError::ApplicationDirError(anyhow!("could not get data dir")),
+    )?;
+    Ok(dirs.data_local_dir().to_path_buf())
+}
+
+/// Keeps every score in one JSON file. Writes go to a temporary file that
+/// replaces the old one, so a crash mid-write can't lose anything, and the
+/// last few versions are kept as backups.
 pub struct HighScoresImpl {
-    file: File,
+    path: PathBuf,
+    /// Held while reading or writing, since the file itself gets replaced.
+    lock: File,
 }
 
 impl HighScores for HighScoresImpl {
     fn save(&mut self, result: ScoreInfo) -> Result<(), LottoError> {
-        self.save_innner(result)
+        self.save_innner(vec![result])
+            .map_err(LottoError::ApplicationDirError)
+    }
+
+    fn save_all(&mut self, results: Vec<ScoreInfo>) -> Result<(), LottoError> {
+        self.save_innner(results)
             .map_err(LottoError::ApplicationDirError)
     }
 
-    fn load(&mut self, repo: Option<&Path>) -> Result<Vec<ScoreInfo>, LottoError> {
+    fn load_all(&mut self, repo: Option<&RepoId>) -> Result<Vec<ScoreInfo>, LottoError> {
         self.load_inner(repo)
             .map_err(LottoError::ApplicationDirError)
     }
+
+    fn modify(&mut self, f: &mut dyn FnMut(&mut Vec<ScoreInfo>) -> bool) -> Result<(), LottoError> {
+        self.update(f).map_err(LottoError::ApplicationDirError)
+    }
+
+    fn migrate_authors(&mut self) -> Result<usize, LottoError> {
+        self.migrate_authors_inner()
+            .map_err(LottoError::ApplicationDirError)
+    }
+
+    fn load_awards(&mut self) -> Result<Awards, LottoError> {
+        self.lock.lock_shared()?;
+        let read = self.read();
+        self.lock.unlock()?;
+        read.map(|contents| contents.awards)
+            .map_err(LottoError::ApplicationDirError)
+    }
+
+    fn modify_awards(&mut self, f: &mut dyn FnMut(&mut Awards) -> bool) -> Result<(), LottoError> {
+        self.update_contents(|contents| f(&mut contents.awards))
+            .map_err(LottoError::ApplicationDirError)
+    }
+
+    fn check(&mut self) -> Result<StoreStatus, LottoError> {
+        self.check_inner().map_err(LottoError::ApplicationDirError)
+    }
+
+    fn migrate(&mut self) -> Result<StoreStatus, LottoError> {
+        self.migrate_inner()
+            .map_err(LottoError::ApplicationDirError)
+    }
+}
+
+/// A JSON file as read, upgraded to the current version.
+struct Contents {
+    scores: Vec<ScoreInfo>,
+    awards: Awards,
+    /// The version on disk, 0 if there is no file yet.
+    version: u32,
+    damaged: bool,
+}
+
+impl Contents {
+    fn empty() -> Self {
+        Contents {
+            scores: vec![],
+            awards: Awards::default(),
+            version: 0,
+            damaged: false,
+        }
+    }
 }
 
 impl HighScoresImpl {
@@ -61,62 +368,492 @@ impl HighScoresImpl {
                 "could not get parent of path"
             )))?;
         std::fs::create_dir_all(parent)?;
-        let file = OpenOptions::new()
-            .read(true)
+        let lock = OpenOptions::new()
             .write(true)
             .create(true)
-            .open(path)
-            .unwrap();
-        Ok(HighScoresImpl { file })
+            .truncate(false)
+            .open(sibling(path, "lock"))?;
+        Ok(HighScoresImpl {
+            path: path.to_path_buf(),
+            lock,
+        })
     }
 
     pub fn standard() -> Result<Self, LottoError> {
-        let data_dir = ProjectDirs::from("com", "joelynch", "CommitLotto").ok_or(
-            LottoError::ApplicationDirError(anyhow!("could not get data dir")),
-        )?;
-        let path = data_dir.data_local_dir().join("highscores.json");
-        HighScoresImpl::new(&path)
-    }
-
-    fn load_inner(&mut self, repo: Option<&Path>) -> anyhow::Result<Vec<ScoreInfo>> {
-        self.file.lock_shared()?;
-        let mut scores = if let Some(repo) = repo {
-            let mut res = vec![];
-            for score in self.scores()? {
-                if refer_to_same_file(&score.repo, repo)? {
-                    res.push(score);
-                }
-            }
-            res
-        } else {
-            self.scores()?
-        };
-        self.file.unlock()?;
+        HighScoresImpl::new(&Self::standard_path()?)
+    }
+
+    pub fn standard_path() -> Result<PathBuf, LottoError> {
+        Ok(data_dir()?.join("highscores.json"))
+    }
+
+    pub fn from_config(config: &StorageConfig) -> Result<Self, LottoError> {
+        match &config.path {
+            Some(path) => HighScoresImpl::new(path),
+            None => HighScoresImpl::standard(),
+        }
+    }
+
+    pub fn backup_path(&self, n: usize) -> PathBuf {
+        sibling(&self.path, &format!("bak.{}", n))
+    }
+
+    fn load_inner(&mut self, repo: Option<&RepoId>) -> anyhow::Result<Vec<ScoreInfo>> {
+        self.lock.lock_shared()?;
+        let read = self.read();
+        self.lock.unlock()?;
+        let Contents {
+            mut scores,
+            damaged,
+            ..
+        } = read?;
+        if damaged {
+            eprintln!(
+                "{} is damaged, {} scores could be recovered from it and its backups",
+                self.path.display(),
+                scores.len()
+            );
+        }
+        if let Some(repo) = repo {
+            scores.retain(|s| s.is_from(repo));
+        }
         scores.sort_by_key(|s| -(s.score as i64));
         Ok(scores)
     }
 
-    fn save_innner(&mut self, result: ScoreInfo) -> anyhow::Result<()> {
-        self.file.lock_exclusive()?;
-        let mut scores = self.scores()?;
-        scores.push(result);
-        self.file.set_len(0)?;
-        self.file.rewind()?;
-        serde_json::to_writer(&self.file, &scores)?;
-        self.file.flush()?;
-        self.file.unlock()?;
+    fn save_innner(&mut self, results: Vec<ScoreInfo>) -> anyhow::Result<()> {
+        self.update(|scores| {
+            let changed = !results.is_empty();
+            scores.extend(results);
+            changed
+        })?;
+        Ok(())
+    }
+
+    fn migrate_authors_inner(&mut self) -> anyhow::Result<usize> {
+        let mut filled = 0;
+        self.update(|scores| {
+            filled = scores
+                .iter_mut()
+                .filter(|s| s.author.is_none())
+                .map(|s| s.fill_people())
+                .filter(|filled| *filled)
+                .count();
+            filled > 0
+        })?;
+        Ok(filled)
+    }
+
+    /// Change the scores while holding the lock, writing them back if `f`
+    /// returns true or the file had to be recovered.
+    fn update(&mut self, f: impl FnOnce(&mut Vec<ScoreInfo>) -> bool) -> anyhow::Result<()> {
+        self.update_contents(|contents| f(&mut contents.scores))
+    }
+
+    fn update_contents(&mut self, f: impl FnOnce(&mut Contents) -> bool) -> anyhow::Result<()> {
+        self.lock.lock_exclusive()?;
+        let result = self.read().and_then(|mut contents| {
+            if f(&mut contents) || contents.damaged {
+                self.write(&contents, contents.damaged)?;
+            }
+            Ok(())
+        });
+        self.lock.unlock()?;
+        result
+    }
+
+    fn check_inner(&mut self) -> anyhow::Result<StoreStatus> {
+        self.lock.lock_shared()?;
+        let read = self.read();
+        self.lock.unlock()?;
+        let contents = read?;
+        Ok(self.status(&contents))
+    }
+
+    fn migrate_inner(&mut self) -> anyhow::Result<StoreStatus> {
+        self.lock.lock_exclusive()?;
+        let result = self.read().and_then(|mut contents| {
+            let status = self.status(&contents);
+            if status.needs_migration() && !contents.damaged {
+                fs::copy(
+                    &self.path,
+                    sibling(&self.path, &format!("v{}", contents.version)),
+                )?;
+            }
+            if status.needs_migration() || contents.damaged {
+                self.write(&contents, contents.damaged)?;
+                contents.version = CURRENT_VERSION;
+                contents.damaged = false;
+            }
+            Ok(self.status(&contents))
+        });
+        self.lock.unlock()?;
+        result
+    }
+
+    fn status(&self, contents: &Contents) -> StoreStatus {
+        StoreStatus {
+            path: self.path.clone(),
+            version: contents.version,
+            current: CURRENT_VERSION,
+            scores: contents.scores.len(),
+            damaged: contents.damaged,
+        }
+    }
+
+    /// Read the scores, salvaging what can be read if the file is damaged.
+    /// Files written by a newer version are left alone.
+    fn read(&self) -> anyhow::Result<Contents> {
+        let bytes = match fs::read(&self.path) {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Contents::empty()),
+            Err(e) => return Err(e.into()),
+        };
+        if bytes.iter().all(u8::is_ascii_whitespace) {
+            return Ok(Contents::empty());
+        }
+        let value = serde_json::from_slice(&bytes);
+        if let Ok(value) = &value {
+            store::ensure_supported(value)?;
+        }
+        match value.map_err(Into::into).and_then(store::parse_with_awards) {
+            Ok((scores, awards, version)) => Ok(Contents {
+                scores,
+                awards,
+                version,
+                damaged: false,
+            }),
+            Err(_) => {
+                let (scores, awards) = self.recover(&String::from_utf8_lossy(&bytes));
+                Ok(Contents {
+                    scores,
+                    awards,
+                    version: CURRENT_VERSION,
+                    damaged: true,
+                })
+            }
+        }
+    }
+
+    /// Every readable score in a damaged file, plus any in the newest
+    /// readable backup that are missing from it, and that backup's awards.
+    fn recover(&self, text: &str) -> (Vec<ScoreInfo>, Awards) {
+        let mut scores = salvage(text);
+        let backup = (1..=BACKUPS).find_map(|n| {
+            let bytes = fs::read(self.backup_path(n)).ok()?;
+            store::parse_with_awards(serde_json::from_slice(&bytes).ok()?).ok()
+        });
+        let (backup, awards) = match backup {
+            Some((scores, awards, _)) => (scores, awards),
+            None => (vec![], Awards::default()),
+        };
+        for score in backup {
+            if !scores
+                .iter()
+                .any(|s| s.repo == score.repo && s.commit == score.commit)
+            {
+                scores.push(score);
+            }
+        }
+        (scores, awards)
+    }
+
+    fn write(&self, contents: &Contents, damaged: bool) -> anyhow::Result<()> {
+        if damaged {
+            let kept = sibling(&self.path, &format!("corrupt-{}", Utc::now().timestamp()));
+            fs::rename(&self.path, &kept)?;
+            eprintln!("The damaged file has been kept at {}", kept.display());
+        } else if self.path.exists() {
+            self.rotate_backups()?;
+        }
+        let tmp = sibling(&self.path, "tmp");
+        let mut file = io::BufWriter::new(File::create(&tmp)?);
+        store::to_writer(&mut file, &contents.scores, &contents.awards)?;
+        file.into_inner()?.sync_all()?;
+        fs::rename(&tmp, &self.path)?;
+        Ok(())
+    }
+
+    fn rotate_backups(&self) -> io::Result<()> {
+        for n in (1..BACKUPS).rev() {
+            if self.backup_path(n).exists() {
+                fs::rename(self.backup_path(n), self.backup_path(n + 1))?;
+            }
+        }
+        fs::copy(&self.path, self.backup_path(1))?;
         Ok(())
     }
+}
 
-    fn scores(&mut self) -> anyhow::Result<Vec<ScoreInfo>> {
-        if self.file.metadata()?.len() == 0 {
-            return Ok(vec![]);
+/// Parse every complete object in an array in damaged JSON, skipping any
+/// that don't parse as a score.
+fn salvage(text: &str) -> Vec<ScoreInfo> {
+    let mut scores = vec![];
+    // the open brackets, and where each object started
+    let mut open: Vec<(char, usize)> = vec![];
+    let (mut in_string, mut escaped) = (false, false);
+    for (i, c) in text.char_indices() {
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            '"' => in_string = true,
+            '{' | '[' => open.push((c, i)),
+            ']' => {
+                open.pop();
+            }
+            '}' => {
+                if let Some(('{', start)) = open.pop() {
+                    if open.last().is_some_and(|(c, _)| *c == '[') {
+                        if let Ok(mut score) = serde_json::from_str::<ScoreInfo>(&text[start..=i]) {
+                            // salvaged scores skip the migrations
+                            if score.length == 0 {
+                                match legacy_score(&score.commit) {
+                                    Some(result) => {
+                                        score.score = result.total_points();
+                                        score.rules = result.rules.iter().map(|r| r.id()).collect();
+                                        score.length = result.hash.len();
+                                    }
+                                    None => score.length = score.commit.len(),
+                                }
+                            }
+                            score.rules = score
+                                .rules
+                                .iter()
+                                .map(|r| {
+                                    RuleId::from_name(&r.to_string(), &score.commit, score.length)
+                                })
+                                .collect();
+                            scores.push(score);
+                        }
+                    }
+                }
+            }
+            _ => {}
         }
-        self.file.rewind()?;
-        serde_json::from_reader(&self.file).map_err(Into::into)
     }
+    scores
 }
 
-fn refer_to_same_file(path1: impl AsRef<Path>, path2: impl AsRef<Path>) -> anyhow::Result<bool> {
-    Ok(path1.as_ref().canonicalize()? == path2.as_ref().canonicalize()?)
+/// `path` with `.suffix` added to its file name.
+pub(crate) fn sibling(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".");
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuthorStats {
+    pub author: Person,
+    pub commits: usize,
+    pub total: u64,
+    pub best: ScoreInfo,
+}
+
+/// Group scores by author email, best scorer first. Scores without an author
+/// are left out.
+pub fn by_author(scores: &[ScoreInfo]) -> Vec<AuthorStats> {
+    let mut authors: BTreeMap<String, AuthorStats> = BTreeMap::new();
+    for score in scores {
+        let Some(author) = &score.author else {
+            continue;
+        };
+        let stats = authors
+            .entry(author.email.to_lowercase())
+            .or_insert_with(|| AuthorStats {
+                author: author.clone(),
+                commits: 0,
+                total: 0,
+                best: score.clone(),
+            });
+        stats.commits += 1;
+        stats.total += score.score;
+        if score.score > stats.best.score {
+            stats.best = score.clone();
+        }
+    }
+    let mut authors: Vec<AuthorStats> = authors.into_values().collect();
+    authors.sort_by_key(|a| -(a.best.score as i64));
+    authors
+}
+
+/// Compare paths, falling back to comparing them as written if either no
+/// longer exists.
+pub(crate) fn same_path(path1: &Path, path2: &Path) -> bool {
+    match (path1.canonicalize(), path2.canonicalize()) {
+        (Ok(path1), Ok(path2)) => path1 == path2,
+        _ => path1 == path2,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::test_util::{person, score_info};
+
+    #[test]
+    fn test_load_by_repo() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("highscores.json");
+        std::fs::write(
+            &path,
+            r#"[
+                {"repo": "/moved/away", "commit": "aaaaaaa", "score": 10, "date": 0, "rules": []},
+                {"repo": "/old/path", "root": "abc", "commit": "bbbbbbb", "score": 20, "date": 0, "rules": []},
+                {"repo": "/other", "root": "def", "commit": "ccccccc", "score": 30, "date": 0, "rules": []}
+            ]"#,
+        )
+        .unwrap();
+        let mut highscores = HighScoresImpl::new(&path).unwrap();
+        let repo = RepoId {
+            root: Some("abc".into()),
+            remote: None,
+            path: PathBuf::from("/moved/away"),
+        };
+        let mut commits: Vec<String> = highscores
+            .load(Some(&repo))
+            .unwrap()
+            .into_iter()
+            .map(|s| s.commit)
+            .collect();
+        // legacy scores are scored again, so only which ones load matters
+        commits.sort();
+        assert_eq!(commits, vec!["aaaaaaa", "bbbbbbb"]);
+        assert_eq!(highscores.load(None).unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_is_listed() {
+        let score = ScoreInfo {
+            date: 100,
+            ..score_info("aaaaaaa", 1)
+        };
+        let all = Window {
+            since: None,
+            until: None,
+        };
+        assert!(score.is_listed(7, &all, None));
+        assert!(score.is_listed(7, &all, Some("SOMEONE@example.com")));
+        assert!(!score.is_listed(7, &all, Some("other@example.com")));
+        assert!(!score.is_listed(8, &all, None));
+        let later = Window {
+            since: Some(101),
+            until: None,
+        };
+        assert!(!score.is_listed(7, &later, None));
+    }
+
+    #[test]
+    fn test_recovers_damaged_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("highscores.json");
+        let score = |commit: &str| {
+            format!(
+                r#"{{"repo": "/r", "commit": "{}", "score": 1, "date": 0, "rules": ["a \"}}"]}}"#,
+                commit
+            )
+        };
+        let mut highscores = HighScoresImpl::new(&path).unwrap();
+        std::fs::write(&path, format!("[{}]", score("aaaaaaa"))).unwrap();
+        highscores
+            .save(serde_json::from_str(&score("bbbbbbb")).unwrap())
+            .unwrap();
+        assert!(highscores.backup_path(1).exists());
+
+        // a write that was cut short
+        std::fs::write(
+            &path,
+            format!(
+                "[{}, {}, {{\"repo\": \"/r",
+                score("bbbbbbb"),
+                score("ccccccc")
+            ),
+        )
+        .unwrap();
+        let mut commits: Vec<String> = highscores
+            .load(None)
+            .unwrap()
+            .into_iter()
+            .map(|s| s.commit)
+            .collect();
+        commits.sort();
+        assert_eq!(commits, vec!["aaaaaaa", "bbbbbbb", "ccccccc"]);
+
+        highscores
+            .save(serde_json::from_str(&score("ddddddd")).unwrap())
+            .unwrap();
+        assert_eq!(highscores.load(None).unwrap().len(), 4);
+        let kept = std::fs::read_dir(dir.path())
+            .unwrap()
+            .filter(|e| {
+                let name = e.as_ref().unwrap().file_name();
+                name.to_string_lossy().contains("corrupt")
+            })
+            .count();
+        assert_eq!(kept, 1);
+
+        for commit in ["eeeeeee", "fffffff", "1111111", "2222222", "3333333"] {
+            highscores
+                .save(serde_json::from_str(&score(commit)).unwrap())
+                .unwrap();
+        }
+        assert!(highscores.backup_path(BACKUPS).exists());
+        assert!(!highscores.backup_path(BACKUPS + 1).exists());
+
+        // saving nothing doesn't push a real backup out
+        let newest = std::fs::read(highscores.backup_path(1)).unwrap();
+        highscores.save_all(vec![]).unwrap();
+        assert_eq!(std::fs::read(highscores.backup_path(1)).unwrap(), newest);
+    }
+
+    #[test]
+    fn test_migrate() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("highscores.json");
+        let old = r#"[{"repo": "/r", "commit": "aaaaaaa", "score": 1, "date": 0, "rules": []}]"#;
+        std::fs::write(&path, old).unwrap();
+        let mut highscores = HighScoresImpl::new(&path).unwrap();
+        assert!(highscores.check().unwrap().needs_migration());
+
+        let status = highscores.migrate().unwrap();
+        assert_eq!((status.version, status.scores), (CURRENT_VERSION, 1));
+        assert_eq!(std::fs::read_to_string(sibling(&path, "v1")).unwrap(), old);
+
+        std::fs::write(&path, r#"{"version": 1000, "scores": []}"#).unwrap();
+        assert!(highscores.save_all(vec![]).is_err());
+        assert!(std::fs::read_to_string(&path).unwrap().contains("1000"));
+    }
+
+    #[test]
+    fn test_by_author() {
+        let score = |email: &str, score: u64| ScoreInfo {
+            date: 0,
+            author: Some(person(email)),
+            ..score_info(&format!("{:07}", score), score)
+        };
+        let mut anonymous = score("", 1000);
+        anonymous.author = None;
+        let scores = vec![
+            score("a@example.com", 10),
+            score("b@example.com", 50),
+            score("A@example.com", 30),
+            anonymous,
+        ];
+        let authors = by_author(&scores);
+        assert_eq!(authors.len(), 2);
+        assert_eq!(authors[0].author.email, "b@example.com");
+        assert_eq!(authors[1].commits, 2);
+        assert_eq!(authors[1].total, 40);
+        assert_eq!(authors[1].best.score, 30);
+        assert!(scores[2].is_by("a@example.com"));
+    }
 }
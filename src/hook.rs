@@ -0,0 +1,277 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, Context};
+use git2::Repository;
+
+use crate::{errors::LottoError, git::git_output};
+
+/// First line after the shebang of every hook we write.
+const MARKER: &str = "# Installed by commit-poker";
+/// Added to the name of an existing hook when it is moved aside so it can
+/// still be run.
+const CHAINED: &str = "pre-commit-poker";
+/// Set when `commit-poker` runs `git commit` itself, so the hook doesn't
+/// score the commit a second time.
+pub const WRAPPED_ENV: &str = "COMMIT_POKER_WRAPPED";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HookKind {
+    /// Scores each new commit.
+    PostCommit,
+    /// Tells the high scores about amended and rebased commits.
+    PostRewrite,
+}
+
+impl HookKind {
+    pub const ALL: [HookKind; 2] = [HookKind::PostCommit, HookKind::PostRewrite];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            HookKind::PostCommit => "post-commit",
+            HookKind::PostRewrite => "post-rewrite",
+        }
+    }
+
+    /// The `commit-poker-util hook` subcommand the hook runs.
+    fn action(&self) -> &'static str {
+        match self {
+            HookKind::PostCommit => "run",
+            HookKind::PostRewrite => "rewritten",
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum HookStatus {
+    NotInstalled,
+    Installed {
+        chained: bool,
+    },
+    /// A hook exists that wasn't written by us.
+    Foreign,
+}
+
+pub struct Hook {
+    dir: PathBuf,
+    kind: HookKind,
+}
+
+impl Hook {
+    pub fn new(dir: PathBuf, kind: HookKind) -> Self {
+        Hook { dir, kind }
+    }
+
+    /// The hook in the repository's hooks directory, honouring
+    /// `core.hooksPath`.
+    pub fn for_repo(repo: &Repository, kind: HookKind) -> Result<Self, LottoError> {
+        let configured = repo
+            .config()
+            .and_then(|c| c.get_path("core.hooksPath"))
+            .ok();
+        // the git directory shared by all worktrees
+        let common = repo.commondir();
+        let dir = match configured {
+            Some(path) if path.is_absolute() => path,
+            Some(path) => repo.workdir().unwrap_or(common).join(path),
+            None => common.join("hooks"),
+        };
+        Ok(Hook::new(dir, kind))
+    }
+
+    pub fn discover(kind: HookKind) -> Result<Self, LottoError> {
+        match Repository::discover(".") {
+            Ok(repo) => Hook::for_repo(&repo, kind),
+            // libgit2 can't open SHA-256 repositories, but git knows where
+            // their hooks go
+            Err(_) => {
+                let dir = git_output(Path::new("."), &["rev-parse", "--git-path", "hooks"])?;
+                Ok(Hook::new(PathBuf::from(dir.trim_end()), kind))
+            }
+        }
+    }
+
+    pub fn kind(&self) -> HookKind {
+        self.kind
+    }
+
+    pub fn path(&self) -> PathBuf {
+        self.dir.join(self.kind.name())
+    }
+
+    fn chained_name(&self) -> String {
+        format!("{}.{}", self.kind.name(), CHAINED)
+    }
+
+    fn chained_path(&self) -> PathBuf {
+        self.dir.join(self.chained_name())
+    }
+
+    pub fn status(&self) -> HookStatus {
+        match fs::read_to_string(self.path()) {
+            Ok(script) if script.contains(MARKER) => HookStatus::Installed {
+                chained: self.chained_path().exists(),
+            },
+            Ok(_) => HookStatus::Foreign,
+            Err(_) => HookStatus::NotInstalled,
+        }
+    }
+
+    /// Write a hook that runs `program hook run` (or `rewritten`), moving any
+    /// existing hook aside to be run first.
+    pub fn install(&self, program: &Path) -> Result<HookStatus, LottoError> {
+        self.install_inner(program).map_err(LottoError::HookError)?;
+        Ok(self.status())
+    }
+
+    /// Remove our hook, putting back any hook it replaced. Returns whether
+    /// there was anything to remove.
+    pub fn uninstall(&self) -> Result<bool, LottoError> {
+        self.uninstall_inner().map_err(LottoError::HookError)
+    }
+
+    fn install_inner(&self, program: &Path) -> anyhow::Result<()> {
+        fs::create_dir_all(&self.dir)
+            .with_context(|| format!("could not create {}", self.dir.display()))?;
+        if self.status() == HookStatus::Foreign {
+            if self.chained_path().exists() {
+                return Err(anyhow!(
+                    "{} already exists, not overwriting it",
+                    self.chained_path().display()
+                ));
+            }
+            fs::rename(self.path(), self.chained_path())?;
+        }
+        let chained = self.chained_name();
+        let program = shell_quote(&program.to_string_lossy());
+        let action = self.kind.action();
+        let script = match self.kind {
+            HookKind::PostCommit => format!(
+                "#!/bin/sh\n\
+                 {MARKER}\n\
+                 hooks_dir=$(dirname \"$0\")\n\
+                 if [ -x \"$hooks_dir/{chained}\" ]; then\n    \
+                     \"$hooks_dir/{chained}\" \"$@\"\n\
+                 fi\n\
+                 exec {program} hook {action}\n"
+            ),
+            // git writes the rewritten commits to stdin, which both hooks need
+            HookKind::PostRewrite => format!(
+                "#!/bin/sh\n\
+                 {MARKER}\n\
+                 hooks_dir=$(dirname \"$0\")\n\
+                 rewritten=$(cat)\n\
+                 if [ -x \"$hooks_dir/{chained}\" ]; then\n    \
+                     printf '%s\\n' \"$rewritten\" | \"$hooks_dir/{chained}\" \"$@\"\n\
+                 fi\n\
+                 printf '%s\\n' \"$rewritten\" | exec {program} hook {action} \"$@\"\n"
+            ),
+        };
+        fs::write(self.path(), script)?;
+        make_executable(&self.path())?;
+        Ok(())
+    }
+
+    fn uninstall_inner(&self) -> anyhow::Result<bool> {
+        match self.status() {
+            HookStatus::NotInstalled => Ok(false),
+            HookStatus::Foreign => Err(anyhow!(
+                "{} was not installed by commit-poker",
+                self.path().display()
+            )),
+            HookStatus::Installed { chained } => {
+                fs::remove_file(self.path())?;
+                if chained {
+                    fs::rename(self.chained_path(), self.path())?;
+                }
+                Ok(true)
+            }
+        }
+    }
+}
+
+/// Quote a string for the shell, in single quotes.
+fn shell_quote(text: &str) -> String {
+    format!("'{}'", text.replace('\'', "'\\''"))
+}
+
+#[cfg(unix)]
+fn make_executable(path: &Path) -> anyhow::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(0o755))?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn make_executable(_path: &Path) -> anyhow::Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_install_chains_existing_hook() {
+        let dir = tempfile::tempdir().unwrap();
+        let hook = Hook::new(dir.path().join("hooks"), HookKind::PostCommit);
+        assert_eq!(hook.status(), HookStatus::NotInstalled);
+        assert!(!hook.uninstall().unwrap());
+
+        fs::create_dir_all(dir.path().join("hooks")).unwrap();
+        fs::write(hook.path(), "#!/bin/sh\necho mine\n").unwrap();
+        assert_eq!(hook.status(), HookStatus::Foreign);
+        assert!(hook.uninstall().is_err());
+
+        let status = hook.install(Path::new("/bin/commit-poker-util")).unwrap();
+        assert_eq!(status, HookStatus::Installed { chained: true });
+        let script = fs::read_to_string(hook.path()).unwrap();
+        assert!(script.contains("exec '/bin/commit-poker-util' hook run"));
+        // installing again only updates our own hook
+        hook.install(Path::new("/bin/commit-poker-util")).unwrap();
+        assert_eq!(
+            fs::read_to_string(hook.chained_path()).unwrap(),
+            "#!/bin/sh\necho mine\n"
+        );
+
+        assert!(hook.uninstall().unwrap());
+        assert_eq!(hook.status(), HookStatus::Foreign);
+        assert!(!hook.chained_path().exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_quotes_program() {
+        let dir = tempfile::tempdir().unwrap();
+        let program = dir.path().join("it's here");
+        fs::write(&program, "#!/bin/sh\necho \"$@\"\n").unwrap();
+        make_executable(&program).unwrap();
+        let hook = Hook::new(dir.path().join("hooks"), HookKind::PostCommit);
+        hook.install(&program).unwrap();
+        let output = std::process::Command::new(hook.path()).output().unwrap();
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "hook run\n");
+    }
+
+    #[test]
+    fn test_hooks_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        assert_eq!(
+            Hook::for_repo(&repo, HookKind::PostCommit).unwrap().path(),
+            repo.path().join("hooks").join("post-commit")
+        );
+        repo.config()
+            .unwrap()
+            .set_str("core.hooksPath", ".githooks")
+            .unwrap();
+        assert_eq!(
+            Hook::for_repo(&repo, HookKind::PostRewrite).unwrap().path(),
+            repo.workdir()
+                .unwrap()
+                .join(".githooks")
+                .join("post-rewrite")
+        );
+    }
+}
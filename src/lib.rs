@@ -1,5 +1,27 @@
+pub mod achievements;
+pub mod anticheat;
+pub mod backfill;
+pub mod cards;
+pub mod combinatorics;
+pub mod commit;
+pub mod config;
 pub mod errors;
 pub mod git;
 pub mod highscores;
+pub mod hook;
 pub mod lotto;
+pub mod mine;
 pub mod output;
+pub mod period;
+pub mod refstore;
+pub mod remote;
+pub mod rewrite;
+pub mod scoring;
+pub mod seasons;
+pub mod server;
+pub mod simulation;
+pub mod sqlite;
+pub mod stats;
+pub mod store;
+#[cfg(test)]
+mod test_util;
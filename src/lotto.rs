@@ -1,37 +1,305 @@
-use std::collections::BTreeMap;
+use std::{collections::BTreeMap, fmt};
 
-use num_bigint::BigInt;
-use num_integer::binomial;
-use num_rational::BigRational;
-use num_traits::{cast::ToPrimitive, Pow};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    cards::{self, Card, Deal, HandRank},
+    combinatorics::{FlushKind, Hand},
+    config::RulesConfig,
+};
 
 pub struct LottoResult<'a> {
     pub hash: &'a str,
+    pub full_hash: Option<&'a str>,
     pub rules: Vec<Box<dyn LottoRuleFamily<'a> + 'a>>,
+    /// The cards dealt from the full hash, if any.
+    pub cards: Vec<Card>,
 }
 
 impl<'a> LottoResult<'a> {
     pub fn new(hash: &'a str) -> Self {
+        Self::with_rules(hash, &RulesConfig::default())
+    }
+
+    /// Score the hash with only the rules enabled in the config.
+    pub fn with_rules(hash: &'a str, config: &RulesConfig) -> Self {
         let mut rules: Vec<Box<dyn LottoRuleFamily<'a> + 'a>> = vec![];
-        if let Some(rule) = NOfAKind::new(hash) {
+        if let Some(rule) = NOfAKind::new(hash).filter(|_| config.n_of_a_kind) {
             rules.push(Box::new(rule));
         }
-        if let Some(rule) = Flush::new(hash) {
+        if let Some(rule) = Flush::new(hash).filter(|_| config.flush) {
             rules.push(Box::new(rule));
         }
-        if let Some(rule) = Straight::new(hash) {
+        if let Some(rule) = Straight::new(hash, config.min_straight).filter(|_| config.straight) {
             rules.push(Box::new(rule));
         }
         rules.sort_by_key(|r| -(r.points() as i64));
-        Self { hash, rules }
+        Self {
+            hash,
+            full_hash: None,
+            rules,
+            cards: vec![],
+        }
+    }
+
+    pub fn with_full_hash(mut self, full_hash: &'a str) -> Self {
+        self.full_hash = Some(full_hash);
+        self
+    }
+
+    /// Deal a poker hand from the full hash, scoring it if it makes a pair or
+    /// better.
+    pub fn with_cards(mut self, full_hash: &str, deal: Deal) -> Self {
+        let Some(dealt) = cards::deal(full_hash, deal) else {
+            return self;
+        };
+        let (rank, best) = cards::best_hand(&dealt);
+        if rank > HandRank::HighCard {
+            self.rules.push(Box::new(PokerHand { deal, rank, best }));
+            self.rules.sort_by_key(|r| -(r.points() as i64));
+        }
+        self.cards = dealt;
+        self
+    }
+
+    /// The hand made up of every rule that fired on the hash.
+    pub fn hand(&self) -> Hand {
+        self.rules
+            .iter()
+            .fold(Hand::new(self.hash.len()), |hand, rule| {
+                rule.constrain(hand)
+            })
+    }
+
+    /// The exact probability of every rule firing together.
+    pub fn probability(&self) -> f64 {
+        self.hand().probability()
     }
 
     pub fn total_points(&self) -> u64 {
-        self.rules.iter().map(|r| r.points()).sum()
+        if self.rules.is_empty() {
+            return 0;
+        }
+        (100.0 / self.probability()).ceil() as u64
+    }
+}
+
+/// What a rule scored, kept with each score so that hits can be counted and
+/// their odds worked out later without parsing names or knowing the config
+/// they were scored with.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(into = "String", from = "String")]
+pub enum RuleId {
+    /// One character repeated this many times.
+    OfAKind(usize),
+    /// This many characters repeated twice each.
+    Pairs(usize),
+    /// `count` characters repeated `kind` times each, `kind` being over 2.
+    Kinds {
+        count: usize,
+        kind: usize,
+    },
+    /// Characters repeated different numbers of times.
+    FullHouse,
+    Flush(FlushKind),
+    /// The longest run of consecutive characters, the whole hash or part of it.
+    Straight(usize),
+    Poker(Deal, HandRank),
+    /// A rule recorded by name before ids were kept, which can't be told
+    /// from the name and short hash alone.
+    Legacy(String),
+}
+
+impl RuleId {
+    /// Every rule the config can score on a hash of this length.
+    pub fn all(config: &RulesConfig, length: usize) -> Vec<RuleId> {
+        let mut ids = vec![];
+        if config.n_of_a_kind {
+            ids.extend((2..=length).map(RuleId::OfAKind));
+            ids.extend((2..=length / 2).map(RuleId::Pairs));
+            for kind in 3..=length {
+                ids.extend((2..=length / kind).map(|count| RuleId::Kinds { count, kind }));
+            }
+            if length >= 5 {
+                ids.push(RuleId::FullHouse);
+            }
+        }
+        if config.flush {
+            ids.extend([FlushKind::Letters, FlushKind::Numbers].map(RuleId::Flush));
+        }
+        if config.straight {
+            ids.extend((config.min_straight.max(1)..=length.min(16)).map(RuleId::Straight));
+        }
+        if config.poker {
+            ids.extend(
+                HandRank::ALL
+                    .iter()
+                    .filter(|r| **r > HandRank::HighCard)
+                    .map(|r| RuleId::Poker(config.deal, *r)),
+            );
+        }
+        ids
+    }
+
+    /// The name shown for the rule on a hash of this length.
+    pub fn name(&self, length: usize) -> String {
+        match self {
+            RuleId::OfAKind(kind) => format!("{} of a kind", kind),
+            RuleId::Pairs(count) => format!("{} pairs!", count),
+            RuleId::Kinds { count, kind } => format!("{} x {} of a kind!", count, kind),
+            RuleId::FullHouse => "FULL HOUSE!!".into(),
+            RuleId::Flush(FlushKind::Letters) => "Flush: all letters".into(),
+            RuleId::Flush(FlushKind::Numbers) => "Flush: all numbers".into(),
+            RuleId::Straight(run) if *run >= length => "Straight".into(),
+            RuleId::Straight(run) => format!("Partial straight of {}", run),
+            RuleId::Poker(_, rank) => format!("Poker hand: {}", rank),
+            RuleId::Legacy(name) => name.clone(),
+        }
+    }
+
+    /// The probability that a random hash of this length gets the rule,
+    /// whatever else it scores. `None` for a legacy rule.
+    pub fn probability(&self, length: usize) -> Option<f64> {
+        let hand = || Hand::new(length);
+        let kinds = |count: usize, kind: usize| hand().with_kinds(vec![kind; count]).probability();
+        let probability = match self {
+            RuleId::OfAKind(kind) => kinds(1, *kind),
+            RuleId::Pairs(count) => kinds(*count, 2),
+            RuleId::Kinds { count, kind } => kinds(*count, *kind),
+            RuleId::FullHouse => {
+                // anything repeated that isn't one kind of repeat
+                let mut uniform = 0.0;
+                for kind in 2..=length {
+                    for count in 1..=length / kind {
+                        uniform += kinds(count, kind);
+                    }
+                }
+                1.0 - hand().with_kinds([]).probability() - uniform
+            }
+            RuleId::Flush(kind) => hand().with_flush(*kind).probability(),
+            RuleId::Straight(run) => hand().with_straight(*run).probability(),
+            RuleId::Poker(deal, rank) => rank.probability(*deal),
+            RuleId::Legacy(_) => return None,
+        };
+        Some(probability)
+    }
+
+    /// The id of a rule recorded by id or, before ids were kept, by name,
+    /// given the short hash it was recorded with and how much of the hash was
+    /// scored.
+    pub fn from_name(name: &str, short_hash: &str, length: usize) -> RuleId {
+        let name = match RuleId::from(name.to_string()) {
+            RuleId::Legacy(name) => name,
+            id => return id,
+        };
+        let name = name.as_str();
+        let scored = &short_hash[..length.min(short_hash.len())];
+        let number = |text: &str| text.parse::<usize>().ok();
+        let legacy = || RuleId::Legacy(name.to_string());
+        match name {
+            "FULL HOUSE!!" => RuleId::FullHouse,
+            "Flush: all letters" => RuleId::Flush(FlushKind::Letters),
+            "Flush: all numbers" => RuleId::Flush(FlushKind::Numbers),
+            "Flush" if scored.starts_with(|c: char| c.is_ascii_digit()) => {
+                RuleId::Flush(FlushKind::Numbers)
+            }
+            "Flush" => RuleId::Flush(FlushKind::Letters),
+            "Straight" => RuleId::Straight(length),
+            // the run can only be found again if all of the hash was kept
+            "Partial straight" if scored.len() == length && length > 0 => {
+                Straight::new(scored, 1).map_or_else(legacy, |s| RuleId::Straight(s.run.len()))
+            }
+            name => {
+                if let Some(count) = name.strip_suffix(" pairs!").and_then(number) {
+                    RuleId::Pairs(count)
+                } else if let Some((count, kind)) = name
+                    .strip_suffix(" of a kind!")
+                    .and_then(|rest| rest.split_once(" x "))
+                {
+                    match (number(count), number(kind)) {
+                        (Some(count), Some(kind)) => RuleId::Kinds { count, kind },
+                        _ => legacy(),
+                    }
+                } else if let Some(kind) = name.strip_suffix(" of a kind").and_then(number) {
+                    RuleId::OfAKind(kind)
+                } else {
+                    legacy()
+                }
+            }
+        }
+    }
+}
+
+fn rank_id(rank: HandRank) -> String {
+    rank.name().to_lowercase().replace(' ', "-")
+}
+
+fn deal_id(deal: Deal) -> &'static str {
+    match deal {
+        Deal::FiveCard => "five-card",
+        Deal::Holdem => "holdem",
+    }
+}
+
+impl fmt::Display for RuleId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RuleId::OfAKind(kind) => write!(f, "{}-of-a-kind", kind),
+            RuleId::Pairs(count) => write!(f, "{}-pairs", count),
+            RuleId::Kinds { count, kind } => write!(f, "{}x{}-of-a-kind", count, kind),
+            RuleId::FullHouse => write!(f, "full-house"),
+            RuleId::Flush(FlushKind::Letters) => write!(f, "flush-letters"),
+            RuleId::Flush(FlushKind::Numbers) => write!(f, "flush-numbers"),
+            RuleId::Straight(run) => write!(f, "straight-{}", run),
+            RuleId::Poker(deal, rank) => write!(f, "poker-{}-{}", deal_id(*deal), rank_id(*rank)),
+            RuleId::Legacy(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+impl From<RuleId> for String {
+    fn from(id: RuleId) -> String {
+        id.to_string()
+    }
+}
+
+/// Anything that isn't an id is kept as a legacy name.
+impl From<String> for RuleId {
+    fn from(text: String) -> RuleId {
+        let number = |text: &str| text.parse::<usize>().ok();
+        let parsed = match text.as_str() {
+            "full-house" => Some(RuleId::FullHouse),
+            "flush-letters" => Some(RuleId::Flush(FlushKind::Letters)),
+            "flush-numbers" => Some(RuleId::Flush(FlushKind::Numbers)),
+            text => {
+                if let Some(run) = text.strip_prefix("straight-") {
+                    number(run).map(RuleId::Straight)
+                } else if let Some(hand) = text.strip_prefix("poker-") {
+                    [Deal::FiveCard, Deal::Holdem].into_iter().find_map(|deal| {
+                        let rank = hand.strip_prefix(deal_id(deal))?.strip_prefix('-')?;
+                        let rank = HandRank::ALL.into_iter().find(|r| rank_id(*r) == rank)?;
+                        Some(RuleId::Poker(deal, rank))
+                    })
+                } else if let Some(count) = text.strip_suffix("-pairs") {
+                    number(count).map(RuleId::Pairs)
+                } else if let Some(kinds) = text.strip_suffix("-of-a-kind") {
+                    match kinds.split_once('x') {
+                        Some((count, kind)) => number(count)
+                            .zip(number(kind))
+                            .map(|(count, kind)| RuleId::Kinds { count, kind }),
+                        None => number(kinds).map(RuleId::OfAKind),
+                    }
+                } else {
+                    None
+                }
+            }
+        };
+        parsed.unwrap_or(RuleId::Legacy(text))
     }
 }
 
 pub trait LottoRuleFamily<'a> {
+    fn id(&self) -> RuleId;
     fn name(&self) -> String;
     fn description(&self) -> String;
     fn probability(&self) -> f64;
@@ -39,6 +307,8 @@ pub trait LottoRuleFamily<'a> {
         (100.0 / self.probability()).ceil() as u64
     }
     fn positions(&self) -> Vec<Vec<usize>>;
+    /// Add the constraint this rule places on the hash to the hand.
+    fn constrain(&self, hand: Hand) -> Hand;
 }
 
 pub struct NOfAKind<'a> {
@@ -65,6 +335,20 @@ impl<'a> NOfAKind<'a> {
 }
 
 impl<'a> LottoRuleFamily<'a> for NOfAKind<'a> {
+    fn id(&self) -> RuleId {
+        let count = self.values.len();
+        let kind = self.values.values().next().copied().unwrap_or_default();
+        if count == 1 {
+            RuleId::OfAKind(kind)
+        } else if self.values.values().all(|v| *v == 2) {
+            RuleId::Pairs(count)
+        } else if self.values.values().all(|v| *v == kind) {
+            RuleId::Kinds { count, kind }
+        } else {
+            RuleId::FullHouse
+        }
+    }
+
     fn name(&self) -> String {
         if self.values.is_empty() {
             return "N/A".into();
@@ -95,15 +379,7 @@ impl<'a> LottoRuleFamily<'a> for NOfAKind<'a> {
         if self.values.is_empty() {
             return 0.0;
         }
-        let numerator = self
-            .values
-            .values()
-            .map(|v| binomial(BigInt::from(self.commit.len()), BigInt::from(*v)))
-            .fold(BigInt::from(1), |acc, x| acc * x)
-            * BigInt::from(15)
-                .pow((self.commit.len() - self.values.values().sum::<usize>()) as u32);
-        let denominator = BigInt::from(16).pow(self.commit.len() as u32);
-        BigRational::new(numerator, denominator).to_f64().unwrap()
+        self.constrain(Hand::new(self.commit.len())).probability()
     }
 
     fn positions(&self) -> Vec<Vec<usize>> {
@@ -121,6 +397,10 @@ impl<'a> LottoRuleFamily<'a> for NOfAKind<'a> {
         }
         positions
     }
+
+    fn constrain(&self, hand: Hand) -> Hand {
+        hand.with_kinds(self.values.values().copied())
+    }
 }
 
 pub struct Flush<'a> {
@@ -147,6 +427,14 @@ impl<'a> Flush<'a> {
 }
 
 impl<'a> LottoRuleFamily<'a> for Flush<'a> {
+    fn id(&self) -> RuleId {
+        if self.letters {
+            RuleId::Flush(FlushKind::Letters)
+        } else {
+            RuleId::Flush(FlushKind::Numbers)
+        }
+    }
+
     fn name(&self) -> String {
         "Flush".into()
     }
@@ -160,11 +448,7 @@ impl<'a> LottoRuleFamily<'a> for Flush<'a> {
     }
 
     fn probability(&self) -> f64 {
-        if self.letters {
-            (10.0 / 16.0).pow(self.commit.len() as f64)
-        } else {
-            (6.0 / 16.0).pow(self.commit.len() as f64)
-        }
+        self.constrain(Hand::new(self.commit.len())).probability()
     }
 
     fn positions(&self) -> Vec<Vec<usize>> {
@@ -178,6 +462,14 @@ impl<'a> LottoRuleFamily<'a> for Flush<'a> {
         }
         vec![positions]
     }
+
+    fn constrain(&self, hand: Hand) -> Hand {
+        if self.letters {
+            hand.with_flush(FlushKind::Letters)
+        } else {
+            hand.with_flush(FlushKind::Numbers)
+        }
+    }
 }
 
 struct Straight<'a> {
@@ -185,14 +477,13 @@ struct Straight<'a> {
     run: String,
 }
 
-static MIN_STRAIGHT: u16 = 4;
-
 impl<'a> Straight<'a> {
-    pub fn new(commit: &'a str) -> Option<Self> {
+    pub fn new(commit: &'a str, min_straight: usize) -> Option<Self> {
         let mut sorted = commit.chars().collect::<Vec<char>>();
         sorted.sort();
+        sorted.dedup();
         let mut iter = sorted.into_iter();
-        let mut last = iter.next().unwrap();
+        let mut last = iter.next()?;
         let mut longest_run = String::new();
         let mut current_run = String::from(last);
 
@@ -212,7 +503,7 @@ impl<'a> Straight<'a> {
             longest_run = current_run;
         }
 
-        if longest_run.len() >= MIN_STRAIGHT as usize {
+        if longest_run.len() >= min_straight {
             Some(Straight {
                 commit,
                 run: longest_run,
@@ -232,6 +523,10 @@ impl<'a> Straight<'a> {
 }
 
 impl<'a> LottoRuleFamily<'a> for Straight<'a> {
+    fn id(&self) -> RuleId {
+        RuleId::Straight(self.run.len())
+    }
+
     fn name(&self) -> String {
         if self.commit.len() == self.run.len() {
             "Straight".into()
@@ -245,18 +540,7 @@ impl<'a> LottoRuleFamily<'a> for Straight<'a> {
     }
 
     fn probability(&self) -> f64 {
-        let numerator = // which chars contain the straight
-            permutations(
-                self.commit.len() as u32,
-                self.run.len() as u32)
-            // chars don't contain the straight can be anything
-            * BigInt::from(16).pow((self.commit.len() - self.run.len()) as u32)
-            // first char choices
-            * (16 - self.run.len());
-        // straight ordering
-
-        let denominator = BigInt::from(16).pow(self.commit.len() as u32);
-        BigRational::new(numerator, denominator).to_f64().unwrap()
+        self.constrain(Hand::new(self.commit.len())).probability()
     }
 
     fn positions(&self) -> Vec<Vec<usize>> {
@@ -271,25 +555,53 @@ impl<'a> LottoRuleFamily<'a> for Straight<'a> {
         }
         vec![positions]
     }
+
+    fn constrain(&self, hand: Hand) -> Hand {
+        hand.with_straight(self.run.len())
+    }
+}
+
+pub struct PokerHand {
+    deal: Deal,
+    pub rank: HandRank,
+    /// The five cards making up the hand.
+    pub best: Vec<Card>,
 }
 
-fn permutations(n: u32, k: u32) -> BigInt {
-    let mut n = n;
-    if n == k {
-        return BigInt::from(1);
+impl<'a> LottoRuleFamily<'a> for PokerHand {
+    fn id(&self) -> RuleId {
+        RuleId::Poker(self.deal, self.rank)
+    }
+
+    fn name(&self) -> String {
+        format!("Poker hand: {}", self.rank)
+    }
+
+    fn description(&self) -> String {
+        self.best
+            .iter()
+            .map(|c| c.to_string())
+            .collect::<Vec<String>>()
+            .join(" ")
+    }
+
+    fn probability(&self) -> f64 {
+        self.rank.probability(self.deal)
     }
-    let mut result = BigInt::from(n);
-    n -= 1;
-    while n != k {
-        result *= n;
-        n -= 1;
+
+    fn positions(&self) -> Vec<Vec<usize>> {
+        vec![]
+    }
+
+    fn constrain(&self, hand: Hand) -> Hand {
+        hand.with_cards(self.deal, self.rank)
     }
-    result
 }
 
 #[cfg(test)]
 mod test {
     use num_traits::abs;
+    use rand::SeedableRng;
 
     use super::*;
 
@@ -298,13 +610,16 @@ mod test {
         let commit = "aabbccddeeff";
         let rule = NOfAKind::new(commit).unwrap();
         assert_eq!(rule.name(), "6 pairs!");
-        assert_eq!(rule.description(), "2 as, 2 bs, 2 cs, 2 ds, 2 es, 2 fs");
+        assert_eq!(
+            rule.description(),
+            "2 x a, 2 x b, 2 x c, 2 x d, 2 x e, 2 x f"
+        );
         assert!(
-            abs(rule.probability() - 0.00029364581882) < 0.00000001,
+            abs(rule.probability() - 0.00021293216150) < 0.00000001,
             "{}",
             rule.probability()
         );
-        assert_eq!(rule.points(), 340547);
+        assert_eq!(rule.points(), 469634);
         let mut pos = rule.positions();
         pos.sort();
         assert_eq!(
@@ -325,13 +640,13 @@ mod test {
         let commit = "abcdef";
         let rule = Flush::new(commit).unwrap();
         assert_eq!(rule.name(), "Flush");
-        assert_eq!(rule.description(), "All letters");
+        assert_eq!(rule.description(), "all letters");
         assert!(
-            abs(rule.probability() - 0.05960464477) < 0.00000001,
+            abs(rule.probability() - 0.00278091430) < 0.00000001,
             "{}",
             rule.probability()
         );
-        assert_eq!(rule.points(), 1678);
+        assert_eq!(rule.points(), 35960);
         let pos = rule.positions();
         assert_eq!(pos, vec![vec![0, 1, 2, 3, 4, 5],]);
     }
@@ -339,22 +654,120 @@ mod test {
     #[test]
     fn test_straight() {
         let commit = "abcdef";
-        let rule = Straight::new(commit).unwrap();
+        let rule = Straight::new(commit, 4).unwrap();
         assert_eq!(rule.name(), "Straight");
         assert_eq!(rule.description(), "abcdef");
         assert!(
-            abs(rule.probability() - 0.0000005960464477539063) < 0.00000000001,
+            abs(rule.probability() - 0.0004720687866210937) < 0.00000000001,
             "{}",
             rule.probability()
         );
-        assert_eq!(rule.points(), 167772160);
+        assert_eq!(rule.points(), 211834);
+        assert!(Straight::new("", 1).is_none());
         let pos = rule.positions();
         assert_eq!(pos, vec![vec![0, 1, 2, 3, 4, 5],]);
     }
 
     #[test]
-    fn test_permutations() {
-        assert_eq!(permutations(5, 3), BigInt::from(20));
-        assert_eq!(permutations(5, 5), BigInt::from(1));
+    fn test_joint_hand() {
+        let result = LottoResult::new("abcdef");
+        assert_eq!(result.rules.len(), 2);
+        // only the 6! orderings of abcdef are both a flush and a straight
+        assert!(
+            abs(result.probability() - 720.0 / 16f64.powi(6)) < 0.00000000001,
+            "{}",
+            result.probability()
+        );
+        assert_eq!(result.total_points(), 2330169);
+        assert_eq!(LottoResult::new("0a").total_points(), 0);
+    }
+
+    #[test]
+    fn test_poker_hand() {
+        let result = LottoResult::new("0a").with_cards("1", Deal::FiveCard);
+        assert_eq!(result.cards.len(), 5);
+        assert_eq!(result.rules.len(), 1);
+        let rule = &result.rules[0];
+        assert_eq!(rule.name(), "Poker hand: Straight flush");
+        assert_eq!(rule.description(), "3♣ 2♣ 4♣ 5♣ 6♣");
+        assert_eq!(result.total_points(), 7219334);
+        assert!(LottoResult::new("0a")
+            .with_cards("zz", Deal::FiveCard)
+            .cards
+            .is_empty());
+    }
+
+    #[test]
+    fn test_rule_probability() {
+        let config = RulesConfig::default();
+        // 5 choose 2 places for the pair, then 16 * 15 * 14 * 13 characters
+        let pair = 10.0 * 43_680.0 / 16f64.powi(5);
+        assert!(abs(RuleId::OfAKind(2).probability(5).unwrap() - pair) < 1e-12);
+        assert_eq!(RuleId::Legacy("Royal flush".into()).probability(7), None);
+
+        // every rule agrees with how often random hashes get it
+        let mut rng = rand::rngs::StdRng::seed_from_u64(3);
+        let samples = 20_000;
+        let mut hits: BTreeMap<RuleId, u64> = BTreeMap::new();
+        for _ in 0..samples {
+            let full_hash = crate::simulation::random_hash(40, &mut rng);
+            for rule in crate::scoring::score_hash(&full_hash, &config).rules {
+                *hits.entry(rule.id()).or_default() += 1;
+            }
+        }
+        let all = RuleId::all(&config, 7);
+        for (id, hits) in hits {
+            assert!(all.contains(&id), "{} isn't listed", id);
+            let p = id.probability(7).unwrap();
+            let expected = p * samples as f64;
+            let sigma = (expected * (1.0 - p)).sqrt().max(1.0);
+            assert!(
+                abs(hits as f64 - expected) < 5.0 * sigma,
+                "{}: {} hits, expected {}",
+                id,
+                hits,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn test_rule_ids() {
+        let config = RulesConfig {
+            deal: Deal::Holdem,
+            ..RulesConfig::default()
+        };
+        for id in RuleId::all(&config, 10) {
+            assert_eq!(RuleId::from(id.to_string()), id);
+        }
+        assert_eq!(
+            RuleId::Poker(Deal::Holdem, HandRank::FullHouse).to_string(),
+            "poker-holdem-full-house"
+        );
+        assert_eq!(
+            RuleId::from("Royal flush".to_string()),
+            RuleId::Legacy("Royal flush".into())
+        );
+        assert_eq!(RuleId::Straight(5).name(7), "Partial straight of 5");
+        assert_eq!(RuleId::Straight(7).name(7), "Straight");
+
+        let result = LottoResult::new("aabbc12");
+        let ids: Vec<RuleId> = result.rules.iter().map(|r| r.id()).collect();
+        for (rule, id) in result.rules.iter().zip(&ids) {
+            assert_eq!(RuleId::from_name(&rule.name(), "aabbc12", 7), *id);
+        }
+        assert_eq!(
+            RuleId::from_name("2 x 3 of a kind!", "aaabbb0", 7),
+            RuleId::Kinds { count: 2, kind: 3 }
+        );
+        assert_eq!(
+            RuleId::from_name("Partial straight", "0123aab", 7),
+            RuleId::Straight(4)
+        );
+        // the deal isn't known
+        assert_eq!(
+            RuleId::from_name("Poker hand: Pair", "aa01234", 7),
+            RuleId::Legacy("Poker hand: Pair".into())
+        );
     }
 }
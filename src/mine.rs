@@ -0,0 +1,351 @@
+use std::{
+    fmt,
+    str::FromStr,
+    sync::atomic::{AtomicU64, Ordering},
+    thread,
+};
+
+use anyhow::anyhow;
+use git2::{ObjectType, Oid, Repository};
+
+use crate::{config::RulesConfig, errors::LottoError, lotto::LottoResult, scoring::score_hash};
+
+/// The trailer added to the message of a mined commit.
+pub const NONCE_TRAILER: &str = "Poker-Nonce";
+
+/// What a mined commit has to score.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Target {
+    Points(u64),
+    /// Any rule whose name contains this, ignoring case, e.g. "flush".
+    Rule(String),
+}
+
+impl FromStr for Target {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "" => Err("target can't be empty".into()),
+            s => Ok(s
+                .parse()
+                .map(Target::Points)
+                .unwrap_or_else(|_| Target::Rule(s.to_lowercase()))),
+        }
+    }
+}
+
+impl fmt::Display for Target {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Target::Points(points) => write!(f, "{} points", points),
+            Target::Rule(name) => write!(f, "a {}", name),
+        }
+    }
+}
+
+impl Target {
+    pub fn is_met(&self, result: &LottoResult) -> bool {
+        match self {
+            Target::Points(points) => result.total_points() >= *points,
+            Target::Rule(name) => result
+                .rules
+                .iter()
+                .any(|r| r.name().to_lowercase().contains(name)),
+        }
+    }
+}
+
+/// Which part of the commit is changed to get a new hash.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Nonce {
+    /// A `Poker-Nonce` trailer in the message.
+    Trailer,
+    /// The committer timestamp, going back up to this many seconds.
+    Timestamp { window: u64 },
+}
+
+/// The commit object with a gap where the nonce goes.
+struct Template {
+    before: Vec<u8>,
+    after: Vec<u8>,
+    /// The committer timestamp, when it is the nonce.
+    timestamp: Option<u64>,
+}
+
+impl Template {
+    fn new(raw: &[u8], nonce: Nonce) -> anyhow::Result<Self> {
+        let split = find(raw, b"\n\n").ok_or(anyhow!("commit has no message"))?;
+        let (header, message) = (&raw[..split], &raw[split + 2..]);
+        // a signature can't survive the commit changing
+        let mut lines: Vec<&[u8]> = vec![];
+        let mut in_signature = false;
+        for line in header.split(|b| *b == b'\n') {
+            if line.starts_with(b"gpgsig") {
+                in_signature = true;
+            } else if !(in_signature && line.starts_with(b" ")) {
+                in_signature = false;
+                lines.push(line);
+            }
+        }
+        let header = lines.join(&b'\n');
+
+        match nonce {
+            Nonce::Trailer => {
+                // the message may not be UTF-8, so it is kept as bytes
+                let trailer = format!("{}:", NONCE_TRAILER);
+                let kept: Vec<&[u8]> = message
+                    .split(|b| *b == b'\n')
+                    .filter(|l| !l.starts_with(trailer.as_bytes()))
+                    .collect();
+                let kept = kept.join(&b'\n');
+                let end = kept
+                    .iter()
+                    .rposition(|b| !b.is_ascii_whitespace())
+                    .map_or(0, |i| i + 1);
+                let mut before = header;
+                before.extend_from_slice(b"\n\n");
+                before.extend_from_slice(&kept[..end]);
+                before.extend_from_slice(format!("\n\n{}: ", NONCE_TRAILER).as_bytes());
+                Ok(Template {
+                    before,
+                    after: b"\n".to_vec(),
+                    timestamp: None,
+                })
+            }
+            Nonce::Timestamp { .. } => {
+                let start = find(&header, b"\ncommitter ").ok_or(anyhow!("no committer"))? + 1;
+                let end = header[start..]
+                    .iter()
+                    .position(|b| *b == b'\n')
+                    .map_or(header.len(), |i| start + i);
+                let line = &header[start..end];
+                // committer Name <email> 1697500000 +0000
+                let zone = line
+                    .iter()
+                    .rposition(|b| *b == b' ')
+                    .ok_or(anyhow!("no zone"))?;
+                let time = line[..zone]
+                    .iter()
+                    .rposition(|b| *b == b' ')
+                    .ok_or(anyhow!("no time"))?
+                    + 1;
+                let timestamp = std::str::from_utf8(&line[time..zone])?.parse()?;
+                let mut after = header[start + zone..].to_vec();
+                after.extend_from_slice(b"\n\n");
+                after.extend_from_slice(message);
+                Ok(Template {
+                    before: header[..start + time].to_vec(),
+                    after,
+                    timestamp: Some(timestamp),
+                })
+            }
+        }
+    }
+
+    fn fill(&self, n: u64) -> Vec<u8> {
+        let nonce = match self.timestamp {
+            Some(timestamp) => timestamp - n,
+            None => n,
+        };
+        [
+            &self.before[..],
+            nonce.to_string().as_bytes(),
+            &self.after[..],
+        ]
+        .concat()
+    }
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+pub struct Mined {
+    pub oid: Oid,
+    pub raw: Vec<u8>,
+    pub tries: u64,
+}
+
+pub struct MineOptions {
+    pub target: Target,
+    pub nonce: Nonce,
+    pub threads: usize,
+    pub max_tries: u64,
+}
+
+/// Search for a version of the HEAD commit that meets the target, without
+/// writing anything. Returns `None` if nothing was found in time.
+pub fn mine(
+    repo: &Repository,
+    rules: &RulesConfig,
+    options: &MineOptions,
+) -> Result<Option<Mined>, LottoError> {
+    let head = repo.head().and_then(|h| h.peel_to_commit())?;
+    let odb = repo.odb()?;
+    let raw = odb.read(head.id())?.data().to_vec();
+    let template = Template::new(&raw, options.nonce).map_err(LottoError::MiningFailed)?;
+    let max_tries = match (options.nonce, template.timestamp) {
+        (Nonce::Timestamp { window }, Some(timestamp)) => {
+            options.max_tries.min(window + 1).min(timestamp + 1)
+        }
+        _ => options.max_tries,
+    };
+
+    let threads = options.threads.max(1) as u64;
+    // the smallest nonce that meets the target. Threads keep going until they
+    // pass it, so the result doesn't depend on which thread got there first.
+    let best = AtomicU64::new(u64::MAX);
+    let tries = AtomicU64::new(0);
+    thread::scope(|scope| {
+        for start in 0..threads {
+            let (template, best, tries) = (&template, &best, &tries);
+            scope.spawn(move || {
+                let mut n = start;
+                while n < max_tries && n < best.load(Ordering::Relaxed) {
+                    let Ok(oid) = Oid::hash_object(ObjectType::Commit, &template.fill(n)) else {
+                        return;
+                    };
+                    tries.fetch_add(1, Ordering::Relaxed);
+                    let full_hash = oid.to_string();
+                    let result = score_hash(&full_hash, rules);
+                    if options.target.is_met(&result) {
+                        best.fetch_min(n, Ordering::Relaxed);
+                        return;
+                    }
+                    n += threads;
+                }
+            });
+        }
+    });
+    let n = best.into_inner();
+    if n == u64::MAX {
+        return Ok(None);
+    }
+    let raw = template.fill(n);
+    Ok(Some(Mined {
+        oid: Oid::hash_object(ObjectType::Commit, &raw)?,
+        raw,
+        tries: tries.into_inner(),
+    }))
+}
+
+/// Write the mined commit and point HEAD, or the branch it is on, at it.
+pub fn rewrite_head(repo: &Repository, mined: &Mined) -> Result<Oid, LottoError> {
+    let oid = repo
+        .odb()
+        .and_then(|odb| odb.write(ObjectType::Commit, &mined.raw))?;
+    debug_assert_eq!(oid, mined.oid);
+    repo.head()
+        .and_then(|mut head| head.set_target(oid, "commit-poker-util mine"))?;
+    Ok(oid)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_util::commit;
+
+    fn options(target: Target, nonce: Nonce, threads: usize) -> MineOptions {
+        MineOptions {
+            target,
+            nonce,
+            threads,
+            max_tries: 1_000_000,
+        }
+    }
+
+    fn mine_and_rewrite(repo: &Repository, target: Target, nonce: Nonce) -> Oid {
+        let options = options(target.clone(), nonce, 4);
+        let rules = RulesConfig::default();
+        let mined = mine(repo, &rules, &options).unwrap().unwrap();
+        let oid = rewrite_head(repo, &mined).unwrap();
+        let head = repo.head().unwrap().peel_to_commit().unwrap();
+        assert_eq!(head.id(), oid);
+        let full_hash = oid.to_string();
+        let result = score_hash(&full_hash, &rules);
+        assert!(target.is_met(&result));
+        oid
+    }
+
+    #[test]
+    fn test_mine_trailer() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        commit(&repo, "a@example.com", 1_000);
+        let original = commit(&repo, "a@example.com", 2_000);
+
+        let oid = mine_and_rewrite(&repo, Target::Points(1_000), Nonce::Trailer);
+        let mined = repo.find_commit(oid).unwrap();
+        let original = repo.find_commit(original).unwrap();
+        assert_eq!(mined.tree_id(), original.tree_id());
+        assert_eq!(mined.parent_id(0).unwrap(), original.parent_id(0).unwrap());
+        assert!(mined.message().unwrap().contains("\n\nPoker-Nonce: "));
+
+        // mining again replaces the nonce rather than adding another
+        let oid = mine_and_rewrite(&repo, "flush".parse().unwrap(), Nonce::Trailer);
+        let message = repo
+            .find_commit(oid)
+            .unwrap()
+            .message()
+            .unwrap()
+            .to_string();
+        assert_eq!(message.matches(NONCE_TRAILER).count(), 1);
+    }
+
+    #[test]
+    fn test_mine_timestamp() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        let original = commit(&repo, "a@example.com", 100_000);
+        let oid = mine_and_rewrite(
+            &repo,
+            Target::Rule("flush".into()),
+            Nonce::Timestamp { window: 3600 },
+        );
+        let mined = repo.find_commit(oid).unwrap();
+        let original = repo.find_commit(original).unwrap();
+        assert_eq!(mined.message(), original.message());
+        let back = original.committer().when().seconds() - mined.committer().when().seconds();
+        assert!((0..=3600).contains(&back));
+    }
+
+    #[test]
+    fn test_mine_is_deterministic() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        commit(&repo, "a@example.com", 1_000);
+        let rules = RulesConfig::default();
+        let mined: Vec<Oid> = [1, 3, 8]
+            .into_iter()
+            .map(|threads| {
+                let options = options(Target::Points(500), Nonce::Trailer, threads);
+                mine(&repo, &rules, &options).unwrap().unwrap().oid
+            })
+            .collect();
+        assert_eq!(mined, vec![mined[0]; 3]);
+    }
+
+    #[test]
+    fn test_trailer_keeps_message_bytes() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        let first = commit(&repo, "a@example.com", 1_000);
+        let raw = repo.odb().unwrap().read(first).unwrap().data().to_vec();
+        let split = find(&raw, b"\n\n").unwrap();
+        // a Latin-1 message, as git allows with an encoding header
+        let mut latin1 = raw[..split].to_vec();
+        latin1.extend_from_slice(b"\nencoding ISO-8859-1\n\ncaf\xe9\n");
+        let template = Template::new(&latin1, Nonce::Trailer).unwrap();
+        assert!(template
+            .fill(7)
+            .ends_with(b"\n\ncaf\xe9\n\nPoker-Nonce: 7\n"));
+    }
+
+    #[test]
+    fn test_target() {
+        assert_eq!("500".parse(), Ok(Target::Points(500)));
+        assert_eq!("Full House".parse(), Ok(Target::Rule("full house".into())));
+        assert!("".parse::<Target>().is_err());
+    }
+}
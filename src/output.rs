@@ -1,14 +1,42 @@
 use std::{
+    cell::RefCell,
     io::{stdout, Write},
+    str::FromStr,
     time::Duration,
 };
 
-use crate::{highscores::ScoreInfo, lotto::LottoResult};
+use crate::{
+    achievements::Unlocked, config::OutputConfig, highscores::ScoreInfo, lotto::LottoResult,
+    period::Period,
+};
+use anyhow::anyhow;
 use colored::*;
 use rand::{seq::SliceRandom, thread_rng};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    Terminal,
+    Json,
+}
+
+impl FromStr for Format {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "terminal" => Ok(Format::Terminal),
+            "json" => Ok(Format::Json),
+            _ => Err(anyhow!("unknown output format {}", s)),
+        }
+    }
+}
 
-#[derive(Debug)]
-enum Colour {
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Colour {
     Black,
     Red,
     Green,
@@ -35,11 +63,20 @@ impl Colour {
 pub trait TerminalOutputer {
     fn pre_commit(&self);
     fn post_commit(&self, result: &LottoResult);
+    /// Show the breakdown of a score.
+    fn result(&self, result: &LottoResult);
     fn failed(&self);
-    fn high_score(&self, new: &ScoreInfo, old: &ScoreInfo);
+    /// `new` tops the leaderboard for `period`, where `old` was the best.
+    fn high_score(&self, new: &ScoreInfo, old: &ScoreInfo, period: &Period);
+    /// The commit's author unlocked an achievement they didn't have yet.
+    fn achievement(&self, unlocked: &Unlocked);
+    /// Called once everything about a commit's score has been shown.
+    fn finished(&self) {}
 }
 
-pub struct TerminalOutputerImpl;
+pub struct TerminalOutputerImpl {
+    config: OutputConfig,
+}
 
 impl Default for TerminalOutputerImpl {
     fn default() -> Self {
@@ -49,39 +86,42 @@ impl Default for TerminalOutputerImpl {
 
 impl TerminalOutputerImpl {
     pub fn new() -> Self {
-        TerminalOutputerImpl {}
+        Self::from_config(OutputConfig::default())
+    }
+
+    pub fn from_config(config: OutputConfig) -> Self {
+        TerminalOutputerImpl { config }
     }
 
-    fn base_colour() -> Colour {
-        Colour::Black
+    fn base_colour(&self) -> Colour {
+        self.config.base_colour
     }
 
-    fn score_colours() -> Vec<Colour> {
-        let mut res = vec![
-            Colour::Red,
-            Colour::Green,
-            Colour::Yellow,
-            Colour::Blue,
-            Colour::Magenta,
-            Colour::Cyan,
-        ];
-        let mut rng = thread_rng();
-        res.shuffle(&mut rng);
+    fn score_colours(&self) -> Vec<Colour> {
+        let mut res = self.config.colours.clone();
+        if self.config.shuffle_colours {
+            let mut rng = thread_rng();
+            res.shuffle(&mut rng);
+        }
         res
     }
 
-    fn colourized_hash(results: &LottoResult) -> Vec<ColoredString> {
+    fn delay(&self) {
+        std::thread::sleep(Duration::from_millis(self.config.delay_ms));
+    }
+
+    fn colourized_hash(&self, results: &LottoResult) -> Vec<ColoredString> {
         let mut hash: Vec<ColoredString> = results
             .hash
             .chars()
-            .map(|c| Self::base_colour().colourize_text(&c.to_string()).bold())
+            .map(|c| self.base_colour().colourize_text(&c.to_string()).bold())
             .collect();
 
         for (rule, colour) in results
             .rules
             .iter()
             .flat_map(|r| r.positions())
-            .zip(Self::score_colours())
+            .zip(self.score_colours())
         {
             for position in rule {
                 hash[position] = colour.colourize_text(&hash[position]).bold();
@@ -98,17 +138,36 @@ impl TerminalOutputer for TerminalOutputerImpl {
     }
 
     fn post_commit(&self, result: &LottoResult) {
+        println!("done!");
+        self.result(result);
+    }
+
+    fn result(&self, result: &LottoResult) {
         let mut out = stdout();
         let mut flush = || out.flush().expect("could not flush stdout");
-        println!("done!");
         print!("Your commit hash is ... ");
         flush();
-        for c in Self::colourized_hash(result) {
-            std::thread::sleep(Duration::from_millis(200));
+        for c in self.colourized_hash(result) {
+            self.delay();
             print!("{}", c);
             flush();
         }
         println!();
+        if !result.cards.is_empty() {
+            print!("Your hand is ... ");
+            flush();
+            for card in &result.cards {
+                self.delay();
+                let text = card.to_string();
+                if card.suit.is_red() {
+                    print!("{} ", text.red().bold());
+                } else {
+                    print!("{} ", text.bold());
+                }
+                flush();
+            }
+            println!();
+        }
         for rule in &result.rules {
             println!("> {} - {}", rule.name().cyan(), rule.description().cyan());
             println!(
@@ -130,10 +189,15 @@ impl TerminalOutputer for TerminalOutputerImpl {
         println!("{}", "Failed to commit".red());
     }
 
-    fn high_score(&self, new: &ScoreInfo, old: &ScoreInfo) {
+    fn high_score(&self, new: &ScoreInfo, old: &ScoreInfo, period: &Period) {
+        let title = match period {
+            Period::AllTime => "New high score! ".to_string(),
+            Period::Season(name) => format!("New high score for {}! ", name),
+            period => format!("New {} high score! ", period),
+        };
         println!(
             "{}{}{}",
-            "New high score! ".green(),
+            title.green(),
             new.score.to_string().green().bold(),
             " points!".green()
         );
@@ -142,7 +206,123 @@ impl TerminalOutputer for TerminalOutputerImpl {
             "    {} points from {}: {}",
             old.score.to_string().bold(),
             old.commit.bold(),
-            old.rules.join(", ")
+            old.rule_names()
+        );
+    }
+
+    fn achievement(&self, unlocked: &Unlocked) {
+        println!(
+            "{}{} - {}",
+            "Achievement unlocked! ".yellow(),
+            unlocked.achievement.title().yellow().bold(),
+            unlocked.achievement.description().yellow()
         );
     }
 }
+
+/// Writes one JSON document per scored commit to stdout.
+#[derive(Default)]
+pub struct JsonOutputer {
+    document: RefCell<Option<Value>>,
+}
+
+impl JsonOutputer {
+    pub fn new() -> Self {
+        JsonOutputer::default()
+    }
+
+    pub fn document(result: &LottoResult) -> Value {
+        let rules: Vec<Value> = result
+            .rules
+            .iter()
+            .map(|rule| {
+                json!({
+                    "id": rule.id(),
+                    "name": rule.name(),
+                    "description": rule.description(),
+                    "probability": rule.probability(),
+                    "points": rule.points(),
+                    "positions": rule.positions(),
+                })
+            })
+            .collect();
+        json!({
+            "hash": result.hash,
+            "full_hash": result.full_hash,
+            "rules": rules,
+            "cards": result.cards.iter().map(|c| c.to_string()).collect::<Vec<String>>(),
+            "probability": if result.rules.is_empty() { None } else { Some(result.probability()) },
+            "total_points": result.total_points(),
+            "high_score": null,
+            "achievements": [],
+        })
+    }
+
+    fn emit(document: &Value) {
+        println!("{}", document);
+    }
+}
+
+impl TerminalOutputer for JsonOutputer {
+    fn pre_commit(&self) {}
+
+    fn post_commit(&self, result: &LottoResult) {
+        self.result(result);
+    }
+
+    fn result(&self, result: &LottoResult) {
+        if let Some(previous) = self.document.replace(Some(Self::document(result))) {
+            Self::emit(&previous);
+        }
+    }
+
+    fn failed(&self) {
+        Self::emit(&json!({ "failed": true }));
+    }
+
+    fn high_score(&self, new: &ScoreInfo, old: &ScoreInfo, period: &Period) {
+        if let Some(document) = self.document.borrow_mut().as_mut() {
+            document["high_score"] = json!({
+                "new": new,
+                "previous": old,
+                "period": period.to_string(),
+            });
+        }
+    }
+
+    fn achievement(&self, unlocked: &Unlocked) {
+        if let Some(document) = self.document.borrow_mut().as_mut() {
+            if let Some(achievements) = document["achievements"].as_array_mut() {
+                achievements.push(json!({
+                    "achievement": unlocked.achievement,
+                    "title": unlocked.achievement.title(),
+                    "description": unlocked.achievement.description(),
+                }));
+            }
+        }
+    }
+
+    fn finished(&self) {
+        if let Some(document) = self.document.take() {
+            Self::emit(&document);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_json_document() {
+        let result = LottoResult::new("abcdef").with_full_hash("abcdef0123");
+        let document = JsonOutputer::document(&result);
+        assert_eq!(document["hash"], "abcdef");
+        assert_eq!(document["full_hash"], "abcdef0123");
+        assert_eq!(document["rules"].as_array().unwrap().len(), 2);
+        assert_eq!(document["rules"][0]["positions"][0][0], 0);
+        assert_eq!(document["total_points"], result.total_points());
+        assert!(document["high_score"].is_null());
+        assert!(document["achievements"].as_array().unwrap().is_empty());
+    }
+}
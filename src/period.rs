@@ -0,0 +1,169 @@
+use std::fmt;
+
+use chrono::{Datelike, Days, Local, LocalResult, Months, NaiveDate, TimeZone, Utc};
+
+use crate::config::SeasonConfig;
+
+/// A span of commit dates as Unix timestamps, `until` exclusive. Either end
+/// can be open.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Window {
+    pub since: Option<i64>,
+    pub until: Option<i64>,
+}
+
+impl Window {
+    /// The local days from `first` to `last`, both included.
+    pub fn days(first: NaiveDate, last: NaiveDate) -> Self {
+        Window {
+            since: Some(midnight(first)),
+            until: last.succ_opt().map(midnight),
+        }
+    }
+
+    pub fn contains(&self, date: i64) -> bool {
+        self.since.is_none_or(|since| date >= since) && self.until.is_none_or(|until| date < until)
+    }
+}
+
+/// The start of a day in the local time zone.
+pub fn midnight(date: NaiveDate) -> i64 {
+    let midnight = date.and_hms_opt(0, 0, 0).unwrap();
+    match Local.from_local_datetime(&midnight) {
+        LocalResult::Single(dt) | LocalResult::Ambiguous(dt, _) => dt.timestamp(),
+        LocalResult::None => Utc.from_utc_datetime(&midnight).timestamp(),
+    }
+}
+
+/// The local day a timestamp falls on.
+pub fn local_date(date: i64) -> NaiveDate {
+    match Local.timestamp_opt(date, 0) {
+        LocalResult::Single(dt) | LocalResult::Ambiguous(dt, _) => dt.date_naive(),
+        LocalResult::None => NaiveDate::default(),
+    }
+}
+
+/// The season a timestamp falls in. The first configured one wins if they
+/// overlap.
+pub fn season_at(seasons: &[SeasonConfig], date: i64) -> Option<&SeasonConfig> {
+    seasons.iter().find(|s| s.window().contains(date))
+}
+
+/// A leaderboard a new score can top.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Period {
+    AllTime,
+    Season(String),
+    Month,
+    /// Monday to Sunday.
+    Week,
+    Day,
+}
+
+impl Period {
+    /// The leaderboards a new score at `date` is announced for, broadest
+    /// first. Topping the day's is too common to be worth announcing.
+    pub fn announced(date: i64, seasons: &[SeasonConfig]) -> Vec<Period> {
+        let mut periods = vec![Period::AllTime];
+        periods.extend(season_at(seasons, date).map(|s| Period::Season(s.name.clone())));
+        periods.extend([Period::Month, Period::Week]);
+        periods
+    }
+
+    /// This period's window around `date`, or `None` for a season it isn't
+    /// in.
+    pub fn around(&self, date: i64, seasons: &[SeasonConfig]) -> Option<Window> {
+        let day = local_date(date);
+        match self {
+            Period::AllTime => Some(Window::default()),
+            Period::Season(name) => seasons
+                .iter()
+                .find(|s| &s.name == name)
+                .map(SeasonConfig::window)
+                .filter(|w| w.contains(date)),
+            Period::Month => {
+                let first = day.with_day(1)?;
+                let last = first.checked_add_months(Months::new(1))?.pred_opt()?;
+                Some(Window::days(first, last))
+            }
+            Period::Week => {
+                let monday =
+                    day.checked_sub_days(Days::new(day.weekday().num_days_from_monday().into()))?;
+                Some(Window::days(monday, monday.checked_add_days(Days::new(6))?))
+            }
+            Period::Day => Some(Window::days(day, day)),
+        }
+    }
+}
+
+impl fmt::Display for Period {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Period::AllTime => write!(f, "all-time"),
+            Period::Season(name) => write!(f, "{}", name),
+            Period::Month => write!(f, "monthly"),
+            Period::Week => write!(f, "weekly"),
+            Period::Day => write!(f, "daily"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn date(text: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(text, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn test_periods() {
+        // a Wednesday afternoon
+        let now = midnight(date("2026-10-14")) + 15 * 3600;
+        let window = |period: Period| period.around(now, &[]).unwrap();
+        assert_eq!(window(Period::AllTime), Window::default());
+        assert_eq!(
+            window(Period::Day),
+            Window::days(date("2026-10-14"), date("2026-10-14"))
+        );
+        assert_eq!(
+            window(Period::Week),
+            Window::days(date("2026-10-12"), date("2026-10-18"))
+        );
+        assert_eq!(
+            window(Period::Month),
+            Window::days(date("2026-10-01"), date("2026-10-31"))
+        );
+
+        let week = window(Period::Week);
+        assert!(week.contains(midnight(date("2026-10-12"))));
+        assert!(week.contains(midnight(date("2026-10-19")) - 1));
+        assert!(!week.contains(midnight(date("2026-10-19"))));
+    }
+
+    #[test]
+    fn test_seasons() {
+        let seasons = vec![SeasonConfig {
+            name: "Autumn".into(),
+            start: date("2026-09-01"),
+            end: date("2026-11-30"),
+        }];
+        let now = midnight(date("2026-11-30")) + 60;
+        assert_eq!(season_at(&seasons, now).unwrap().name, "Autumn");
+        assert_eq!(
+            Period::announced(now, &seasons),
+            vec![
+                Period::AllTime,
+                Period::Season("Autumn".into()),
+                Period::Month,
+                Period::Week
+            ]
+        );
+        let winter = midnight(date("2026-12-01"));
+        assert!(season_at(&seasons, winter).is_none());
+        assert_eq!(
+            Period::Season("Autumn".into()).around(winter, &seasons),
+            None
+        );
+    }
+}
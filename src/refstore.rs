@@ -0,0 +1,766 @@
+use std::{collections::BTreeMap, path::Path};
+
+use anyhow::anyhow;
+use git2::{ErrorCode, FileMode, ObjectType, Oid, Repository, Signature};
+use serde_json::{json, Value};
+
+use crate::{
+    config::StorageConfig,
+    errors::LottoError,
+    git::{git_output, RepoId},
+    highscores::{HighScores, ScoreInfo},
+    store::{self, Awards, StoreStatus, CURRENT_VERSION},
+};
+
+/// The ref the shared scores are committed to.
+pub const SCORES_REF: &str = "refs/commit-poker/scores";
+/// The file that held every score before each score got its own.
+const LEGACY_SCORES_FILE: &str = "scores.json";
+/// The file in the ref's tree holding the version the scores were written
+/// with.
+const VERSION_FILE: &str = "version";
+/// The directory in the ref's tree with a file for each score, in
+/// subdirectories named after the first two characters of the file name.
+const SCORES_DIR: &str = "scores";
+/// The file in the ref's tree holding the awards.
+const AWARDS_FILE: &str = "awards.json";
+/// How many times to retry when another process moves the ref under us.
+const RETRIES: usize = 5;
+
+/// High scores committed to `refs/commit-poker/scores` in the repository
+/// itself, so a team can share them by pushing and pulling the ref.
+///
+/// Each score is its own blob, so a change only writes the scores that
+/// changed, and changes made since the scores were last pushed replace each
+/// other instead of adding to the ref's history.
+pub struct RefHighScores {
+    repo: Repository,
+}
+
+impl HighScores for RefHighScores {
+    fn save(&mut self, result: ScoreInfo) -> Result<(), LottoError> {
+        self.save_all(vec![result])
+    }
+
+    fn save_all(&mut self, results: Vec<ScoreInfo>) -> Result<(), LottoError> {
+        if results.is_empty() {
+            return Ok(());
+        }
+        let mut results = Some(results);
+        self.modify(&mut |scores| {
+            scores.extend(results.take().unwrap_or_default());
+            true
+        })
+    }
+
+    fn load_all(&mut self, repo: Option<&RepoId>) -> Result<Vec<ScoreInfo>, LottoError> {
+        let (mut scores, _) = self
+            .read(self.tip()?)
+            .map_err(LottoError::ApplicationDirError)?;
+        if let Some(repo) = repo {
+            scores.retain(|s| s.is_from(repo));
+        }
+        scores.sort_by_key(|s| -(s.score as i64));
+        Ok(scores)
+    }
+
+    fn modify(&mut self, f: &mut dyn FnMut(&mut Vec<ScoreInfo>) -> bool) -> Result<(), LottoError> {
+        self.update(&mut |scores, _| f(scores), false)
+    }
+
+    fn migrate_authors(&mut self) -> Result<usize, LottoError> {
+        let mut filled = 0;
+        self.modify(&mut |scores| {
+            filled = scores
+                .iter_mut()
+                .filter(|s| s.author.is_none())
+                .map(|s| s.fill_people())
+                .filter(|filled| *filled)
+                .count();
+            filled > 0
+        })?;
+        Ok(filled)
+    }
+
+    fn load_awards(&mut self) -> Result<Awards, LottoError> {
+        self.read_awards(self.tip()?)
+            .map_err(LottoError::ApplicationDirError)
+    }
+
+    fn modify_awards(&mut self, f: &mut dyn FnMut(&mut Awards) -> bool) -> Result<(), LottoError> {
+        self.update(&mut |_, awards| f(awards), false)
+    }
+
+    fn check(&mut self) -> Result<StoreStatus, LottoError> {
+        let (scores, version) = self
+            .read(self.tip()?)
+            .map_err(LottoError::ApplicationDirError)?;
+        Ok(StoreStatus {
+            path: self.repo.path().join(SCORES_REF),
+            version,
+            current: CURRENT_VERSION,
+            scores: scores.len(),
+            damaged: false,
+        })
+    }
+
+    /// The old version stays in the ref's history.
+    fn migrate(&mut self) -> Result<StoreStatus, LottoError> {
+        if self.check()?.needs_migration() {
+            self.update(&mut |_, _| true, true)?;
+        }
+        self.check()
+    }
+}
+
+impl RefHighScores {
+    pub fn new(repo: Repository) -> Self {
+        RefHighScores { repo }
+    }
+
+    /// The store of the repository at or above `path`.
+    pub fn discover(path: &Path) -> Result<Self, LottoError> {
+        Ok(RefHighScores::new(Repository::discover(path)?))
+    }
+
+    /// The store of the configured repository, or the current one.
+    pub fn from_config(config: &StorageConfig) -> Result<Self, LottoError> {
+        Self::discover(config.path.as_deref().unwrap_or(Path::new(".")))
+    }
+
+    /// Change the scores and awards, keeping the current version in the ref's
+    /// history if `keep` is set, a remote has it, or it was written by an
+    /// older version.
+    fn update(
+        &mut self,
+        f: &mut dyn FnMut(&mut Vec<ScoreInfo>, &mut Awards) -> bool,
+        keep: bool,
+    ) -> Result<(), LottoError> {
+        for _ in 0..RETRIES {
+            let tip = self.tip()?;
+            let (mut scores, version) = self.read(tip).map_err(LottoError::ApplicationDirError)?;
+            let mut awards = self
+                .read_awards(tip)
+                .map_err(LottoError::ApplicationDirError)?;
+            if !f(&mut scores, &mut awards) {
+                return Ok(());
+            }
+            let parents = match tip {
+                Some(tip) if keep || version < CURRENT_VERSION => vec![tip],
+                tip => self.parents(tip)?,
+            };
+            let commit = self.write(&scores, &awards, &parents, "Update scores")?;
+            if self.advance(tip, commit)? {
+                return Ok(());
+            }
+        }
+        Err(LottoError::ApplicationDirError(anyhow!(
+            "{} kept changing while it was being updated",
+            SCORES_REF
+        )))
+    }
+
+    /// Fetch the remote's scores and merge them into ours.
+    pub fn pull(&mut self, remote: &str) -> Result<usize, LottoError> {
+        // fetching a ref the remote doesn't have fails
+        if self
+            .git(&["ls-remote", remote, SCORES_REF])?
+            .trim()
+            .is_empty()
+        {
+            return Ok(0);
+        }
+        let tracking = tracking_ref(remote)?;
+        self.git(&[
+            "fetch",
+            "--quiet",
+            remote,
+            &format!("+{}:{}", SCORES_REF, tracking),
+        ])?;
+        let theirs = self.repo.refname_to_id(&tracking)?;
+        let before = self.load_all(None)?.len();
+        for _ in 0..RETRIES {
+            let ours = self.tip()?;
+            let merged = match ours {
+                None => theirs,
+                Some(ours) if self.repo.graph_descendant_of(ours, theirs)? || ours == theirs => {
+                    return Ok(0)
+                }
+                Some(ours) if self.repo.graph_descendant_of(theirs, ours)? => theirs,
+                Some(ours) => {
+                    let base = self.repo.merge_base(ours, theirs).ok();
+                    let read = |oid| self.read(oid).map(|(scores, _)| scores);
+                    let scores = merge(
+                        &read(base).map_err(LottoError::ApplicationDirError)?,
+                        &read(Some(ours)).map_err(LottoError::ApplicationDirError)?,
+                        &read(Some(theirs)).map_err(LottoError::ApplicationDirError)?,
+                    );
+                    let read_awards = |oid| self.read_awards(Some(oid));
+                    let mut awards = read_awards(ours).map_err(LottoError::ApplicationDirError)?;
+                    awards
+                        .add_missing(read_awards(theirs).map_err(LottoError::ApplicationDirError)?);
+                    let message = format!("Merge scores from {}", remote);
+                    self.write(&scores, &awards, &[ours, theirs], &message)?
+                }
+            };
+            if self.advance(ours, merged)? {
+                let after = self.load_all(None)?.len();
+                return Ok(after.saturating_sub(before));
+            }
+        }
+        Err(LottoError::ApplicationDirError(anyhow!(
+            "{} kept changing while it was being merged",
+            SCORES_REF
+        )))
+    }
+
+    /// Merge the remote's scores, then push the result. The push is retried
+    /// if someone else pushed in between.
+    pub fn push(&mut self, remote: &str) -> Result<(), LottoError> {
+        if self.tip()?.is_none() {
+            return Ok(());
+        }
+        let refspec = format!("{0}:{0}", SCORES_REF);
+        let mut attempt = 0;
+        loop {
+            self.pull(remote)?;
+            let pushed = self.tip()?;
+            match self.git(&["push", "--quiet", remote, &refspec]) {
+                Err(e) if is_rejected(&e) && attempt + 1 < RETRIES => attempt += 1,
+                Err(e) => return Err(e),
+                Ok(_) => {
+                    // later changes have to keep what the remote has now
+                    if let Some(pushed) = pushed {
+                        self.repo.reference(
+                            &tracking_ref(remote)?,
+                            pushed,
+                            true,
+                            "commit-poker: push scores",
+                        )?;
+                    }
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    /// The parents of a commit replacing `tip`: `tip` itself if a remote has
+    /// it, or else the commits it has, so that changes nobody else has seen
+    /// don't pile up.
+    fn parents(&self, tip: Option<Oid>) -> Result<Vec<Oid>, LottoError> {
+        let Some(tip) = tip else {
+            return Ok(vec![]);
+        };
+        for reference in self.repo.references_glob("refs/commit-poker/remotes/*")? {
+            let Some(synced) = reference?.target() else {
+                continue;
+            };
+            if synced == tip || self.repo.graph_descendant_of(synced, tip)? {
+                return Ok(vec![tip]);
+            }
+        }
+        Ok(self.repo.find_commit(tip)?.parent_ids().collect())
+    }
+
+    fn git(&self, args: &[&str]) -> Result<String, LottoError> {
+        let dir = self.repo.workdir().unwrap_or(self.repo.path());
+        git_output(dir, args)
+    }
+
+    fn tip(&self) -> Result<Option<Oid>, LottoError> {
+        match self.repo.refname_to_id(SCORES_REF) {
+            Ok(oid) => Ok(Some(oid)),
+            Err(e) if e.code() == ErrorCode::NotFound => Ok(None),
+            Err(e) => Err(LottoError::Git(e)),
+        }
+    }
+
+    /// The scores committed at `commit`, and the version they were written
+    /// with, 0 if there aren't any.
+    fn read(&self, commit: Option<Oid>) -> anyhow::Result<(Vec<ScoreInfo>, u32)> {
+        let Some(commit) = commit else {
+            return Ok((vec![], 0));
+        };
+        let tree = self.repo.find_commit(commit)?.tree()?;
+        let blob = |id: Oid| -> anyhow::Result<Value> {
+            Ok(serde_json::from_slice(self.repo.find_blob(id)?.content())?)
+        };
+        if let Some(entry) = tree.get_name(LEGACY_SCORES_FILE) {
+            return store::parse(blob(entry.id())?);
+        }
+        let version =
+            tree.get_name(VERSION_FILE)
+                .ok_or(anyhow!("{} has no {}", SCORES_REF, VERSION_FILE))?;
+        let mut scores = vec![];
+        if let Some(dir) = tree.get_name(SCORES_DIR) {
+            for shard in self.repo.find_tree(dir.id())?.iter() {
+                for entry in self.repo.find_tree(shard.id())?.iter() {
+                    scores.push(blob(entry.id())?);
+                }
+            }
+        }
+        store::parse(json!({ "version": blob(version.id())?, "scores": scores }))
+    }
+
+    /// The awards committed at `commit`.
+    fn read_awards(&self, commit: Option<Oid>) -> anyhow::Result<Awards> {
+        let Some(commit) = commit else {
+            return Ok(Awards::default());
+        };
+        let tree = self.repo.find_commit(commit)?.tree()?;
+        let Some(entry) = tree.get_name(AWARDS_FILE) else {
+            return Ok(Awards::default());
+        };
+        let awards = serde_json::from_slice(self.repo.find_blob(entry.id())?.content())?;
+        Ok(awards)
+    }
+
+    fn write(
+        &self,
+        scores: &[ScoreInfo],
+        awards: &Awards,
+        parents: &[Oid],
+        message: &str,
+    ) -> Result<Oid, LottoError> {
+        // blobs that are already there aren't written again
+        let mut shards: BTreeMap<String, Vec<(String, Oid)>> = BTreeMap::new();
+        for score in scores {
+            let (repo, commit) = score.key();
+            let name =
+                Oid::hash_object(ObjectType::Blob, format!("{}\n{}", repo, commit).as_bytes())?
+                    .to_string();
+            let json =
+                serde_json::to_vec(score).map_err(|e| LottoError::ApplicationDirError(e.into()))?;
+            let blob = self.repo.blob(&json)?;
+            shards
+                .entry(name[..2].to_string())
+                .or_default()
+                .push((name, blob));
+        }
+        let mut dir = self.repo.treebuilder(None)?;
+        for (shard, entries) in shards {
+            let mut tree = self.repo.treebuilder(None)?;
+            for (name, blob) in entries {
+                tree.insert(name, blob, FileMode::Blob.into())?;
+            }
+            dir.insert(shard, tree.write()?, FileMode::Tree.into())?;
+        }
+        let mut tree = self.repo.treebuilder(None)?;
+        tree.insert(SCORES_DIR, dir.write()?, FileMode::Tree.into())?;
+        let version = self.repo.blob(CURRENT_VERSION.to_string().as_bytes())?;
+        tree.insert(VERSION_FILE, version, FileMode::Blob.into())?;
+        if *awards != Awards::default() {
+            let json = serde_json::to_vec_pretty(awards)
+                .map_err(|e| LottoError::ApplicationDirError(e.into()))?;
+            tree.insert(AWARDS_FILE, self.repo.blob(&json)?, FileMode::Blob.into())?;
+        }
+        let tree = self.repo.find_tree(tree.write()?)?;
+        let signature = self
+            .repo
+            .signature()
+            .or_else(|_| Signature::now("commit-poker", "commit-poker@localhost"))?;
+        let parents = parents
+            .iter()
+            .map(|oid| self.repo.find_commit(*oid))
+            .collect::<Result<Vec<_>, _>>()?;
+        let parents: Vec<&git2::Commit> = parents.iter().collect();
+        Ok(self
+            .repo
+            .commit(None, &signature, &signature, message, &tree, &parents)?)
+    }
+
+    /// Move the ref from `from` to `to`, unless something else moved it
+    /// first. Returns whether it was moved.
+    fn advance(&self, from: Option<Oid>, to: Oid) -> Result<bool, LottoError> {
+        let result = match from {
+            Some(from) => self.repo.reference_matching(
+                SCORES_REF,
+                to,
+                true,
+                from,
+                "commit-poker: update scores",
+            ),
+            None => self
+                .repo
+                .reference(SCORES_REF, to, false, "commit-poker: create scores"),
+        };
+        match result {
+            Ok(_) => Ok(true),
+            Err(e) if matches!(e.code(), ErrorCode::Modified | ErrorCode::Exists) => Ok(false),
+            Err(e) => Err(LottoError::Git(e)),
+        }
+    }
+}
+
+/// Whether a push failed because the remote has scores we haven't merged,
+/// rather than because it couldn't be reached or refused us.
+fn is_rejected(error: &LottoError) -> bool {
+    match error {
+        LottoError::CommandFailed { stderr, .. } => {
+            ["[rejected]", "non-fast-forward", "fetch first"]
+                .iter()
+                .any(|reason| stderr.contains(reason))
+        }
+        _ => false,
+    }
+}
+
+/// Where a remote's scores are fetched to. Remotes given as a URL or path
+/// can't be part of a ref name, so they're named by their hash instead.
+fn tracking_ref(remote: &str) -> Result<String, LottoError> {
+    let named = !remote.is_empty()
+        && remote
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    let name = if named {
+        remote.to_string()
+    } else {
+        format!(
+            "url-{}",
+            Oid::hash_object(ObjectType::Blob, remote.as_bytes())?
+        )
+    };
+    Ok(format!("refs/commit-poker/remotes/{}/scores", name))
+}
+
+/// Three-way merge of two sets of scores that diverged from `base`. A score
+/// changed or removed on one side only takes that side's version. When both
+/// sides changed it, the one that has been rewritten wins, then the one with
+/// more flags, then ours.
+pub fn merge(base: &[ScoreInfo], ours: &[ScoreInfo], theirs: &[ScoreInfo]) -> Vec<ScoreInfo> {
+    let by_key = |scores: &[ScoreInfo]| -> BTreeMap<(String, String), ScoreInfo> {
+        scores.iter().map(|s| (s.key(), s.clone())).collect()
+    };
+    let (base, mut ours, mut theirs) = (by_key(base), by_key(ours), by_key(theirs));
+    let keys: Vec<(String, String)> = ours.keys().chain(theirs.keys()).cloned().collect();
+    let mut merged = BTreeMap::new();
+    for key in keys {
+        if merged.contains_key(&key) {
+            continue;
+        }
+        let original = base.get(&key);
+        let score = match (ours.remove(&key), theirs.remove(&key)) {
+            (Some(ours), Some(theirs)) if Some(&ours) == original => Some(theirs),
+            (Some(ours), Some(theirs)) if Some(&theirs) == original || ours == theirs => Some(ours),
+            (Some(ours), Some(theirs)) => {
+                let rank = |s: &ScoreInfo| (s.rewritten_as.is_some(), s.flags.len());
+                Some(if rank(&theirs) > rank(&ours) {
+                    theirs
+                } else {
+                    ours
+                })
+            }
+            // removed on the other side, say by pruning, and not changed here
+            (Some(kept), None) | (None, Some(kept)) if Some(&kept) == original => None,
+            (Some(kept), None) | (None, Some(kept)) => Some(kept),
+            (None, None) => None,
+        };
+        if let Some(score) = score {
+            merged.insert(key, score);
+        }
+    }
+    let mut merged: Vec<ScoreInfo> = merged.into_values().collect();
+    merged.sort_by_key(|s| -(s.score as i64));
+    merged
+}
+
+/// Add the scores in `other` that `scores` doesn't have, returning how many
+/// were added.
+pub fn add_missing(scores: &mut Vec<ScoreInfo>, other: Vec<ScoreInfo>) -> usize {
+    let before = scores.len();
+    for score in other {
+        if !scores.iter().any(|s| s.key() == score.key()) {
+            scores.push(score);
+        }
+    }
+    scores.len() - before
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        seasons::ArchivedSeason,
+        test_util::{commit, person, score_info},
+    };
+
+    fn score(commit: &str, points: u64) -> ScoreInfo {
+        ScoreInfo {
+            root: Some("root".into()),
+            author: Some(person("a@example.com")),
+            ..score_info(commit, points)
+        }
+    }
+
+    fn archive(highscores: &mut RefHighScores, name: &str) {
+        let season = ArchivedSeason {
+            name: name.into(),
+            start: Default::default(),
+            end: Default::default(),
+            length: 7,
+            winners: vec![],
+        };
+        let mut new = Some(Awards {
+            seasons: vec![season],
+            ..Default::default()
+        });
+        highscores
+            .modify_awards(&mut |awards| awards.add_missing(new.take().unwrap_or_default()))
+            .unwrap();
+    }
+
+    fn seasons(highscores: &mut RefHighScores) -> Vec<String> {
+        let mut names: Vec<String> = highscores
+            .load_awards()
+            .unwrap()
+            .seasons
+            .into_iter()
+            .map(|s| s.name)
+            .collect();
+        names.sort();
+        names
+    }
+
+    fn commits(scores: &[ScoreInfo]) -> Vec<&str> {
+        let mut commits: Vec<&str> = scores.iter().map(|s| s.commit.as_str()).collect();
+        commits.sort();
+        commits
+    }
+
+    #[test]
+    fn test_merge() {
+        let base = vec![score("aaaaaaa", 10), score("bbbbbbb", 20)];
+        // we prune a, they add c and rewrite b
+        let ours = vec![score("bbbbbbb", 20), score("ddddddd", 40)];
+        let mut rewritten = score("bbbbbbb", 20);
+        rewritten.rewritten_as = Some("eeeeeee".into());
+        let theirs = vec![score("aaaaaaa", 10), rewritten, score("ccccccc", 30)];
+
+        let merged = merge(&base, &ours, &theirs);
+        assert_eq!(commits(&merged), vec!["bbbbbbb", "ccccccc", "ddddddd"]);
+        let b = merged.iter().find(|s| s.commit == "bbbbbbb").unwrap();
+        assert_eq!(b.rewritten_as.as_deref(), Some("eeeeeee"));
+
+        // both sides changing a score keeps the rewritten one
+        let mut superseded = score("bbbbbbb", 20);
+        superseded.superseded = true;
+        let merged = merge(&base, &[superseded], &theirs);
+        let b = merged.iter().find(|s| s.commit == "bbbbbbb").unwrap();
+        assert!(b.rewritten_as.is_some());
+    }
+
+    fn blobs(repo: &Repository, commit: Oid) -> Vec<Oid> {
+        let mut blobs = vec![];
+        let tree = repo.find_commit(commit).unwrap().tree().unwrap();
+        tree.walk(git2::TreeWalkMode::PreOrder, |_, entry| {
+            blobs.push(entry.id());
+            git2::TreeWalkResult::Ok
+        })
+        .unwrap();
+        blobs
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        let mut highscores = RefHighScores::new(repo);
+        assert_eq!(highscores.check().unwrap().version, 0);
+        highscores.save(score("aaaaaaa", 10)).unwrap();
+        highscores.save(score("bbbbbbb", 20)).unwrap();
+
+        let scores = highscores.load_all(None).unwrap();
+        assert_eq!(commits(&scores), vec!["aaaaaaa", "bbbbbbb"]);
+        assert_eq!(scores[0].score, 20);
+        let status = highscores.check().unwrap();
+        assert_eq!((status.version, status.scores), (CURRENT_VERSION, 2));
+        // nothing was pushed, so the second change replaced the first
+        let tip = highscores.tip().unwrap().unwrap();
+        assert_eq!(highscores.repo.find_commit(tip).unwrap().parent_count(), 0);
+
+        // the same commit in another clone is the same score, and the first
+        // score's blob is shared with the last version
+        let mut same = score("aaaaaaa", 10);
+        same.repo = "/elsewhere".into();
+        highscores.save(same).unwrap();
+        assert_eq!(highscores.load_all(None).unwrap().len(), 2);
+        let before = blobs(&highscores.repo, tip);
+        highscores.save(score("ccccccc", 30)).unwrap();
+        let after = blobs(&highscores.repo, highscores.tip().unwrap().unwrap());
+        assert_eq!(after.iter().filter(|b| before.contains(b)).count(), 3);
+    }
+
+    #[test]
+    fn test_reads_one_file_of_scores() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut highscores = RefHighScores::new(Repository::init(dir.path()).unwrap());
+        {
+            let repo = &highscores.repo;
+            // as the last version to keep every score in one file wrote it
+            let json = json!({"version": 7, "scores": [score("aaaaaaa", 10)]});
+            let blob = repo.blob(json.to_string().as_bytes()).unwrap();
+            let mut tree = repo.treebuilder(None).unwrap();
+            tree.insert(LEGACY_SCORES_FILE, blob, FileMode::Blob.into())
+                .unwrap();
+            let tree = repo.find_tree(tree.write().unwrap()).unwrap();
+            let signature = Signature::now("a", "a@example.com").unwrap();
+            repo.commit(
+                Some(SCORES_REF),
+                &signature,
+                &signature,
+                "Scores",
+                &tree,
+                &[],
+            )
+            .unwrap();
+        }
+
+        assert_eq!(
+            commits(&highscores.load_all(None).unwrap()),
+            vec!["aaaaaaa"]
+        );
+        let legacy = highscores.tip().unwrap().unwrap();
+        highscores.save(score("bbbbbbb", 20)).unwrap();
+        assert_eq!(
+            commits(&highscores.load_all(None).unwrap()),
+            vec!["aaaaaaa", "bbbbbbb"]
+        );
+        // the old version is kept
+        let tip = highscores.tip().unwrap().unwrap();
+        let parents: Vec<Oid> = highscores
+            .repo
+            .find_commit(tip)
+            .unwrap()
+            .parent_ids()
+            .collect();
+        assert_eq!(parents, vec![legacy]);
+    }
+
+    #[test]
+    fn test_is_rejected() {
+        let failed = |stderr: &str| LottoError::CommandFailed {
+            command: "git push".into(),
+            status: Some(1),
+            signal: None,
+            stderr: stderr.into(),
+        };
+        assert!(is_rejected(&failed(
+            " ! [rejected]        refs/commit-poker/scores -> refs/commit-poker/scores (fetch first)"
+        )));
+        assert!(!is_rejected(&failed(
+            "fatal: could not read from remote repository"
+        )));
+        assert!(!is_rejected(&failed(
+            " ! [remote rejected] refs/commit-poker/scores -> refs/commit-poker/scores (hook declined)"
+        )));
+    }
+
+    #[test]
+    fn test_tracking_ref() {
+        assert_eq!(
+            tracking_ref("origin").unwrap(),
+            "refs/commit-poker/remotes/origin/scores"
+        );
+        for remote in ["/tmp/x.git", "https://h/x", "../bare", "a.lock", ""] {
+            let name = tracking_ref(remote).unwrap();
+            assert!(git2::Reference::is_valid_name(&name), "{}", name);
+        }
+        assert_ne!(tracking_ref("/a").unwrap(), tracking_ref("/b").unwrap());
+    }
+
+    #[test]
+    fn test_sync_with_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let remote = dir.path().join("remote.git");
+        Repository::init_bare(&remote).unwrap();
+        let remote = remote.to_str().unwrap();
+        let clone = |name: &str| {
+            let repo = Repository::init(dir.path().join(name)).unwrap();
+            commit(&repo, "a@example.com", 1_000);
+            RefHighScores::new(repo)
+        };
+        let (mut alice, mut bob) = (clone("alice"), clone("bob"));
+        alice.save(score("aaaaaaa", 10)).unwrap();
+        alice.push(remote).unwrap();
+        bob.save(score("bbbbbbb", 20)).unwrap();
+        bob.push(remote).unwrap();
+        assert_eq!(alice.pull(remote).unwrap(), 1);
+        assert_eq!(
+            commits(&alice.load_all(None).unwrap()),
+            vec!["aaaaaaa", "bbbbbbb"]
+        );
+    }
+
+    #[test]
+    fn test_sync() {
+        let dir = tempfile::tempdir().unwrap();
+        let remote = dir.path().join("remote.git");
+        Repository::init_bare(&remote).unwrap();
+        let clone = |name: &str| {
+            let repo = Repository::init(dir.path().join(name)).unwrap();
+            repo.remote("origin", remote.to_str().unwrap()).unwrap();
+            commit(&repo, "a@example.com", 1_000);
+            RefHighScores::new(repo)
+        };
+        let (mut alice, mut bob) = (clone("alice"), clone("bob"));
+
+        // nothing to pull yet
+        assert_eq!(bob.pull("origin").unwrap(), 0);
+        alice.save(score("aaaaaaa", 10)).unwrap();
+        alice.push("origin").unwrap();
+        // what was pushed stays in the history of later changes
+        let pushed = alice.tip().unwrap().unwrap();
+        alice.save(score("fffffff", 5)).unwrap();
+        let tip = alice.tip().unwrap().unwrap();
+        assert_eq!(
+            alice
+                .repo
+                .find_commit(tip)
+                .unwrap()
+                .parent_ids()
+                .collect::<Vec<_>>(),
+            vec![pushed]
+        );
+        alice.save(score("eeeeeee", 5)).unwrap();
+        let tip = alice.tip().unwrap().unwrap();
+        assert_eq!(
+            alice
+                .repo
+                .find_commit(tip)
+                .unwrap()
+                .parent_ids()
+                .collect::<Vec<_>>(),
+            vec![pushed]
+        );
+        alice
+            .modify(&mut |scores| {
+                scores.retain(|s| s.score > 5);
+                true
+            })
+            .unwrap();
+        archive(&mut alice, "Spring");
+        // bob's push has to merge alice's first
+        bob.save(score("bbbbbbb", 20)).unwrap();
+        archive(&mut bob, "Summer");
+        bob.push("origin").unwrap();
+        assert_eq!(
+            commits(&bob.load_all(None).unwrap()),
+            vec!["aaaaaaa", "bbbbbbb"]
+        );
+        assert_eq!(seasons(&mut bob), vec!["Summer"]);
+
+        // alice prunes her score while pulling bob's
+        alice
+            .modify(&mut |scores| {
+                scores.clear();
+                true
+            })
+            .unwrap();
+        assert_eq!(alice.pull("origin").unwrap(), 1);
+        assert_eq!(commits(&alice.load_all(None).unwrap()), vec!["bbbbbbb"]);
+        alice.push("origin").unwrap();
+        assert_eq!(bob.pull("origin").unwrap(), 0);
+        assert_eq!(commits(&bob.load_all(None).unwrap()), vec!["bbbbbbb"]);
+        assert_eq!(seasons(&mut bob), vec!["Spring", "Summer"]);
+    }
+}
@@ -0,0 +1,289 @@
+use std::time::Duration;
+
+use anyhow::anyhow;
+use serde::de::DeserializeOwned;
+use ureq::{Agent, AgentBuilder};
+
+use crate::{
+    config::StorageConfig,
+    errors::LottoError,
+    git::RepoId,
+    highscores::{HighScores, ScoreInfo},
+    period::Window,
+    server::Changes,
+    store::{Awards, StoreStatus},
+};
+
+/// How long to wait for the server before giving up on a score.
+const TIMEOUT: Duration = Duration::from_secs(10);
+/// How many scores are sent at once, keeping requests under the server's cap.
+const CHUNK: usize = 1000;
+
+/// High scores kept by a `commit-poker-util serve` server on another machine.
+/// The server filters what is loaded, so only one repository's or author's
+/// scores are downloaded.
+pub struct RemoteHighScores {
+    url: String,
+    /// The author's token, without which scores can only be read.
+    token: Option<String>,
+    agent: Agent,
+}
+
+impl HighScores for RemoteHighScores {
+    fn save(&mut self, result: ScoreInfo) -> Result<(), LottoError> {
+        self.save_all(vec![result])
+    }
+
+    fn save_all(&mut self, results: Vec<ScoreInfo>) -> Result<(), LottoError> {
+        for saved in results.chunks(CHUNK) {
+            self.send(&Changes {
+                saved: saved.to_vec(),
+                removed: vec![],
+            })?;
+        }
+        Ok(())
+    }
+
+    fn load_all(&mut self, repo: Option<&RepoId>) -> Result<Vec<ScoreInfo>, LottoError> {
+        let query = repo.map(repo_query).unwrap_or_default();
+        let mut scores: Vec<ScoreInfo> = self.get("/api/scores/all", &query)?;
+        scores.sort_by_key(|s| -(s.score as i64));
+        Ok(scores)
+    }
+
+    fn best_in(
+        &mut self,
+        repo: &RepoId,
+        length: usize,
+        window: &Window,
+        except: &str,
+    ) -> Result<Option<ScoreInfo>, LottoError> {
+        let mut query = repo_query(repo);
+        query.push(("length", length.to_string()));
+        query.extend(window.since.map(|since| ("since", since.to_string())));
+        query.extend(window.until.map(|until| ("until", until.to_string())));
+        query.push(("except", except.to_string()));
+        self.get("/api/scores/best", &query)
+    }
+
+    fn load_by_author(&mut self, email: &str) -> Result<Vec<ScoreInfo>, LottoError> {
+        let mut scores: Vec<ScoreInfo> =
+            self.get("/api/scores/all", &[("author", email.to_string())])?;
+        scores.retain(ScoreInfo::counts);
+        scores.sort_by_key(|s| -(s.score as i64));
+        Ok(scores)
+    }
+
+    /// Only what `f` changed is sent, so other machines' scores saved in the
+    /// meantime are kept.
+    fn modify(&mut self, f: &mut dyn FnMut(&mut Vec<ScoreInfo>) -> bool) -> Result<(), LottoError> {
+        let before = self.load_all(None)?;
+        let mut after = before.clone();
+        if !f(&mut after) {
+            return Ok(());
+        }
+        let changes = Changes::between(&before, &after);
+        if changes.is_empty() {
+            return Ok(());
+        }
+        for saved in changes.saved.chunks(CHUNK) {
+            self.send(&Changes {
+                saved: saved.to_vec(),
+                removed: vec![],
+            })?;
+        }
+        if !changes.removed.is_empty() {
+            self.send(&Changes {
+                saved: vec![],
+                removed: changes.removed,
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Scores without an author can't be saved by anyone, so there are none
+    /// to fill in here.
+    fn migrate_authors(&mut self) -> Result<usize, LottoError> {
+        Ok(0)
+    }
+
+    fn load_awards(&mut self) -> Result<Awards, LottoError> {
+        self.get("/api/awards", &[])
+    }
+
+    /// The server archives seasons and unlocks achievements itself when
+    /// scores are saved, so nothing is sent.
+    fn modify_awards(&mut self, _: &mut dyn FnMut(&mut Awards) -> bool) -> Result<(), LottoError> {
+        Ok(())
+    }
+
+    fn check(&mut self) -> Result<StoreStatus, LottoError> {
+        self.get("/api/store", &[])
+    }
+
+    fn migrate(&mut self) -> Result<StoreStatus, LottoError> {
+        let status = self.check()?;
+        if status.needs_migration() {
+            return Err(LottoError::ServerError(anyhow!(
+                "the store can only be migrated on the server"
+            )));
+        }
+        Ok(status)
+    }
+}
+
+impl RemoteHighScores {
+    pub fn new(url: &str, token: Option<&str>) -> Self {
+        RemoteHighScores {
+            url: url.trim_end_matches('/').to_string(),
+            token: token.map(str::to_string),
+            agent: AgentBuilder::new().timeout(TIMEOUT).build(),
+        }
+    }
+
+    pub fn from_config(config: &StorageConfig) -> Result<Self, LottoError> {
+        match &config.url {
+            Some(url) => Ok(RemoteHighScores::new(url, config.token.as_deref())),
+            None => Err(LottoError::ConfigError(anyhow!(
+                "the remote backend needs storage.url"
+            ))),
+        }
+    }
+
+    fn get<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        query: &[(&str, String)],
+    ) -> Result<T, LottoError> {
+        query
+            .iter()
+            .fold(
+                self.agent.get(&format!("{}{}", self.url, path)),
+                |request, (name, value)| request.query(name, value),
+            )
+            .call()
+            .map_err(error)?
+            .into_json()
+            .map_err(|e| LottoError::ServerError(e.into()))
+    }
+
+    fn send(&self, changes: &Changes) -> Result<(), LottoError> {
+        let Some(token) = &self.token else {
+            return Err(LottoError::ConfigError(anyhow!(
+                "saving scores to the server needs storage.token"
+            )));
+        };
+        self.agent
+            .post(&format!("{}/api/scores", self.url))
+            .set("Authorization", &format!("Bearer {}", token))
+            .send_json(changes)
+            .map_err(error)?;
+        Ok(())
+    }
+}
+
+/// The query asking the server for a repository's scores.
+fn repo_query(repo: &RepoId) -> Vec<(&'static str, String)> {
+    let mut query = vec![("path", repo.path.display().to_string())];
+    query.extend(repo.root.clone().map(|root| ("root", root)));
+    query.extend(repo.remote.clone().map(|remote| ("remote", remote)));
+    query
+}
+
+/// Use the server's own explanation of an error when there is one.
+fn error(e: ureq::Error) -> LottoError {
+    match e {
+        ureq::Error::Status(status, response) => {
+            let body = response.into_string().unwrap_or_default();
+            let message = serde_json::from_str::<serde_json::Value>(&body)
+                .ok()
+                .and_then(|v| v["error"].as_str().map(str::to_string))
+                .unwrap_or(body);
+            LottoError::ServerError(anyhow!("status {}: {}", status, message))
+        }
+        e => LottoError::ServerError(e.into()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        config::Config, highscores::HighScoresImpl, server::ScoreServer, test_util::score_info,
+    };
+
+    #[test]
+    fn test_remote() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("scores.json");
+        let mut config = Config::default();
+        config
+            .storage
+            .tokens
+            .insert("someone@example.com".into(), "secret".into());
+        let server = ScoreServer::bind("127.0.0.1:0", &config).unwrap();
+        let url = format!("http://{}", server.addr());
+        std::thread::spawn(move || {
+            let mut highscores = HighScoresImpl::new(&path).unwrap();
+            server.run(&mut highscores)
+        });
+
+        let mut remote = RemoteHighScores::new(&url, Some("secret"));
+        assert_eq!(remote.check().unwrap().scores, 0);
+        let score = |commit: &str| ScoreInfo {
+            full_hash: Some(format!("{:0<40}", commit)),
+            ..score_info(commit, 0)
+        };
+        remote.save(score("aaaa123")).unwrap();
+        remote.save(score("bbbbbbb")).unwrap();
+        assert_eq!(remote.load(None).unwrap().len(), 2);
+        assert_eq!(
+            remote.load_by_author("someone@example.com").unwrap().len(),
+            2
+        );
+        assert!(remote
+            .load_by_author("nobody@example.com")
+            .unwrap()
+            .is_empty());
+        let repo = RepoId {
+            root: None,
+            remote: None,
+            path: "/repo".into(),
+        };
+        let elsewhere = RepoId {
+            path: "/elsewhere".into(),
+            ..repo.clone()
+        };
+        assert_eq!(remote.load_all(Some(&repo)).unwrap().len(), 2);
+        assert!(remote.load_all(Some(&elsewhere)).unwrap().is_empty());
+        let best = remote
+            .best_in(&repo, 7, &Window::default(), "bbbbbbb")
+            .unwrap();
+        assert_eq!(best.unwrap().commit, "aaaa123");
+
+        remote
+            .modify(&mut |scores| {
+                scores.retain(|s| s.commit == "bbbbbbb");
+                scores[0].superseded = true;
+                true
+            })
+            .unwrap();
+        assert!(remote.load(None).unwrap().is_empty());
+        assert_eq!(remote.load_all(None).unwrap().len(), 1);
+
+        let mut anonymous = RemoteHighScores::new(&url, None);
+        assert_eq!(anonymous.load_all(None).unwrap().len(), 1);
+        assert!(anonymous.save(score("ccccccc")).is_err());
+        let mut forged = RemoteHighScores::new(&url, Some("guess"));
+        assert!(matches!(
+            forged.save(score("ccccccc")),
+            Err(LottoError::ServerError(_))
+        ));
+
+        let mut unreachable = RemoteHighScores::new("http://127.0.0.1:1", None);
+        assert!(matches!(
+            unreachable.load(None),
+            Err(LottoError::ServerError(_))
+        ));
+    }
+}
@@ -0,0 +1,306 @@
+use std::{collections::HashSet, path::Path};
+
+use git2::{ErrorCode, Oid, Repository};
+
+use crate::{
+    anticheat,
+    config::{AntiCheatConfig, RewritePolicy, RulesConfig},
+    errors::LottoError,
+    git::{Commit, RepoId},
+    highscores::{same_path, HighScores, ScoreInfo},
+    scoring::score,
+};
+
+/// Parse the `<old> <new> [extra]` lines git gives the post-rewrite hook.
+pub fn parse_rewrites(input: &str) -> Vec<(String, String)> {
+    input
+        .lines()
+        .filter_map(|line| {
+            let mut hashes = line.split_whitespace();
+            Some((hashes.next()?.to_string(), hashes.next()?.to_string()))
+        })
+        .collect()
+}
+
+/// Record that commits were amended or rebased, given pairs of old and new
+/// full hashes. Returns how many scored commits were rewritten.
+pub fn rewritten(
+    repo: &Repository,
+    highscores: &mut impl HighScores,
+    rules: &RulesConfig,
+    anti_cheat: &AntiCheatConfig,
+    policy: RewritePolicy,
+    rewrites: &[(String, String)],
+) -> Result<usize, LottoError> {
+    let repo_id = RepoId::for_repo(repo)?;
+    let mut new_commits = vec![];
+    for (old, new) in rewrites {
+        let oid = Oid::from_str(new)?;
+        let commit = repo.find_commit(oid)?;
+        let mut commit = Commit::from_repo_and_commit(&repo_id, commit)?;
+        commit.provenance.rewritten = true;
+        new_commits.push((old, commit));
+    }
+    let mut count = 0;
+    highscores.modify(&mut |scores| {
+        count = 0;
+        for (old, commit) in &new_commits {
+            let mut new = ScoreInfo::new(&score(commit, rules), commit);
+            let repo_scores: Vec<ScoreInfo> = scores
+                .iter()
+                .filter(|s| s.is_from(&repo_id))
+                .cloned()
+                .collect();
+            anticheat::check(&mut new, commit, &repo_scores, anti_cheat);
+            if apply(scores, &repo_id, old, new, policy) {
+                count += 1;
+            }
+        }
+        count > 0
+    })?;
+    Ok(count)
+}
+
+/// Mark the scores of `old` as rewritten into `new`, adding `new`'s score if
+/// it hasn't been recorded, and decide which of them counts. Returns whether
+/// `old` had been scored.
+pub fn apply(
+    scores: &mut Vec<ScoreInfo>,
+    repo: &RepoId,
+    old: &str,
+    new: ScoreInfo,
+    policy: RewritePolicy,
+) -> bool {
+    let olds: Vec<usize> = (0..scores.len())
+        .filter(|&i| {
+            let score = &scores[i];
+            score.is_from(repo)
+                && score.rewritten_as.is_none()
+                && score.commit != new.commit
+                && old.starts_with(&score.commit)
+        })
+        .collect();
+    if olds.is_empty() {
+        return false;
+    }
+    let counted = lineage(scores, olds.clone())
+        .into_iter()
+        .find(|&i| !scores[i].superseded);
+    for &i in &olds {
+        scores[i].rewritten_as = Some(new.commit.clone());
+    }
+    let latest = match scores
+        .iter()
+        .position(|s| s.is_from(repo) && s.commit == new.commit)
+    {
+        Some(i) => i,
+        None => {
+            scores.push(new);
+            scores.len() - 1
+        }
+    };
+    if let Some(counted) = counted {
+        let keep_latest = match policy {
+            RewritePolicy::KeepBest => scores[latest].score > scores[counted].score,
+            RewritePolicy::KeepLatest => true,
+            RewritePolicy::ForbidRerolls => false,
+        };
+        scores[counted].superseded = keep_latest;
+        scores[latest].superseded = !keep_latest;
+    }
+    true
+}
+
+/// The given scores and every score that was rewritten into one of them.
+fn lineage(scores: &[ScoreInfo], mut lineage: Vec<usize>) -> Vec<usize> {
+    let mut i = 0;
+    while i < lineage.len() {
+        let commit = &scores[lineage[i]].commit;
+        let earlier: Vec<usize> = (0..scores.len())
+            .filter(|j| scores[*j].rewritten_as.as_ref() == Some(commit) && !lineage.contains(j))
+            .collect();
+        lineage.extend(earlier);
+        i += 1;
+    }
+    lineage
+}
+
+/// Drop the scores recorded in this clone for commits that no ref reaches
+/// any more, unless they count for a commit they were rewritten into that
+/// still is. Returns how many were dropped.
+pub fn prune(repo: &Repository, highscores: &mut impl HighScores) -> Result<usize, LottoError> {
+    let repo_id = RepoId::for_repo(repo)?;
+    let reachable = reachable(repo)?;
+    let is_live = |commit: &str| match repo.revparse_single(commit) {
+        Ok(object) => reachable.contains(&object.id()),
+        // can't tell which commit it was, so keep it
+        Err(e) => e.code() == ErrorCode::Ambiguous,
+    };
+    let mut pruned = 0;
+    highscores.modify(&mut |scores| {
+        let keep: Vec<bool> = scores
+            .iter()
+            .map(|s| {
+                !s.is_from(&repo_id)
+                    || !same_path(Path::new(&s.repo), &repo_id.path)
+                    || is_live(&s.commit)
+                    || (!s.superseded && rewritten_is_live(scores, s, &is_live))
+            })
+            .collect();
+        let mut keep = keep.into_iter();
+        let before = scores.len();
+        scores.retain(|_| keep.next().unwrap());
+        pruned = before - scores.len();
+        pruned > 0
+    })?;
+    Ok(pruned)
+}
+
+/// Whether any commit `score` was rewritten into is still reachable.
+fn rewritten_is_live(
+    scores: &[ScoreInfo],
+    score: &ScoreInfo,
+    is_live: &dyn Fn(&str) -> bool,
+) -> bool {
+    let mut next = score.rewritten_as.clone();
+    // bounded in case of a cycle
+    for _ in 0..=scores.len() {
+        let Some(commit) = next else {
+            return false;
+        };
+        if is_live(&commit) {
+            return true;
+        }
+        next = scores
+            .iter()
+            .find(|s| s.commit == commit)
+            .and_then(|s| s.rewritten_as.clone());
+    }
+    false
+}
+
+/// Every commit reachable from HEAD or a ref.
+fn reachable(repo: &Repository) -> Result<HashSet<Oid>, LottoError> {
+    let mut walk = repo.revwalk()?;
+    walk.push_glob("*")?;
+    // HEAD may be detached, or unborn
+    let _ = walk.push_head();
+    walk.collect::<Result<_, _>>().map_err(LottoError::Git)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{highscores::HighScoresImpl, test_util::commit};
+
+    fn scored(repo: &Repository, oid: Oid, points: u64) -> ScoreInfo {
+        let id = RepoId::for_repo(repo).unwrap();
+        let commit = Commit::from_repo_and_commit(&id, repo.find_commit(oid).unwrap()).unwrap();
+        let mut score = ScoreInfo::new(&score(&commit, &RulesConfig::default()), &commit);
+        score.score = points;
+        score
+    }
+
+    #[test]
+    fn test_policies() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        let a = commit(&repo, "a@example.com", 1_000);
+        let b = commit(&repo, "a@example.com", 2_000);
+        let c = commit(&repo, "a@example.com", 3_000);
+        let id = RepoId::for_repo(&repo).unwrap();
+        let counted = |scores: &[ScoreInfo]| -> Vec<u64> {
+            scores
+                .iter()
+                .filter(|s| !s.superseded)
+                .map(|s| s.score)
+                .collect()
+        };
+        let rewrite = |policy| {
+            let mut scores = vec![scored(&repo, a, 50)];
+            apply(
+                &mut scores,
+                &id,
+                &a.to_string(),
+                scored(&repo, b, 10),
+                policy,
+            );
+            apply(
+                &mut scores,
+                &id,
+                &b.to_string(),
+                scored(&repo, c, 30),
+                policy,
+            );
+            scores
+        };
+
+        let scores = rewrite(RewritePolicy::KeepBest);
+        assert_eq!(scores.len(), 3);
+        assert_eq!(counted(&scores), vec![50]);
+        assert_eq!(scores[0].rewritten_as, Some(scores[1].commit.clone()));
+        assert_eq!(counted(&rewrite(RewritePolicy::KeepLatest)), vec![30]);
+        assert_eq!(counted(&rewrite(RewritePolicy::ForbidRerolls)), vec![50]);
+
+        // commits nobody scored are left alone
+        let mut scores = vec![];
+        let new = scored(&repo, c, 30);
+        assert!(!apply(
+            &mut scores,
+            &id,
+            &b.to_string(),
+            new,
+            RewritePolicy::KeepBest
+        ));
+        assert!(scores.is_empty());
+    }
+
+    #[test]
+    fn test_prune() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path().join("repo")).unwrap();
+        let a = commit(&repo, "a@example.com", 1_000);
+        let b = commit(&repo, "a@example.com", 2_000);
+        let mut highscores = HighScoresImpl::new(&dir.path().join("scores.json")).unwrap();
+        highscores
+            .save_all(vec![scored(&repo, a, 10), scored(&repo, b, 20)])
+            .unwrap();
+
+        // amend b into c, keeping b's score
+        repo.reset(
+            repo.find_commit(a).unwrap().as_object(),
+            git2::ResetType::Soft,
+            None,
+        )
+        .unwrap();
+        let c = commit(&repo, "a@example.com", 3_000);
+        let rules = RulesConfig::default();
+        let rewrites = parse_rewrites(&format!("{} {}\n", b, c));
+        assert_eq!(
+            rewritten(
+                &repo,
+                &mut highscores,
+                &rules,
+                &AntiCheatConfig::default(),
+                RewritePolicy::ForbidRerolls,
+                &rewrites
+            )
+            .unwrap(),
+            1
+        );
+        assert_eq!(highscores.load_all(None).unwrap().len(), 3);
+
+        // b is unreachable but still counts for c, and c is reachable
+        assert_eq!(prune(&repo, &mut highscores).unwrap(), 0);
+
+        // dropping c makes both unreachable
+        repo.reset(
+            repo.find_commit(a).unwrap().as_object(),
+            git2::ResetType::Soft,
+            None,
+        )
+        .unwrap();
+        assert_eq!(prune(&repo, &mut highscores).unwrap(), 2);
+        assert_eq!(highscores.load(None).unwrap().len(), 1);
+    }
+}
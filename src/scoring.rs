@@ -0,0 +1,241 @@
+use crate::{
+    achievements, anticheat,
+    config::{AntiCheatConfig, RulesConfig, SeasonConfig},
+    errors::LottoError,
+    git::Commit,
+    highscores::{HighScores, ScoreInfo},
+    lotto::LottoResult,
+    output::TerminalOutputer,
+    period::{local_date, Period},
+    seasons,
+};
+
+/// Score a commit with the rules enabled in the config.
+pub fn score<'a>(commit: &'a Commit, rules: &RulesConfig) -> LottoResult<'a> {
+    score_hash(&commit.full_hash, rules)
+}
+
+/// Score the configured number of characters of a full hash, however many
+/// git would abbreviate it to.
+pub fn score_hash<'a>(full_hash: &'a str, rules: &RulesConfig) -> LottoResult<'a> {
+    let hash = &full_hash[..rules.length.min(full_hash.len())];
+    let result = LottoResult::with_rules(hash, rules).with_full_hash(full_hash);
+    if rules.poker {
+        result.with_cards(full_hash, rules.deal)
+    } else {
+        result
+    }
+}
+
+/// Scores recorded before the length could be configured were scored on git's
+/// short hash, which is longer than the default in big repositories, and with
+/// points that didn't account for how rules combine. They are all scored again
+/// on at most the default length with the default rules, less the poker hand
+/// since the full hash wasn't kept. `None` if there is no hash.
+pub fn legacy_score(short_hash: &str) -> Option<LottoResult<'_>> {
+    let rules = RulesConfig {
+        poker: false,
+        ..Default::default()
+    };
+    let hash = short_hash.get(..rules.length).unwrap_or(short_hash);
+    (!hash.is_empty()).then(|| LottoResult::with_rules(hash, &rules))
+}
+
+/// Show the score of a commit that has just been made and save it, announcing
+/// if it beats the repository's high score, or failing that its high score
+/// for the season, month or week, and any achievements its author unlocks.
+pub fn record(
+    output: &impl TerminalOutputer,
+    highscores: &mut impl HighScores,
+    rules: &RulesConfig,
+    anti_cheat: &AntiCheatConfig,
+    seasons: &[SeasonConfig],
+    commit: &Commit,
+) -> Result<(), LottoError> {
+    let results = score(commit, rules);
+    let scores = highscores.load_all(Some(&commit.repo))?;
+    let mut score = ScoreInfo::new(&results, commit);
+    anticheat::check(&mut score, commit, &scores, anti_cheat);
+    output.post_commit(&results);
+    if score.counts() {
+        for period in Period::announced(score.date, seasons) {
+            let Some(window) = period.around(score.date, seasons) else {
+                continue;
+            };
+            let best = highscores.best_in(&commit.repo, score.length, &window, &score.commit)?;
+            if let Some(old_score) = best.filter(|old| score.score > old.score) {
+                output.high_score(&score, &old_score, &period);
+                break;
+            }
+        }
+    }
+    // the post-rewrite hook may have recorded an amended commit already
+    if scores.iter().any(|s| s.key() == score.key()) {
+        output.finished();
+        return Ok(());
+    }
+    // saved first, since a server unlocks achievements itself
+    highscores.save(score.clone())?;
+    for unlocked in achievements::unlock(highscores, &[score.clone()])? {
+        output.achievement(&unlocked);
+    }
+    output.finished();
+    seasons::archive_ended(seasons, highscores, rules.length, local_date(score.date))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use std::cell::RefCell;
+
+    use super::*;
+    use crate::{
+        achievements::Unlocked,
+        config::MAX_LENGTH,
+        git::RepoId,
+        highscores::HighScoresImpl,
+        lotto::RuleId,
+        test_util::{person, score_info},
+    };
+
+    /// Remembers which leaderboards were announced.
+    #[derive(Default)]
+    struct Announcements(RefCell<Vec<(Period, String)>>);
+
+    impl TerminalOutputer for Announcements {
+        fn pre_commit(&self) {}
+        fn post_commit(&self, _result: &LottoResult) {}
+        fn result(&self, _result: &LottoResult) {}
+        fn failed(&self) {}
+        fn high_score(&self, _new: &ScoreInfo, old: &ScoreInfo, period: &Period) {
+            self.0
+                .borrow_mut()
+                .push((period.clone(), old.commit.clone()));
+        }
+        fn achievement(&self, _unlocked: &Unlocked) {}
+    }
+
+    #[test]
+    fn test_scoring_length() {
+        let full_hash = "abcdefabcdef0123456789012345678901234567";
+        for length in [7, 9, MAX_LENGTH] {
+            let rules = RulesConfig {
+                length,
+                poker: false,
+                ..Default::default()
+            };
+            let result = score_hash(full_hash, &rules);
+            assert_eq!(result.hash.len(), length);
+        }
+        // the same flush is far less likely over more characters
+        let flush = LottoResult::new("abcdefabcd");
+        assert!(flush.total_points() > LottoResult::new("abcdefa").total_points());
+    }
+
+    #[test]
+    fn test_full_house_stays_rare() {
+        let full_house = RuleId::FullHouse.probability(MAX_LENGTH);
+        assert!(full_house.unwrap() < 0.25, "{:?}", full_house);
+    }
+
+    #[test]
+    fn test_legacy_score() {
+        assert!(legacy_score("").is_none());
+        let result = legacy_score("aaaabbbb0").unwrap();
+        assert_eq!(result.hash, "aaaabbb");
+        // short enough already, but scored with the current points
+        let short = legacy_score("aaaabbb").unwrap();
+        assert_eq!(short.total_points(), result.total_points());
+        assert_eq!(legacy_score("abab").unwrap().hash, "abab");
+        assert!(result.rules.iter().all(|r| !r.name().starts_with("Poker")));
+    }
+
+    #[test]
+    fn test_record_ignores_scores_that_dont_count() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut highscores = HighScoresImpl::new(&dir.path().join("scores.json")).unwrap();
+        highscores
+            .save_all(vec![
+                score_info("0000001", 1),
+                ScoreInfo {
+                    mined: true,
+                    ..score_info("0000002", 1_000_000_000)
+                },
+                ScoreInfo {
+                    disqualified: true,
+                    ..score_info("0000003", 1_000_000_000)
+                },
+            ])
+            .unwrap();
+        let person = person("someone@example.com");
+        let repo = RepoId {
+            root: None,
+            remote: None,
+            path: "/repo".into(),
+        };
+        let full_hash = format!("{:0<40}", "1234567");
+        let commit = Commit::new(
+            repo,
+            "1234567".into(),
+            full_hash,
+            "1000".into(),
+            None,
+            person.clone(),
+            person,
+        );
+        let output = Announcements::default();
+        record(
+            &output,
+            &mut highscores,
+            &RulesConfig::default(),
+            &AntiCheatConfig::default(),
+            &[],
+            &commit,
+        )
+        .unwrap();
+        assert_eq!(
+            output.0.into_inner(),
+            vec![(Period::AllTime, "0000001".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_record_tells_commits_apart_by_full_hash() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut highscores = HighScoresImpl::new(&dir.path().join("scores.json")).unwrap();
+        // another commit with the same short hash
+        highscores
+            .save(ScoreInfo {
+                full_hash: Some(format!("{:f<40}", "1234567")),
+                ..score_info("1234567", 1)
+            })
+            .unwrap();
+        let person = person("someone@example.com");
+        let repo = RepoId {
+            root: None,
+            remote: None,
+            path: "/repo".into(),
+        };
+        let commit = Commit::new(
+            repo,
+            "1234567".into(),
+            format!("{:0<40}", "1234567"),
+            "1000".into(),
+            None,
+            person.clone(),
+            person,
+        );
+        for _ in 0..2 {
+            record(
+                &Announcements::default(),
+                &mut highscores,
+                &RulesConfig::default(),
+                &AntiCheatConfig::default(),
+                &[],
+                &commit,
+            )
+            .unwrap();
+        }
+        assert_eq!(highscores.load_all(None).unwrap().len(), 2);
+    }
+}
@@ -0,0 +1,228 @@
+use std::{
+    fs::{self, File},
+    io,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::SeasonConfig,
+    errors::LottoError,
+    highscores::{data_dir, sibling, HighScores, ScoreInfo},
+    store::Awards,
+};
+
+/// How many of a season's best scores are archived when it ends.
+pub const WINNERS: usize = 3;
+
+/// The winners of a season that has ended, kept even if their scores are
+/// later pruned or the season is removed from the config.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ArchivedSeason {
+    pub name: String,
+    pub start: NaiveDate,
+    pub end: NaiveDate,
+    /// The hash length the winners were scored at.
+    pub length: usize,
+    pub winners: Vec<ScoreInfo>,
+}
+
+/// The best scores that count from a season, best first.
+pub fn winners(scores: &[ScoreInfo], season: &SeasonConfig, length: usize) -> Vec<ScoreInfo> {
+    let window = season.window();
+    let mut winners: Vec<ScoreInfo> = scores
+        .iter()
+        .filter(|s| s.counts() && s.length == length && window.contains(s.date))
+        .cloned()
+        .collect();
+    winners.sort_by_key(|s| std::cmp::Reverse(s.score));
+    winners.truncate(WINNERS);
+    winners
+}
+
+/// Archive the winners of every season that ended before `today` and hasn't
+/// been archived yet, returning the newly archived ones. They are kept with
+/// the scores, so they're shared with whoever shares the scores.
+pub fn archive_ended(
+    seasons: &[SeasonConfig],
+    highscores: &mut (impl HighScores + ?Sized),
+    length: usize,
+    today: NaiveDate,
+) -> Result<Vec<ArchivedSeason>, LottoError> {
+    let ended: Vec<&SeasonConfig> = seasons.iter().filter(|s| s.end < today).collect();
+    if ended.is_empty() {
+        return Ok(vec![]);
+    }
+    let archived = highscores.load_awards()?.seasons;
+    let ended: Vec<&SeasonConfig> = ended
+        .into_iter()
+        .filter(|s| !archived.iter().any(|a| a.name == s.name))
+        .collect();
+    if ended.is_empty() {
+        return Ok(vec![]);
+    }
+    let scores = highscores.load(None)?;
+    let mut new: Vec<ArchivedSeason> = ended
+        .into_iter()
+        .map(|season| ArchivedSeason {
+            name: season.name.clone(),
+            start: season.start,
+            end: season.end,
+            length,
+            winners: winners(&scores, season, length),
+        })
+        .collect();
+    highscores.modify_awards(&mut |awards| {
+        // another process may have archived some in the meantime
+        new.retain(|season| !awards.seasons.iter().any(|a| a.name == season.name));
+        awards.add_missing(Awards {
+            seasons: new.clone(),
+            ..Default::default()
+        })
+    })?;
+    Ok(new)
+}
+
+/// Where seasons were archived before they were kept with the scores.
+pub fn legacy_path() -> Result<PathBuf, LottoError> {
+    Ok(data_dir()?.join("seasons.json"))
+}
+
+/// Move the seasons archived in a file on this machine into the store,
+/// renaming the file once they're all there.
+pub fn import_legacy(
+    path: &Path,
+    highscores: &mut (impl HighScores + ?Sized),
+) -> Result<(), LottoError> {
+    let archived: Vec<ArchivedSeason> = match File::open(path) {
+        Ok(file) => serde_json::from_reader(io::BufReader::new(file))
+            .with_context(|| format!("reading {}", path.display()))
+            .map_err(LottoError::ApplicationDirError)?,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e.into()),
+    };
+    let mut missing = Some(Awards {
+        seasons: archived.clone(),
+        ..Default::default()
+    });
+    highscores
+        .modify_awards(&mut |awards| awards.add_missing(missing.take().unwrap_or_default()))?;
+    // a server archives its own seasons, so the file is kept if it can't
+    let kept = highscores.load_awards()?.seasons;
+    if archived
+        .iter()
+        .all(|a| kept.iter().any(|k| k.name == a.name))
+    {
+        fs::rename(path, sibling(path, "imported"))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{highscores::HighScoresImpl, period::midnight, test_util::score_info};
+
+    fn date(text: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(text, "%Y-%m-%d").unwrap()
+    }
+
+    fn score(commit: &str, points: u64, day: &str) -> ScoreInfo {
+        ScoreInfo {
+            date: midnight(date(day)) + 3600,
+            ..score_info(commit, points)
+        }
+    }
+
+    #[test]
+    fn test_archive_ended() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut highscores = HighScoresImpl::new(&dir.path().join("scores.json")).unwrap();
+        let mut superseded = score("ddddddd", 90, "2026-09-10");
+        superseded.superseded = true;
+        highscores
+            .save_all(vec![
+                score("aaaaaaa", 10, "2026-09-01"),
+                score("bbbbbbb", 50, "2026-11-30"),
+                score("ccccccc", 70, "2026-12-01"),
+                superseded,
+            ])
+            .unwrap();
+        let seasons = vec![
+            SeasonConfig {
+                name: "Autumn".into(),
+                start: date("2026-09-01"),
+                end: date("2026-11-30"),
+            },
+            SeasonConfig {
+                name: "Winter".into(),
+                start: date("2026-12-01"),
+                end: date("2027-02-28"),
+            },
+        ];
+
+        // nothing has ended on the last day of autumn
+        let archived = archive_ended(&seasons, &mut highscores, 7, date("2026-11-30")).unwrap();
+        assert!(archived.is_empty());
+
+        let archived = archive_ended(&seasons, &mut highscores, 7, date("2026-12-15")).unwrap();
+        assert_eq!(archived.len(), 1);
+        let commits: Vec<&str> = archived[0]
+            .winners
+            .iter()
+            .map(|s| s.commit.as_str())
+            .collect();
+        assert_eq!(commits, vec!["bbbbbbb", "aaaaaaa"]);
+        // archived once, even if scores change later
+        highscores.save(score("eeeeeee", 99, "2026-10-01")).unwrap();
+        assert!(
+            archive_ended(&seasons, &mut highscores, 7, date("2026-12-16"))
+                .unwrap()
+                .is_empty()
+        );
+        assert_eq!(highscores.load_awards().unwrap().seasons, archived);
+    }
+
+    #[test]
+    fn test_import_legacy() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut highscores = HighScoresImpl::new(&dir.path().join("scores.json")).unwrap();
+        let path = dir.path().join("seasons.json");
+        let season = |name: &str| ArchivedSeason {
+            name: name.into(),
+            start: date("2026-09-01"),
+            end: date("2026-11-30"),
+            length: 7,
+            winners: vec![],
+        };
+        highscores
+            .modify_awards(&mut |awards| {
+                awards.seasons = vec![season("Autumn")];
+                true
+            })
+            .unwrap();
+        let legacy = vec![
+            ArchivedSeason {
+                length: 8,
+                ..season("Autumn")
+            },
+            season("Summer"),
+        ];
+        fs::write(&path, serde_json::to_string(&legacy).unwrap()).unwrap();
+
+        import_legacy(&path, &mut highscores).unwrap();
+        let names: Vec<(String, usize)> = highscores
+            .load_awards()
+            .unwrap()
+            .seasons
+            .into_iter()
+            .map(|s| (s.name, s.length))
+            .collect();
+        assert_eq!(names, vec![("Autumn".into(), 7), ("Summer".into(), 7)]);
+        assert!(!path.exists());
+        import_legacy(&path, &mut highscores).unwrap();
+    }
+}
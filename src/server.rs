@@ -0,0 +1,781 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    io::Read,
+    mem::discriminant,
+    path::PathBuf,
+    str::FromStr,
+};
+
+use anyhow::anyhow;
+use chrono::{TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use tiny_http::{Header, Response, Server};
+
+use crate::{
+    achievements, anticheat,
+    config::{AntiCheatConfig, Config, RulesConfig, SeasonConfig},
+    errors::LottoError,
+    git::{ObjectFormat, RepoId},
+    highscores::{by_author, HighScores, ScoreInfo},
+    period::{local_date, Window},
+    scoring::score_hash,
+    seasons::archive_ended,
+};
+
+/// How many scores the API and page list unless asked for more.
+const DEFAULT_COUNT: usize = 20;
+/// The largest request body taken, in bytes.
+pub const MAX_BODY: usize = 4 << 20;
+
+/// Scores to add or replace, and the keys of scores to drop, as sent by the
+/// remote backend.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Changes {
+    #[serde(default)]
+    pub saved: Vec<ScoreInfo>,
+    #[serde(default)]
+    pub removed: Vec<(String, String)>,
+}
+
+impl Changes {
+    /// What turns `before` into `after`, going by [`ScoreInfo::key`].
+    pub fn between(before: &[ScoreInfo], after: &[ScoreInfo]) -> Self {
+        let before_by_key: HashMap<_, _> = before.iter().map(|s| (s.key(), s)).collect();
+        let after_keys: Vec<_> = after.iter().map(|s| s.key()).collect();
+        Changes {
+            saved: after
+                .iter()
+                .filter(|s| before_by_key.get(&s.key()) != Some(s))
+                .cloned()
+                .collect(),
+            removed: before
+                .iter()
+                .map(|s| s.key())
+                .filter(|k| !after_keys.contains(k))
+                .collect(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.saved.is_empty() && self.removed.is_empty()
+    }
+
+    pub fn apply(&self, scores: &mut Vec<ScoreInfo>) {
+        scores.retain(|s| !self.removed.contains(&s.key()));
+        for saved in &self.saved {
+            match scores.iter_mut().find(|s| s.key() == saved.key()) {
+                Some(score) => *score = saved.clone(),
+                None => scores.push(saved.clone()),
+            }
+        }
+    }
+}
+
+/// Which scores a request asks for, from its query string.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct Filter {
+    n: Option<usize>,
+    /// A root commit, remote URL or path.
+    repo: Option<String>,
+    /// The [`RepoId`] of a repository, as the remote backend sends it.
+    root: Option<String>,
+    remote: Option<String>,
+    path: Option<String>,
+    author: Option<String>,
+    /// Unix timestamps bounding the commit date, `until` exclusive.
+    since: Option<i64>,
+    until: Option<i64>,
+    length: Option<usize>,
+    /// A commit whose score is left out.
+    except: Option<String>,
+}
+
+impl Filter {
+    fn parse(query: &str) -> Result<Self, String> {
+        let mut filter = Filter::default();
+        for pair in query.split('&').filter(|p| !p.is_empty()) {
+            let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+            let value = decode(value);
+            match name {
+                "n" => filter.n = Some(number(name, &value)?),
+                "since" => filter.since = Some(number(name, &value)?),
+                "until" => filter.until = Some(number(name, &value)?),
+                "length" => filter.length = Some(number(name, &value)?),
+                "repo" => filter.repo = Some(value),
+                "root" => filter.root = Some(value),
+                "remote" => filter.remote = Some(value),
+                "path" => filter.path = Some(value),
+                "author" => filter.author = Some(value),
+                "except" => filter.except = Some(value),
+                _ => return Err(format!("unknown parameter {}", name)),
+            }
+        }
+        Ok(filter)
+    }
+
+    /// Whether the filter takes the score, which has to be of hashes
+    /// `length` long if the filter doesn't say.
+    fn matches(&self, score: &ScoreInfo, length: Option<usize>) -> bool {
+        self.length.or(length).is_none_or(|l| score.length == l)
+            && self.repo.as_ref().is_none_or(|repo| {
+                score.root.as_ref() == Some(repo)
+                    || score.remote.as_ref() == Some(repo)
+                    || score.repo == *repo
+            })
+            && self.repo_id().is_none_or(|repo| score.is_from(&repo))
+            && self.author.as_ref().is_none_or(|a| score.is_by(a))
+            && Window {
+                since: self.since,
+                until: self.until,
+            }
+            .contains(score.date)
+            && self.except.as_ref().is_none_or(|c| score.commit != *c)
+    }
+
+    fn repo_id(&self) -> Option<RepoId> {
+        if self.root.is_none() && self.path.is_none() {
+            return None;
+        }
+        Some(RepoId {
+            root: self.root.clone(),
+            remote: self.remote.clone(),
+            path: PathBuf::from(self.path.clone().unwrap_or_default()),
+        })
+    }
+}
+
+fn number<T: FromStr>(name: &str, value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("{} must be a number", name))
+}
+
+/// Undo the percent encoding of a query string value.
+fn decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = vec![];
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => decoded.push(b' '),
+            b'%' if i + 2 < bytes.len() => {
+                let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).unwrap_or_default();
+                match u8::from_str_radix(hex, 16) {
+                    Ok(byte) => {
+                        decoded.push(byte);
+                        i += 2;
+                    }
+                    Err(_) => decoded.push(b'%'),
+                }
+            }
+            byte => decoded.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+struct Reply {
+    status: u16,
+    content_type: &'static str,
+    body: String,
+}
+
+impl Reply {
+    fn json(value: &impl Serialize) -> Self {
+        Reply {
+            status: 200,
+            content_type: "application/json",
+            body: serde_json::to_string(value).unwrap_or_default(),
+        }
+    }
+
+    fn error(status: u16, message: impl ToString) -> Self {
+        Reply {
+            status,
+            ..Reply::json(&serde_json::json!({ "error": message.to_string() }))
+        }
+    }
+}
+
+/// Serves the leaderboard over HTTP: JSON under `/api` and a page at `/`.
+///
+/// Anyone can read the scores, but saving them takes an author's token, and
+/// then only that author's scores can be saved or removed. The server scores
+/// them again with its own rules, decides which are disqualified, and unlocks
+/// achievements and archives seasons itself.
+///
+/// It never sees the commits, so it trusts the author for what only a commit
+/// shows: whether it was mined, and the flags for its parents and author date.
+/// It does flag a tree scored too often and a date in the future, bringing the
+/// date back to now, and never drops a flag or makes a mined score unmined.
+pub struct ScoreServer {
+    server: Server,
+    /// Scores are scored with these, and listed for their length unless
+    /// `length` is given.
+    rules: RulesConfig,
+    anti_cheat: AntiCheatConfig,
+    /// Archived with the server's own scores when they end.
+    seasons: Vec<SeasonConfig>,
+    /// Each author's token, by email.
+    tokens: BTreeMap<String, String>,
+}
+
+impl ScoreServer {
+    pub fn bind(addr: &str, config: &Config) -> Result<Self, LottoError> {
+        let server = Server::http(addr).map_err(|e| LottoError::ServerError(anyhow!(e)))?;
+        Ok(ScoreServer {
+            server,
+            rules: config.rules.clone(),
+            anti_cheat: config.anti_cheat.clone(),
+            seasons: config.seasons.clone(),
+            tokens: config.storage.tokens.clone(),
+        })
+    }
+
+    pub fn addr(&self) -> String {
+        self.server.server_addr().to_string()
+    }
+
+    /// Answer requests one at a time, forever.
+    pub fn run(&self, highscores: &mut dyn HighScores) -> Result<(), LottoError> {
+        for mut request in self.server.incoming_requests() {
+            let token = request
+                .headers()
+                .iter()
+                .find(|h| h.field.equiv("Authorization"))
+                .and_then(|h| h.value.as_str().strip_prefix("Bearer "))
+                .map(str::to_string);
+            let mut body = String::new();
+            let reply = if request.body_length().is_some_and(|n| n > MAX_BODY) {
+                Reply::error(413, "request body is too large")
+            } else {
+                match request
+                    .as_reader()
+                    .take(MAX_BODY as u64 + 1)
+                    .read_to_string(&mut body)
+                {
+                    Ok(n) if n > MAX_BODY => Reply::error(413, "request body is too large"),
+                    Ok(_) => self.respond(
+                        highscores,
+                        request.method().as_str(),
+                        request.url(),
+                        token.as_deref(),
+                        &body,
+                    ),
+                    Err(e) => Reply::error(400, e),
+                }
+            };
+            let header = Header::from_bytes("Content-Type", reply.content_type)
+                .map_err(|_| LottoError::ServerError(anyhow!("bad header")))?;
+            let response = Response::from_string(reply.body)
+                .with_status_code(reply.status)
+                .with_header(header);
+            // the client may have gone away, which is no reason to stop
+            let _ = request.respond(response);
+        }
+        Ok(())
+    }
+
+    fn respond(
+        &self,
+        highscores: &mut dyn HighScores,
+        method: &str,
+        url: &str,
+        token: Option<&str>,
+        body: &str,
+    ) -> Reply {
+        let (path, query) = url.split_once('?').unwrap_or((url, ""));
+        let filter = match Filter::parse(query) {
+            Ok(filter) => filter,
+            Err(e) => return Reply::error(400, e),
+        };
+        let result = match (method, path) {
+            ("GET", "/") => self.top(highscores, &filter).map(|scores| Reply {
+                status: 200,
+                content_type: "text/html; charset=utf-8",
+                body: page(&scores, filter.n.unwrap_or(DEFAULT_COUNT)),
+            }),
+            ("GET", "/api/scores") => self.top(highscores, &filter).map(|mut scores| {
+                scores.truncate(filter.n.unwrap_or(DEFAULT_COUNT));
+                Reply::json(&scores)
+            }),
+            ("GET", "/api/authors") => self.top(highscores, &filter).map(|scores| {
+                let mut authors = by_author(&scores);
+                authors.truncate(filter.n.unwrap_or(DEFAULT_COUNT));
+                Reply::json(&authors)
+            }),
+            // every score, counting or not, for the remote backend
+            ("GET", "/api/scores/all") => highscores.load_all(None).map(|mut scores| {
+                scores.retain(|s| filter.matches(s, None));
+                Reply::json(&scores)
+            }),
+            ("GET", "/api/scores/best") => self
+                .top(highscores, &filter)
+                .map(|scores| Reply::json(&scores.first())),
+            ("POST", "/api/scores") => Ok(self.save(highscores, token, body)),
+            ("GET", "/api/awards") => self
+                .archive(highscores)
+                .and_then(|_| highscores.load_awards())
+                .map(|awards| Reply::json(&awards)),
+            ("GET", "/api/store") => highscores.check().map(|status| Reply::json(&status)),
+            (
+                _,
+                "/" | "/api/scores" | "/api/authors" | "/api/scores/all" | "/api/scores/best"
+                | "/api/awards" | "/api/store",
+            ) => Ok(Reply::error(405, "method not allowed")),
+            _ => Ok(Reply::error(404, "not found")),
+        };
+        result.unwrap_or_else(|e| Reply::error(500, e))
+    }
+
+    /// The scores that count and match the filter, best first.
+    fn top(
+        &self,
+        highscores: &mut dyn HighScores,
+        filter: &Filter,
+    ) -> Result<Vec<ScoreInfo>, LottoError> {
+        let mut scores = highscores.load(None)?;
+        scores.retain(|s| filter.matches(s, Some(self.rules.length)));
+        Ok(scores)
+    }
+
+    /// Apply the changes sent by the author whose token this is.
+    fn save(&self, highscores: &mut dyn HighScores, token: Option<&str>, body: &str) -> Reply {
+        let Some(caller) = token.and_then(|token| self.caller(token)) else {
+            return Reply::error(401, "saving scores needs an author's token");
+        };
+        let changes = match serde_json::from_str::<Changes>(body) {
+            Ok(changes) => changes,
+            Err(e) => return Reply::error(400, e),
+        };
+        let mut refused = None;
+        let mut saved = vec![];
+        let result = highscores.modify(&mut |scores| match self.accept(&changes, caller, scores) {
+            Ok(changes) => {
+                changes.apply(scores);
+                saved = changes.saved.clone();
+                !changes.is_empty()
+            }
+            Err(reply) => {
+                refused = Some(reply);
+                false
+            }
+        });
+        let result = result
+            .and_then(|_| achievements::unlock(highscores, &saved))
+            .and_then(|_| self.archive(highscores));
+        match (refused, result) {
+            (Some(reply), _) => reply,
+            (None, Ok(())) => Reply::json(&serde_json::json!({})),
+            (None, Err(e)) => Reply::error(500, e),
+        }
+    }
+
+    /// Archive the seasons that have ended.
+    fn archive(&self, highscores: &mut dyn HighScores) -> Result<(), LottoError> {
+        let today = local_date(Utc::now().timestamp());
+        archive_ended(&self.seasons, highscores, self.rules.length, today)?;
+        Ok(())
+    }
+
+    /// The email of the author a token belongs to.
+    fn caller(&self, token: &str) -> Option<&str> {
+        self.tokens
+            .iter()
+            .find(|(_, t)| same(t.as_bytes(), token.as_bytes()))
+            .map(|(email, _)| email.as_str())
+    }
+
+    /// The changes as the server will make them, if `caller` may make them:
+    /// only to their own scores, scored again from the full hash, and never
+    /// making a mined or flagged score count.
+    fn accept(
+        &self,
+        changes: &Changes,
+        caller: &str,
+        scores: &[ScoreInfo],
+    ) -> Result<Changes, Reply> {
+        let existing = |key: &(String, String)| scores.iter().find(|s| s.key() == *key);
+        let forbidden = || Reply::error(403, "only the author of a score can change it");
+        for key in &changes.removed {
+            if existing(key).is_some_and(|s| !s.is_by(caller)) {
+                return Err(forbidden());
+            }
+        }
+        let mut saved = vec![];
+        for score in &changes.saved {
+            let old = existing(&score.key());
+            if !score.is_by(caller) || old.is_some_and(|s| !s.is_by(caller)) {
+                return Err(forbidden());
+            }
+            if old.is_some_and(|s| s.mined) && !score.mined {
+                return Err(Reply::error(400, "a mined score stays mined"));
+            }
+            saved.push(self.rescore(score, old, scores)?);
+        }
+        Ok(Changes {
+            saved,
+            removed: changes.removed.clone(),
+        })
+    }
+
+    /// Score a hash again with the server's rules, and check what can be
+    /// checked without its commit: flags already given to it are kept, its
+    /// tree is checked against the repository's scores, and a date in the
+    /// future is flagged and brought back to now.
+    fn rescore(
+        &self,
+        score: &ScoreInfo,
+        old: Option<&ScoreInfo>,
+        scores: &[ScoreInfo],
+    ) -> Result<ScoreInfo, Reply> {
+        let full_hash = score
+            .full_hash
+            .as_deref()
+            .filter(|hash| {
+                let format = ObjectFormat::of_hash(hash);
+                hash.len() == format.hex_len()
+                    && hash
+                        .chars()
+                        .all(|c| c.is_ascii_hexdigit() && !c.is_ascii_uppercase())
+                    && !score.commit.is_empty()
+                    && hash.starts_with(&score.commit)
+            })
+            .ok_or_else(|| Reply::error(400, "scores need the full hash of their commit"))?;
+        let result = score_hash(full_hash, &self.rules);
+        let now = Utc::now().timestamp();
+        let repo = score.key().0;
+        let repo_scores: Vec<ScoreInfo> = scores
+            .iter()
+            .filter(|s| s.key().0 == repo)
+            .cloned()
+            .collect();
+        let mut flags = score.flags.clone();
+        let checked = anticheat::inspect_score(score, &repo_scores, &self.anti_cheat, now);
+        for flag in old.into_iter().flat_map(|s| s.flags.clone()).chain(checked) {
+            if !flags.iter().any(|f| discriminant(f) == discriminant(&flag)) {
+                flags.push(flag);
+            }
+        }
+        Ok(ScoreInfo {
+            score: result.total_points(),
+            rules: result.rules.iter().map(|r| r.id()).collect(),
+            length: result.hash.len(),
+            date: score.date.min(now),
+            disqualified: self.anti_cheat.disqualify && !flags.is_empty(),
+            flags,
+            ..score.clone()
+        })
+    }
+}
+
+/// Compare secrets in time that doesn't depend on where they differ.
+fn same(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+/// The scores as a table on a page of their own.
+fn page(scores: &[ScoreInfo], n: usize) -> String {
+    let rows: String = scores
+        .iter()
+        .take(n)
+        .map(|s| {
+            let author = s.author.as_ref().map_or(String::new(), |a| a.name.clone());
+            let date = Utc
+                .timestamp_opt(s.date, 0)
+                .single()
+                .map_or(String::new(), |d| d.format("%Y-%m-%d").to_string());
+            format!(
+                "<tr><td>{}</td><td><code>{}</code></td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+                s.score,
+                escape(&s.commit),
+                escape(&author),
+                escape(&s.rule_names()),
+                date
+            )
+        })
+        .collect();
+    format!(
+        "<!DOCTYPE html>
+<html>
+<head><meta charset=\"utf-8\"><title>Commit Poker</title></head>
+<body>
+<h1>Commit Poker</h1>
+<table>
+<tr><th>Points</th><th>Commit</th><th>Author</th><th>Hand</th><th>Date</th></tr>
+{}</table>
+</body>
+</html>
+",
+        rows
+    )
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        anticheat::Flag, git::Person, highscores::HighScoresImpl, lotto::RuleId, store::Awards,
+        test_util::score_info,
+    };
+
+    fn score(commit: &str, points: u64, email: &str) -> ScoreInfo {
+        ScoreInfo {
+            root: Some("root".into()),
+            full_hash: Some(format!("{:0<40}", commit)),
+            rules: vec![RuleId::OfAKind(2)],
+            author: Some(Person {
+                name: "<Someone>".into(),
+                email: email.into(),
+            }),
+            ..score_info(commit, points)
+        }
+    }
+
+    fn server() -> ScoreServer {
+        let mut config = Config::default();
+        for (email, token) in [("a@example.com", "token-a"), ("b@example.com", "token-b")] {
+            config.storage.tokens.insert(email.into(), token.into());
+        }
+        ScoreServer::bind("127.0.0.1:0", &config).unwrap()
+    }
+
+    #[test]
+    fn test_filter() {
+        let filter = Filter::parse("n=5&repo=my%20repo&author=a%40example.com&since=100").unwrap();
+        assert_eq!(filter.n, Some(5));
+        assert_eq!(filter.repo.as_deref(), Some("my repo"));
+        assert_eq!(filter.author.as_deref(), Some("a@example.com"));
+        assert!(Filter::parse("n=-1").is_err());
+        assert!(Filter::parse("colour=red").is_err());
+
+        let mut old = score("aaaaaaa", 10, "a@example.com");
+        old.date = 99;
+        assert!(!filter.matches(&old, Some(7)));
+        old.date = 100;
+        assert!(!filter.matches(&old, Some(7)));
+        old.root = Some("my repo".into());
+        assert!(filter.matches(&old, Some(7)));
+        assert!(!filter.matches(&old, Some(8)));
+        assert!(filter.matches(&old, None));
+
+        let filter = Filter::parse("root=my%20repo&path=%2Felsewhere&except=aaaaaaa").unwrap();
+        assert!(!filter.matches(&old, None));
+        old.commit = "bbbbbbb".into();
+        assert!(filter.matches(&old, None));
+        old.root = Some("root".into());
+        assert!(!filter.matches(&old, None));
+    }
+
+    #[test]
+    fn test_changes() {
+        let before = vec![score("aaaaaaa", 10, "a"), score("bbbbbbb", 20, "a")];
+        let mut after = before.clone();
+        after[0].superseded = true;
+        after.remove(1);
+        after.push(score("ccccccc", 30, "a"));
+        let changes = Changes::between(&before, &after);
+        assert_eq!(changes.saved.len(), 2);
+        assert_eq!(changes.removed, vec![before[1].key()]);
+
+        let mut scores = before.clone();
+        changes.apply(&mut scores);
+        assert_eq!(scores, after);
+    }
+
+    #[test]
+    fn test_respond() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut highscores = HighScoresImpl::new(&dir.path().join("scores.json")).unwrap();
+        let server = server();
+        let mut respond = |method, url, token, body: &str| {
+            let reply = server.respond(&mut highscores, method, url, token, body);
+            (reply.status, reply.body)
+        };
+        let post = |score: &ScoreInfo| {
+            serde_json::to_string(&Changes {
+                saved: vec![score.clone()],
+                removed: vec![],
+            })
+            .unwrap()
+        };
+
+        let a = score("aaaa123", 10, "a@example.com");
+        let b = score("bbbbbbb", 20, "b@example.com");
+        assert_eq!(
+            respond("POST", "/api/scores", Some("token-a"), &post(&a)).0,
+            200
+        );
+        assert_eq!(
+            respond("POST", "/api/scores", Some("token-b"), &post(&b)).0,
+            200
+        );
+
+        let (status, body) = respond("GET", "/api/scores?author=a%40example.com", None, "");
+        assert_eq!(status, 200);
+        let scores: Vec<ScoreInfo> = serde_json::from_str(&body).unwrap();
+        assert_eq!(scores, vec![server.rescore(&a, None, &[]).ok().unwrap()]);
+        let (_, body) = respond("GET", "/api/authors?n=1", None, "");
+        assert!(body.contains("b@example.com") && !body.contains("a@example.com"));
+        let (_, body) = respond("GET", "/", None, "");
+        assert!(body.contains("<code>bbbbbbb</code>") && body.contains("&lt;Someone&gt;"));
+        let (_, body) = respond("GET", "/api/scores/all?author=b%40example.com", None, "");
+        assert!(body.contains("bbbbbbb") && !body.contains("aaaa123"));
+        let (_, body) = respond("GET", "/api/scores/best?root=root&except=bbbbbbb", None, "");
+        assert!(body.contains("aaaa123"));
+        let (_, body) = respond("GET", "/api/scores/best?root=other", None, "");
+        assert_eq!(body, "null");
+
+        // the server unlocks achievements for what it saves
+        let straight = score("1234567", 0, "a@example.com");
+        assert_eq!(
+            respond("POST", "/api/scores", Some("token-a"), &post(&straight)).0,
+            200
+        );
+        let (_, body) = respond("GET", "/api/awards", None, "");
+        let awards: Awards = serde_json::from_str(&body).unwrap();
+        let unlocked = &awards.achievements["a@example.com"];
+        assert!(unlocked.iter().all(|u| u.commit == "1234567"));
+        assert_eq!(unlocked.len(), 2);
+
+        assert_eq!(respond("GET", "/api/scores?n=x", None, "").0, 400);
+        assert_eq!(
+            respond("POST", "/api/scores", Some("token-a"), "not json").0,
+            400
+        );
+        assert_eq!(respond("DELETE", "/api/scores", None, "").0, 405);
+        assert_eq!(respond("GET", "/nowhere", None, "").0, 404);
+    }
+
+    #[test]
+    fn test_save_checks() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut highscores = HighScoresImpl::new(&dir.path().join("scores.json")).unwrap();
+        let server = server();
+        let mut respond = |token, changes: Changes| {
+            let body = serde_json::to_string(&changes).unwrap();
+            server
+                .respond(&mut highscores, "POST", "/api/scores", token, &body)
+                .status
+        };
+        let save = |score: &ScoreInfo| Changes {
+            saved: vec![score.clone()],
+            removed: vec![],
+        };
+
+        let mut a = score("aaaa123", 10, "a@example.com");
+        assert_eq!(respond(None, save(&a)), 401);
+        assert_eq!(respond(Some("token-c"), save(&a)), 401);
+        assert_eq!(respond(Some("token-b"), save(&a)), 403);
+        a.full_hash = None;
+        assert_eq!(respond(Some("token-a"), save(&a)), 400);
+        a.full_hash = Some(format!("{:1<40}", "aaaa12"));
+        assert_eq!(respond(Some("token-a"), save(&a)), 400);
+
+        // the server scores it, and only flagged scores are disqualified
+        let a = ScoreInfo {
+            score: 1_000_000,
+            rules: vec![RuleId::OfAKind(7)],
+            disqualified: true,
+            mined: true,
+            ..score("aaaa123", 10, "a@example.com")
+        };
+        assert_eq!(respond(Some("token-a"), save(&a)), 200);
+        assert_eq!(
+            respond(
+                Some("token-a"),
+                save(&ScoreInfo {
+                    mined: false,
+                    ..a.clone()
+                })
+            ),
+            400
+        );
+
+        // someone else can't take over or remove the score
+        let stolen = ScoreInfo {
+            author: Some(Person {
+                name: "B".into(),
+                email: "b@example.com".into(),
+            }),
+            ..a.clone()
+        };
+        assert_eq!(respond(Some("token-b"), save(&stolen)), 403);
+        let remove = Changes {
+            saved: vec![],
+            removed: vec![a.key()],
+        };
+        assert_eq!(respond(Some("token-b"), remove.clone()), 403);
+
+        let saved = highscores.load_all(None).unwrap();
+        assert_eq!(saved.len(), 1);
+        assert!(saved[0].score < a.score && saved[0].rules.contains(&RuleId::OfAKind(4)));
+        assert!(saved[0].mined && !saved[0].disqualified && saved[0].is_by("a@example.com"));
+        let body = serde_json::to_string(&remove).unwrap();
+        let reply = server.respond(
+            &mut highscores,
+            "POST",
+            "/api/scores",
+            Some("token-a"),
+            &body,
+        );
+        assert_eq!(reply.status, 200);
+        assert!(highscores.load_all(None).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_save_checks_what_it_can() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut highscores = HighScoresImpl::new(&dir.path().join("scores.json")).unwrap();
+        let server = server();
+        let mut save = |scores: Vec<ScoreInfo>| {
+            let changes = Changes {
+                saved: scores,
+                removed: vec![],
+            };
+            let body = serde_json::to_string(&changes).unwrap();
+            let reply = server.respond(
+                &mut highscores,
+                "POST",
+                "/api/scores",
+                Some("token-a"),
+                &body,
+            );
+            assert_eq!(reply.status, 200);
+        };
+        let rerolls: Vec<ScoreInfo> = ["aaaa001", "aaaa002", "aaaa003", "aaaa004"]
+            .iter()
+            .map(|commit| ScoreInfo {
+                tree: Some("tree".into()),
+                ..score(commit, 10, "a@example.com")
+            })
+            .collect();
+        let future = ScoreInfo {
+            date: Utc::now().timestamp() + 86400,
+            ..score("bbbb001", 10, "a@example.com")
+        };
+        save(rerolls[..3].to_vec());
+        save(vec![rerolls[3].clone(), future.clone()]);
+        // flags can't be dropped by sending the score again without them
+        save(vec![ScoreInfo {
+            flags: vec![],
+            ..future.clone()
+        }]);
+
+        let saved = highscores.load_all(None).unwrap();
+        let find = |commit: &str| saved.iter().find(|s| s.commit == commit).unwrap();
+        assert!(find("aaaa003").flags.is_empty());
+        assert_eq!(find("aaaa004").flags, vec![Flag::Rerolled { times: 3 }]);
+        let future = find("bbbb001");
+        assert!(matches!(future.flags[..], [Flag::FutureDate { .. }]));
+        assert!(future.date <= Utc::now().timestamp());
+    }
+}
@@ -0,0 +1,193 @@
+use std::collections::BTreeMap;
+
+use anyhow::anyhow;
+use rand::Rng;
+
+use crate::{
+    config::{RulesConfig, MAX_LENGTH},
+    errors::LottoError,
+    lotto::{LottoResult, RuleId},
+};
+
+const HEX: &[u8; 16] = b"0123456789abcdef";
+
+/// How many hits a rule has to be expected to get before never seeing it
+/// counts as a failure.
+const MIN_EXPECTED_HITS: f64 = 5.0;
+
+/// How often a rule was seen compared to how often it claims it should be
+/// seen.
+#[derive(Debug, Clone)]
+pub struct RuleFrequency {
+    pub id: RuleId,
+    pub name: String,
+    pub claimed: f64,
+    pub hits: u64,
+    pub samples: u64,
+}
+
+impl RuleFrequency {
+    pub fn observed(&self) -> f64 {
+        self.hits as f64 / self.samples as f64
+    }
+
+    /// The Wilson score interval around the observed frequency, `z` standard
+    /// deviations wide.
+    pub fn interval(&self, z: f64) -> (f64, f64) {
+        let n = self.samples as f64;
+        let p = self.observed();
+        let z2 = z * z;
+        let centre = (p + z2 / (2.0 * n)) / (1.0 + z2 / n);
+        let half = z / (1.0 + z2 / n) * (p * (1.0 - p) / n + z2 / (4.0 * n * n)).sqrt();
+        ((centre - half).max(0.0), (centre + half).min(1.0))
+    }
+
+    pub fn within_tolerance(&self, z: f64) -> bool {
+        if self.hits == 0 && self.claimed * self.samples as f64 >= MIN_EXPECTED_HITS {
+            return false;
+        }
+        let (low, high) = self.interval(z);
+        low <= self.claimed && self.claimed <= high
+    }
+}
+
+pub struct Simulation {
+    pub length: usize,
+    pub samples: u64,
+    pub rules: Vec<RuleFrequency>,
+}
+
+impl Simulation {
+    pub fn failures(&self, z: f64) -> Vec<&RuleFrequency> {
+        self.rules
+            .iter()
+            .filter(|r| !r.within_tolerance(z))
+            .collect()
+    }
+}
+
+pub fn random_hash(length: usize, rng: &mut impl Rng) -> String {
+    (0..length)
+        .map(|_| HEX[rng.gen_range(0..HEX.len())] as char)
+        .collect()
+}
+
+/// Score `samples` random hashes of the given length, counting the hits of
+/// every rule the config can score.
+///
+/// Cards are dealt from a random full hash that the short hash is a prefix of.
+pub fn simulate(
+    length: usize,
+    samples: u64,
+    config: &RulesConfig,
+    rng: &mut impl Rng,
+) -> Result<Simulation, LottoError> {
+    if !(1..=MAX_LENGTH).contains(&length) {
+        return Err(LottoError::ConfigError(anyhow!(
+            "the length must be between 1 and {}",
+            MAX_LENGTH
+        )));
+    }
+    if samples == 0 {
+        return Err(LottoError::ConfigError(anyhow!(
+            "at least one hash has to be simulated"
+        )));
+    }
+    let frequency = |id: RuleId| RuleFrequency {
+        name: id.name(length),
+        claimed: id.probability(length).unwrap_or_default(),
+        id,
+        hits: 0,
+        samples,
+    };
+    let mut rules: BTreeMap<RuleId, RuleFrequency> = RuleId::all(config, length)
+        .into_iter()
+        .map(|id| (id.clone(), frequency(id)))
+        .collect();
+    for _ in 0..samples {
+        let full_hash = random_hash(length.max(40), rng);
+        let hash = &full_hash[..length];
+        let mut result = LottoResult::with_rules(hash, config);
+        if config.poker {
+            result = result.with_cards(&full_hash, config.deal);
+        }
+        for rule in result.rules {
+            let id = rule.id();
+            rules
+                .entry(id.clone())
+                .or_insert_with(|| frequency(id))
+                .hits += 1;
+        }
+    }
+    let mut rules: Vec<RuleFrequency> = rules.into_values().collect();
+    rules.sort_by_key(|r| -(r.hits as i64));
+    Ok(Simulation {
+        length,
+        samples,
+        rules,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+    use crate::{cards::Deal, combinatorics::FlushKind};
+
+    #[test]
+    fn test_simulate() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let config = RulesConfig {
+            deal: Deal::Holdem,
+            ..Default::default()
+        };
+        let simulation = simulate(5, 20000, &config, &mut rng).unwrap();
+        // every rule is checked, even the ones never seen
+        assert_eq!(simulation.rules.len(), RuleId::all(&config, 5).len());
+        assert!(simulation.rules.iter().any(|r| r.hits == 0));
+        let failures: Vec<String> = simulation
+            .failures(4.0)
+            .iter()
+            .map(|r| format!("{} ({}): {} vs {}", r.name, r.id, r.observed(), r.claimed))
+            .collect();
+        assert!(failures.is_empty(), "{:?}", failures);
+
+        for (length, samples) in [(0, 10), (MAX_LENGTH + 1, 10), (5, 0)] {
+            assert!(matches!(
+                simulate(length, samples, &config, &mut rng),
+                Err(LottoError::ConfigError(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_interval() {
+        let rule = RuleFrequency {
+            id: RuleId::Flush(FlushKind::Letters),
+            name: "Flush: all letters".into(),
+            claimed: 0.5,
+            hits: 50,
+            samples: 100,
+        };
+        let (low, high) = rule.interval(1.96);
+        assert!((low - 0.4038).abs() < 0.0001, "{}", low);
+        assert!((high - 0.5962).abs() < 0.0001, "{}", high);
+        assert!(rule.within_tolerance(1.96));
+
+        // a rule that should have been seen a few times but never was
+        let unseen = RuleFrequency {
+            hits: 0,
+            claimed: 0.0005,
+            samples: 20_000,
+            ..rule.clone()
+        };
+        assert!(unseen.claimed < unseen.interval(4.0).1);
+        assert!(!unseen.within_tolerance(4.0));
+        let rare = RuleFrequency {
+            claimed: 0.00001,
+            ..unseen
+        };
+        assert!(rare.within_tolerance(4.0));
+    }
+}
@@ -0,0 +1,814 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::anyhow;
+use rusqlite::{params, Connection, OptionalExtension, Row, Transaction, TransactionBehavior};
+
+use crate::{
+    config::StorageConfig,
+    errors::LottoError,
+    git::{Person, RepoId},
+    highscores::{data_dir, HighScores, HighScoresImpl, ScoreInfo},
+    lotto::RuleId,
+    period::Window,
+    scoring::legacy_score,
+    store::{Awards, StoreStatus},
+};
+
+/// Each migration upgrades the database from the `user_version` at its index
+/// to the next one.
+const MIGRATIONS: &[&str] = &[
+    "
+CREATE TABLE scores (
+    id INTEGER PRIMARY KEY,
+    repo TEXT NOT NULL,
+    root TEXT,
+    remote TEXT,
+    commit_hash TEXT NOT NULL,
+    score INTEGER NOT NULL,
+    date INTEGER NOT NULL,
+    rules TEXT NOT NULL,
+    author_name TEXT,
+    author_email TEXT,
+    committer_name TEXT,
+    committer_email TEXT
+);
+CREATE INDEX scores_root ON scores (root);
+CREATE INDEX scores_repo ON scores (repo);
+CREATE INDEX scores_author ON scores (author_email COLLATE NOCASE);
+CREATE INDEX scores_date ON scores (date);
+CREATE INDEX scores_score ON scores (score DESC);
+",
+    "
+ALTER TABLE scores ADD COLUMN rewritten_as TEXT;
+ALTER TABLE scores ADD COLUMN superseded INTEGER NOT NULL DEFAULT 0;
+",
+    "
+ALTER TABLE scores ADD COLUMN tree TEXT;
+ALTER TABLE scores ADD COLUMN flags TEXT NOT NULL DEFAULT '[]';
+ALTER TABLE scores ADD COLUMN disqualified INTEGER NOT NULL DEFAULT 0;
+CREATE INDEX scores_tree ON scores (tree);
+",
+    "
+ALTER TABLE scores ADD COLUMN mined INTEGER NOT NULL DEFAULT 0;
+",
+    "
+ALTER TABLE scores ADD COLUMN hash_length INTEGER NOT NULL DEFAULT 0;
+UPDATE scores SET hash_length = length(commit_hash);
+",
+    "
+CREATE TABLE meta (
+    key TEXT PRIMARY KEY,
+    value TEXT NOT NULL
+);
+",
+    // rules are stored by id from here on, see `convert_rule_names`
+    "",
+    "
+ALTER TABLE scores ADD COLUMN full_hash TEXT;
+",
+    // scores are told apart by `ScoreInfo::key`, the last one saved winning
+    "
+DELETE FROM scores WHERE id NOT IN (
+    SELECT MAX(id) FROM scores
+    GROUP BY COALESCE(root, repo), COALESCE(full_hash, commit_hash)
+);
+CREATE UNIQUE INDEX scores_key ON scores (COALESCE(root, repo), COALESCE(full_hash, commit_hash));
+DROP INDEX scores_root;
+CREATE INDEX scores_best ON scores (root, hash_length, date);
+",
+];
+
+/// The version that added `hash_length`, before which scores with long short
+/// hashes are scored again.
+const LENGTH_VERSION: usize = 5;
+/// The version that added the `meta` table. Databases made before it had
+/// the JSON scores copied in when they were created.
+const META_VERSION: usize = 6;
+/// The version that stores rules by id instead of by name.
+const RULE_ID_VERSION: usize = 7;
+/// Set in `meta` once the JSON scores have been copied in.
+const JSON_IMPORTED: &str = "json_imported";
+/// The key in `meta` of the awards, as JSON.
+const AWARDS: &str = "awards";
+
+const COLUMNS: &str = "repo, root, remote, commit_hash, score, date, rules, \
+                       author_name, author_email, committer_name, committer_email, \
+                       rewritten_as, superseded, tree, flags, disqualified, mined, hash_length, full_hash";
+
+/// High scores kept in an SQLite database, so loading one repository's or
+/// author's scores doesn't read everyone's.
+pub struct SqliteHighScores {
+    path: PathBuf,
+    db: Connection,
+}
+
+impl HighScores for SqliteHighScores {
+    fn save(&mut self, result: ScoreInfo) -> Result<(), LottoError> {
+        self.save_all(vec![result])
+    }
+
+    fn save_all(&mut self, results: Vec<ScoreInfo>) -> Result<(), LottoError> {
+        self.save_inner(results)
+            .map_err(LottoError::ApplicationDirError)
+    }
+
+    fn load_all(&mut self, repo: Option<&RepoId>) -> Result<Vec<ScoreInfo>, LottoError> {
+        self.load_inner(repo)
+            .map_err(LottoError::ApplicationDirError)
+    }
+
+    fn modify(&mut self, f: &mut dyn FnMut(&mut Vec<ScoreInfo>) -> bool) -> Result<(), LottoError> {
+        self.modify_inner(f)
+            .map_err(LottoError::ApplicationDirError)
+    }
+
+    fn best_in(
+        &mut self,
+        repo: &RepoId,
+        length: usize,
+        window: &Window,
+        except: &str,
+    ) -> Result<Option<ScoreInfo>, LottoError> {
+        self.best_in_inner(repo, length, window, except)
+            .map_err(LottoError::ApplicationDirError)
+    }
+
+    fn load_by_author(&mut self, email: &str) -> Result<Vec<ScoreInfo>, LottoError> {
+        self.query(
+            "WHERE author_email = ?1 COLLATE NOCASE AND NOT superseded AND NOT disqualified AND NOT mined \
+             ORDER BY score DESC",
+            params![email],
+        )
+        .map_err(LottoError::ApplicationDirError)
+    }
+
+    fn migrate_authors(&mut self) -> Result<usize, LottoError> {
+        self.migrate_authors_inner()
+            .map_err(LottoError::ApplicationDirError)
+    }
+
+    fn load_awards(&mut self) -> Result<Awards, LottoError> {
+        read_awards(&self.db).map_err(LottoError::ApplicationDirError)
+    }
+
+    fn modify_awards(&mut self, f: &mut dyn FnMut(&mut Awards) -> bool) -> Result<(), LottoError> {
+        self.modify_awards_inner(f)
+            .map_err(LottoError::ApplicationDirError)
+    }
+
+    fn check(&mut self) -> Result<StoreStatus, LottoError> {
+        self.check_inner().map_err(LottoError::ApplicationDirError)
+    }
+
+    /// Databases are migrated when they are opened, so there is nothing left
+    /// to do.
+    fn migrate(&mut self) -> Result<StoreStatus, LottoError> {
+        self.check()
+    }
+}
+
+impl SqliteHighScores {
+    pub fn new(path: &Path) -> Result<Self, LottoError> {
+        Self::open(path).map_err(LottoError::ApplicationDirError)
+    }
+
+    /// Open the configured database. The first time it is opened, any scores
+    /// in the configured JSON file are copied into it.
+    pub fn from_config(config: &StorageConfig) -> Result<Self, LottoError> {
+        let path = match &config.sqlite_path {
+            Some(path) => path.clone(),
+            None => Self::standard_path()?,
+        };
+        let json = match &config.path {
+            Some(path) => path.clone(),
+            None => HighScoresImpl::standard_path()?,
+        };
+        let mut highscores = SqliteHighScores::new(&path)?;
+        highscores.import_json(&json)?;
+        Ok(highscores)
+    }
+
+    pub fn standard_path() -> Result<PathBuf, LottoError> {
+        Ok(data_dir()?.join("highscores.sqlite3"))
+    }
+
+    /// Copy the scores in a JSON file in, unless that has already been done.
+    /// Returns how many were copied.
+    pub fn import_json(&mut self, json: &Path) -> Result<usize, LottoError> {
+        self.import_json_inner(json)
+            .map_err(LottoError::ApplicationDirError)
+    }
+
+    fn import_json_inner(&mut self, json: &Path) -> anyhow::Result<usize> {
+        // taking the write lock first, so two processes can't both import
+        let tx = self
+            .db
+            .transaction_with_behavior(TransactionBehavior::Immediate)?;
+        let imported: Option<String> = tx
+            .query_row(
+                "SELECT value FROM meta WHERE key = ?1",
+                [JSON_IMPORTED],
+                |row| row.get(0),
+            )
+            .optional()?;
+        if imported.is_some() {
+            return Ok(0);
+        }
+        let (scores, awards) = if json.exists() {
+            let mut highscores = HighScoresImpl::new(json)?;
+            (highscores.load_all(None)?, highscores.load_awards()?)
+        } else {
+            (vec![], Awards::default())
+        };
+        let count = scores.len();
+        insert(&tx, scores)?;
+        if awards != Awards::default() {
+            write_awards(&tx, &awards)?;
+        }
+        tx.execute(
+            "INSERT INTO meta (key, value) VALUES (?1, ?2)",
+            params![JSON_IMPORTED, json.to_string_lossy()],
+        )?;
+        tx.commit()?;
+        Ok(count)
+    }
+
+    /// Copy every score and any awards missing here from another store,
+    /// returning how many scores were copied.
+    pub fn import(&mut self, other: &mut impl HighScores) -> Result<usize, LottoError> {
+        let scores = other.load_all(None)?;
+        let imported = scores.len();
+        self.save_all(scores)?;
+        let mut awards = Some(other.load_awards()?);
+        self.modify_awards(&mut |ours| ours.add_missing(awards.take().unwrap_or_default()))?;
+        Ok(imported)
+    }
+
+    fn open(path: &Path) -> anyhow::Result<Self> {
+        let parent = path
+            .parent()
+            .ok_or(anyhow!("could not get parent of path"))?;
+        std::fs::create_dir_all(parent)?;
+        let db = Connection::open(path)?;
+        db.busy_timeout(std::time::Duration::from_secs(5))?;
+        let mut highscores = SqliteHighScores {
+            path: path.to_path_buf(),
+            db,
+        };
+        highscores.upgrade()?;
+        Ok(highscores)
+    }
+
+    fn version(&self) -> anyhow::Result<usize> {
+        let version: i64 = self
+            .db
+            .query_row("PRAGMA user_version", [], |row| row.get(0))?;
+        Ok(version as usize)
+    }
+
+    /// Run any migrations the database hasn't had, keeping a copy of it as it
+    /// was first.
+    fn upgrade(&mut self) -> anyhow::Result<()> {
+        let version = self.version()?;
+        if version > MIGRATIONS.len() {
+            return Err(anyhow!(
+                "high score database is version {}, but this commit-poker only understands up to {}",
+                version,
+                MIGRATIONS.len()
+            ));
+        }
+        if version == MIGRATIONS.len() {
+            return Ok(());
+        }
+        if version > 0 {
+            let backup = self.path.with_extension(format!("sqlite3.v{}", version));
+            self.db
+                .execute("VACUUM INTO ?1", [backup.to_string_lossy()])?;
+        }
+        let tx = self.db.transaction()?;
+        for (i, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+            tx.execute_batch(migration)?;
+            tx.pragma_update(None, "user_version", i + 1)?;
+        }
+        if (1..LENGTH_VERSION).contains(&version) {
+            rescore_legacy(&tx)?;
+        }
+        if (1..RULE_ID_VERSION).contains(&version) {
+            convert_rule_names(&tx)?;
+        }
+        if (1..META_VERSION).contains(&version) {
+            tx.execute(
+                "INSERT INTO meta (key, value) VALUES (?1, '')",
+                [JSON_IMPORTED],
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn check_inner(&mut self) -> anyhow::Result<StoreStatus> {
+        let scores: i64 = self
+            .db
+            .query_row("SELECT COUNT(*) FROM scores", [], |row| row.get(0))?;
+        Ok(StoreStatus {
+            path: self.path.clone(),
+            version: self.version()? as u32,
+            current: MIGRATIONS.len() as u32,
+            scores: scores as usize,
+            damaged: false,
+        })
+    }
+
+    fn save_inner(&mut self, results: Vec<ScoreInfo>) -> anyhow::Result<()> {
+        let tx = self.db.transaction()?;
+        insert(&tx, results)?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Rewrites the whole table, which is fine for the rare changes that
+    /// need every score.
+    fn modify_inner(
+        &mut self,
+        f: &mut dyn FnMut(&mut Vec<ScoreInfo>) -> bool,
+    ) -> anyhow::Result<()> {
+        let tx = self.db.transaction()?;
+        let mut scores = tx
+            .prepare(&format!("SELECT {COLUMNS} FROM scores"))?
+            .query_map([], from_row)?
+            .collect::<Result<Vec<_>, _>>()?;
+        if f(&mut scores) {
+            tx.execute("DELETE FROM scores", [])?;
+            insert(&tx, scores)?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn modify_awards_inner(
+        &mut self,
+        f: &mut dyn FnMut(&mut Awards) -> bool,
+    ) -> anyhow::Result<()> {
+        let tx = self
+            .db
+            .transaction_with_behavior(TransactionBehavior::Immediate)?;
+        let mut awards = read_awards(&tx)?;
+        if f(&mut awards) {
+            write_awards(&tx, &awards)?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn load_inner(&mut self, repo: Option<&RepoId>) -> anyhow::Result<Vec<ScoreInfo>> {
+        let Some(repo) = repo else {
+            return self.query("ORDER BY score DESC", []);
+        };
+        // The index narrows it down to the repository's root commit, and
+        // scores recorded before there were roots are matched by path.
+        let mut scores = self.query(
+            "WHERE root = ?1 OR root IS NULL ORDER BY score DESC",
+            params![repo.root],
+        )?;
+        scores.retain(|s| s.is_from(repo));
+        Ok(scores)
+    }
+
+    fn best_in_inner(
+        &mut self,
+        repo: &RepoId,
+        length: usize,
+        window: &Window,
+        except: &str,
+    ) -> anyhow::Result<Option<ScoreInfo>> {
+        let scores = self.query(
+            "WHERE (root = ?1 OR root IS NULL) AND hash_length = ?2 \
+             AND date >= ?3 AND date < ?4 AND commit_hash != ?5 \
+             AND NOT superseded AND NOT disqualified AND NOT mined \
+             ORDER BY score DESC",
+            params![
+                repo.root,
+                length as i64,
+                window.since.unwrap_or(i64::MIN),
+                window.until.unwrap_or(i64::MAX),
+                except
+            ],
+        )?;
+        Ok(scores.into_iter().find(|s| s.is_from(repo)))
+    }
+
+    fn query(&self, clause: &str, params: impl rusqlite::Params) -> anyhow::Result<Vec<ScoreInfo>> {
+        let mut select = self
+            .db
+            .prepare(&format!("SELECT {COLUMNS} FROM scores {clause}"))?;
+        let scores = select
+            .query_map(params, from_row)?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(scores)
+    }
+
+    fn migrate_authors_inner(&mut self) -> anyhow::Result<usize> {
+        let tx = self.db.transaction()?;
+        let mut filled = 0;
+        {
+            let mut select = tx.prepare(&format!(
+                "SELECT id, {COLUMNS} FROM scores WHERE author_email IS NULL"
+            ))?;
+            let mut update = tx.prepare(
+                "UPDATE scores SET author_name = ?2, author_email = ?3, \
+                 committer_name = ?4, committer_email = ?5 WHERE id = ?1",
+            )?;
+            let missing = select
+                .query_map([], |row| Ok((row.get::<_, i64>(0)?, from_row_at(row, 1)?)))?
+                .collect::<Result<Vec<_>, _>>()?;
+            for (id, mut score) in missing {
+                if !score.fill_people() {
+                    continue;
+                }
+                let (author, committer) = (score.author.unwrap(), score.committer.unwrap());
+                update.execute(params![
+                    id,
+                    author.name,
+                    author.email,
+                    committer.name,
+                    committer.email
+                ])?;
+                filled += 1;
+            }
+        }
+        tx.commit()?;
+        Ok(filled)
+    }
+
+    #[cfg(test)]
+    fn plan(&self, clause: &str) -> String {
+        self.db
+            .query_row(
+                &format!("EXPLAIN QUERY PLAN SELECT {COLUMNS} FROM scores {clause}"),
+                [],
+                |row| row.get(3),
+            )
+            .unwrap()
+    }
+}
+
+/// Score scores recorded before lengths were kept again, see [`legacy_score`].
+fn rescore_legacy(tx: &Transaction) -> anyhow::Result<()> {
+    let mut select = tx.prepare("SELECT id, commit_hash FROM scores")?;
+    let rows = select
+        .query_map([], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut update =
+        tx.prepare("UPDATE scores SET score = ?1, rules = ?2, hash_length = ?3 WHERE id = ?4")?;
+    for (id, commit) in rows {
+        if let Some(result) = legacy_score(&commit) {
+            let rules: Vec<RuleId> = result.rules.iter().map(|r| r.id()).collect();
+            update.execute(params![
+                result.total_points() as i64,
+                serde_json::to_string(&rules)?,
+                result.hash.len() as i64,
+                id
+            ])?;
+        }
+    }
+    Ok(())
+}
+
+/// Store the rules of scores recorded by name by id, see [`RuleId::from_name`].
+fn convert_rule_names(tx: &Transaction) -> anyhow::Result<()> {
+    let mut select = tx.prepare("SELECT id, commit_hash, rules, hash_length FROM scores")?;
+    let rows = select
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, i64>(3)?,
+            ))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut update = tx.prepare("UPDATE scores SET rules = ?1 WHERE id = ?2")?;
+    for (id, commit, rules, length) in rows {
+        let names: Vec<String> = serde_json::from_str(&rules).unwrap_or_default();
+        let rules: Vec<RuleId> = names
+            .iter()
+            .map(|name| RuleId::from_name(name, &commit, length as usize))
+            .collect();
+        update.execute(params![serde_json::to_string(&rules)?, id])?;
+    }
+    Ok(())
+}
+
+fn read_awards(db: &Connection) -> anyhow::Result<Awards> {
+    let awards: Option<String> = db
+        .query_row("SELECT value FROM meta WHERE key = ?1", [AWARDS], |row| {
+            row.get(0)
+        })
+        .optional()?;
+    match awards {
+        Some(awards) => Ok(serde_json::from_str(&awards)?),
+        None => Ok(Awards::default()),
+    }
+}
+
+fn write_awards(tx: &Transaction, awards: &Awards) -> anyhow::Result<()> {
+    tx.execute(
+        "INSERT OR REPLACE INTO meta (key, value) VALUES (?1, ?2)",
+        params![AWARDS, serde_json::to_string(awards)?],
+    )?;
+    Ok(())
+}
+
+/// Add the scores, replacing any saved with the same [`ScoreInfo::key`].
+fn insert(tx: &Transaction, scores: Vec<ScoreInfo>) -> anyhow::Result<()> {
+    let update: Vec<String> = COLUMNS
+        .split(", ")
+        .map(|column| format!("{column} = excluded.{column}"))
+        .collect();
+    let mut insert = tx.prepare(&format!(
+        "INSERT INTO scores ({COLUMNS}) \
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19) \
+         ON CONFLICT (COALESCE(root, repo), COALESCE(full_hash, commit_hash)) DO UPDATE SET {}",
+        update.join(", ")
+    ))?;
+    for score in scores {
+        insert.execute(params![
+            score.repo,
+            score.root,
+            score.remote,
+            score.commit,
+            score.score as i64,
+            score.date,
+            serde_json::to_string(&score.rules)?,
+            score.author.as_ref().map(|p| &p.name),
+            score.author.as_ref().map(|p| &p.email),
+            score.committer.as_ref().map(|p| &p.name),
+            score.committer.as_ref().map(|p| &p.email),
+            score.rewritten_as,
+            score.superseded,
+            score.tree,
+            serde_json::to_string(&score.flags)?,
+            score.disqualified,
+            score.mined,
+            score.length as i64,
+            score.full_hash,
+        ])?;
+    }
+    Ok(())
+}
+
+fn from_row(row: &Row) -> rusqlite::Result<ScoreInfo> {
+    from_row_at(row, 0)
+}
+
+fn from_row_at(row: &Row, start: usize) -> rusqlite::Result<ScoreInfo> {
+    let col = |i: usize| start + i;
+    let person = |name: Option<String>, email: Option<String>| {
+        email.map(|email| Person {
+            name: name.unwrap_or_default(),
+            email,
+        })
+    };
+    let rules: String = row.get(col(6))?;
+    let flags: String = row.get(col(14))?;
+    Ok(ScoreInfo {
+        repo: row.get(col(0))?,
+        root: row.get(col(1))?,
+        remote: row.get(col(2))?,
+        commit: row.get(col(3))?,
+        full_hash: row.get(col(18))?,
+        score: row.get::<_, i64>(col(4))? as u64,
+        date: row.get(col(5))?,
+        rules: serde_json::from_str(&rules).unwrap_or_default(),
+        author: person(row.get(col(7))?, row.get(col(8))?),
+        committer: person(row.get(col(9))?, row.get(col(10))?),
+        rewritten_as: row.get(col(11))?,
+        superseded: row.get(col(12))?,
+        tree: row.get(col(13))?,
+        flags: serde_json::from_str(&flags).unwrap_or_default(),
+        disqualified: row.get(col(15))?,
+        mined: row.get(col(16))?,
+        length: row.get::<_, i64>(col(17))? as usize,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        combinatorics::FlushKind,
+        test_util::{person, score_info},
+    };
+
+    fn score(root: Option<&str>, email: &str, score: u64) -> ScoreInfo {
+        ScoreInfo {
+            root: root.map(Into::into),
+            rules: vec![RuleId::Flush(FlushKind::Letters)],
+            author: Some(person(email)),
+            ..score_info(&format!("{:07}", score), score)
+        }
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut highscores = SqliteHighScores::new(&dir.path().join("scores.db")).unwrap();
+        highscores
+            .save_all(vec![
+                score(Some("aaa"), "a@example.com", 10),
+                score(Some("bbb"), "b@example.com", 30),
+                score(None, "a@example.com", 20),
+            ])
+            .unwrap();
+
+        let repo = RepoId {
+            root: Some("aaa".into()),
+            remote: None,
+            path: "/repo".into(),
+        };
+        let scores = highscores.load(Some(&repo)).unwrap();
+        let commits: Vec<u64> = scores.iter().map(|s| s.score).collect();
+        assert_eq!(commits, vec![20, 10]);
+        assert_eq!(scores[1].rules, vec![RuleId::Flush(FlushKind::Letters)]);
+        assert_eq!(scores[1].committer, None);
+
+        let scores = highscores.load_by_author("A@example.com").unwrap();
+        assert_eq!(scores.len(), 2);
+        assert_eq!(highscores.load(None).unwrap()[0].score, 30);
+    }
+
+    #[test]
+    fn test_queries_use_indexes() {
+        let dir = tempfile::tempdir().unwrap();
+        let highscores = SqliteHighScores::new(&dir.path().join("scores.db")).unwrap();
+        assert!(highscores
+            .plan("WHERE author_email = 'a' COLLATE NOCASE")
+            .contains("scores_author"));
+        assert!(highscores.plan("WHERE root = 'a'").contains("scores_best"));
+    }
+
+    #[test]
+    fn test_saving_again_replaces() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("scores.db");
+        let db = Connection::open(&path).unwrap();
+        for (i, migration) in MIGRATIONS[..8].iter().enumerate() {
+            db.execute_batch(migration).unwrap();
+            db.pragma_update(None, "user_version", i + 1).unwrap();
+        }
+        db.execute(
+            "INSERT INTO scores (repo, root, commit_hash, score, date, rules) \
+             VALUES ('/r', 'aaa', 'aaaaaaa', 1, 0, '[]'), ('/r', 'aaa', 'aaaaaaa', 2, 0, '[]')",
+            [],
+        )
+        .unwrap();
+        drop(db);
+        let mut highscores = SqliteHighScores::new(&path).unwrap();
+        assert_eq!(highscores.load_all(None).unwrap()[0].score, 2);
+
+        let mut again = score(Some("aaa"), "a@example.com", 10);
+        highscores.save(again.clone()).unwrap();
+        again.superseded = true;
+        highscores.save(again.clone()).unwrap();
+        let scores = highscores.load_all(None).unwrap();
+        assert_eq!(scores.len(), 2);
+        assert!(scores.contains(&again));
+    }
+
+    #[test]
+    fn test_best_in() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut highscores = SqliteHighScores::new(&dir.path().join("scores.db")).unwrap();
+        let at = |points: u64, date: i64| ScoreInfo {
+            date,
+            ..score(Some("aaa"), "a@example.com", points)
+        };
+        highscores
+            .save_all(vec![
+                at(10, 100),
+                at(20, 200),
+                at(30, 300),
+                ScoreInfo {
+                    mined: true,
+                    ..at(40, 200)
+                },
+                ScoreInfo {
+                    length: 8,
+                    ..at(50, 200)
+                },
+                score(Some("bbb"), "a@example.com", 60),
+            ])
+            .unwrap();
+        let repo = RepoId {
+            root: Some("aaa".into()),
+            remote: None,
+            path: "/repo".into(),
+        };
+        let mut best = |since, until, except: &str| {
+            highscores
+                .best_in(&repo, 7, &Window { since, until }, except)
+                .unwrap()
+                .map(|s| s.score)
+        };
+        assert_eq!(best(None, None, ""), Some(30));
+        assert_eq!(best(None, None, "0000030"), Some(20));
+        assert_eq!(best(Some(100), Some(300), ""), Some(20));
+        assert_eq!(best(Some(400), None, ""), None);
+        assert!(highscores
+            .plan("WHERE root = 'a' AND hash_length = 7 AND date >= 0")
+            .contains("scores_best"));
+    }
+
+    #[test]
+    fn test_import() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut json = HighScoresImpl::new(&dir.path().join("scores.json")).unwrap();
+        json.save_all(vec![
+            score(Some("aaa"), "a@example.com", 10),
+            score(Some("aaa"), "a@example.com", 20),
+        ])
+        .unwrap();
+        let awards = Awards {
+            seasons: vec![crate::seasons::ArchivedSeason {
+                name: "Autumn".into(),
+                start: Default::default(),
+                end: Default::default(),
+                length: 7,
+                winners: vec![],
+            }],
+            ..Default::default()
+        };
+        json.modify_awards(&mut |ours| ours.add_missing(awards.clone()))
+            .unwrap();
+        let mut highscores = SqliteHighScores::new(&dir.path().join("scores.db")).unwrap();
+        assert_eq!(highscores.load_awards().unwrap(), Awards::default());
+        assert_eq!(highscores.import(&mut json).unwrap(), 2);
+        assert_eq!(highscores.load(None).unwrap().len(), 2);
+        assert_eq!(highscores.load_awards().unwrap(), awards);
+    }
+
+    #[test]
+    fn test_upgrade_rescores_legacy_scores() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("scores.db");
+        let db = Connection::open(&path).unwrap();
+        db.execute_batch(MIGRATIONS[0]).unwrap();
+        db.pragma_update(None, "user_version", 1).unwrap();
+        db.execute(
+            "INSERT INTO scores (repo, commit_hash, score, date, rules) \
+             VALUES ('/r', 'aaaabbbb', 1, 0, '[]'), ('/r', 'aaaabbb', 2, 0, '[\"4 of a kind\"]')",
+            [],
+        )
+        .unwrap();
+        drop(db);
+        let mut highscores = SqliteHighScores::new(&path).unwrap();
+        let scores = highscores.load(None).unwrap();
+        let rescored = legacy_score("aaaabbb").unwrap();
+        for score in &scores {
+            assert_eq!(score.score, rescored.total_points());
+            assert_eq!(score.length, 7);
+            assert_eq!(
+                score.rules,
+                vec![RuleId::FullHouse, RuleId::Flush(FlushKind::Letters)]
+            );
+        }
+    }
+
+    #[test]
+    fn test_from_config_imports_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let json = dir.path().join("scores.json");
+        HighScoresImpl::new(&json)
+            .unwrap()
+            .save_all(vec![score(Some("aaa"), "a@example.com", 10)])
+            .unwrap();
+        let config = StorageConfig {
+            path: Some(json.clone()),
+            sqlite_path: Some(dir.path().join("scores.db")),
+            ..Default::default()
+        };
+        let mut highscores = SqliteHighScores::from_config(&config).unwrap();
+        assert_eq!(highscores.load(None).unwrap().len(), 1);
+        drop(highscores);
+
+        // an import that was interrupted is tried again
+        let db = Connection::open(dir.path().join("scores.db")).unwrap();
+        assert_eq!(
+            db.query_row("SELECT value FROM meta", [], |row| row.get::<_, String>(0))
+                .unwrap(),
+            json.to_string_lossy()
+        );
+        db.execute("DELETE FROM meta", []).unwrap();
+        db.execute("DELETE FROM scores", []).unwrap();
+        drop(db);
+        let mut highscores = SqliteHighScores::from_config(&config).unwrap();
+        assert_eq!(highscores.load(None).unwrap().len(), 1);
+        drop(highscores);
+
+        // but never twice
+        let mut highscores = SqliteHighScores::from_config(&config).unwrap();
+        assert_eq!(highscores.load(None).unwrap().len(), 1);
+    }
+}
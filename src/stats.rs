@@ -0,0 +1,207 @@
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+use crate::{config::RulesConfig, highscores::ScoreInfo, lotto::RuleId};
+
+/// How often a rule was hit, against how often it should be.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RuleStats {
+    pub id: RuleId,
+    pub name: String,
+    pub hits: usize,
+    /// The share of commits that hit the rule.
+    pub observed: f64,
+    /// The share that should, from the rule's probability.
+    pub theoretical: Option<f64>,
+}
+
+/// The longest run of commits in a row that scored nothing.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DrySpell {
+    pub commits: usize,
+    /// The dates of the first and last commit of the run.
+    pub from: i64,
+    pub until: i64,
+}
+
+/// What `commit-poker-util stats` reports about a set of scores.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Stats {
+    pub length: usize,
+    pub commits: usize,
+    pub total: u64,
+    pub mean: f64,
+    pub median: f64,
+    /// Most hit first.
+    pub rules: Vec<RuleStats>,
+    /// The best score, since points go up as a hash gets less likely.
+    pub rarest: Option<ScoreInfo>,
+    pub dry_spell: Option<DrySpell>,
+}
+
+/// Summarise the scores of hashes `length` characters long, ignoring any
+/// others.
+pub fn stats(scores: &[ScoreInfo], length: usize, config: &RulesConfig) -> Stats {
+    let mut scores: Vec<&ScoreInfo> = scores.iter().filter(|s| s.length == length).collect();
+    scores.sort_by_key(|s| s.date);
+    let commits = scores.len();
+    let total: u64 = scores.iter().map(|s| s.score).sum();
+
+    let mut points: Vec<u64> = scores.iter().map(|s| s.score).collect();
+    points.sort();
+    let median = match commits {
+        0 => 0.0,
+        n if n % 2 == 0 => (points[n / 2 - 1] + points[n / 2]) as f64 / 2.0,
+        n => points[n / 2] as f64,
+    };
+
+    // every rule that could have been hit, and any that were under another config
+    let mut hits: BTreeMap<&RuleId, usize> = BTreeMap::new();
+    let configured = RuleId::all(config, length);
+    for rule in configured
+        .iter()
+        .chain(scores.iter().flat_map(|s| &s.rules))
+    {
+        hits.entry(rule).or_default();
+    }
+    for rule in scores.iter().flat_map(|s| &s.rules) {
+        *hits.entry(rule).or_default() += 1;
+    }
+    let mut rules: Vec<RuleStats> = hits
+        .into_iter()
+        .map(|(id, hits)| RuleStats {
+            id: id.clone(),
+            name: id.name(length),
+            hits,
+            observed: if commits == 0 {
+                0.0
+            } else {
+                hits as f64 / commits as f64
+            },
+            theoretical: id.probability(length),
+        })
+        .collect();
+    rules.sort_by_key(|r| std::cmp::Reverse(r.hits));
+
+    let mut dry_spell: Option<DrySpell> = None;
+    let mut run_start = 0;
+    for (i, score) in scores.iter().enumerate() {
+        if score.score > 0 {
+            run_start = i + 1;
+            continue;
+        }
+        let run = i + 1 - run_start;
+        if dry_spell.as_ref().is_none_or(|d| run > d.commits) {
+            dry_spell = Some(DrySpell {
+                commits: run,
+                from: scores[run_start].date,
+                until: score.date,
+            });
+        }
+    }
+
+    Stats {
+        length,
+        commits,
+        total,
+        mean: if commits == 0 {
+            0.0
+        } else {
+            total as f64 / commits as f64
+        },
+        median,
+        rules,
+        rarest: scores.iter().max_by_key(|s| s.score).map(|s| (*s).clone()),
+        dry_spell,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{combinatorics::FlushKind, test_util::score_info};
+
+    fn score(points: u64, date: i64, rules: &[RuleId]) -> ScoreInfo {
+        ScoreInfo {
+            date,
+            rules: rules.to_vec(),
+            ..score_info(&format!("{:07x}", date), points)
+        }
+    }
+
+    #[test]
+    fn test_stats() {
+        let scores = vec![
+            score(0, 3, &[]),
+            score(200, 1, &[RuleId::OfAKind(2)]),
+            score(0, 4, &[]),
+            score(
+                900,
+                6,
+                &[RuleId::Pairs(2), RuleId::Flush(FlushKind::Letters)],
+            ),
+            score(300, 2, &[RuleId::OfAKind(2)]),
+            score(0, 5, &[]),
+            // another length isn't comparable
+            ScoreInfo {
+                length: 12,
+                ..score(5_000, 7, &[RuleId::Flush(FlushKind::Numbers)])
+            },
+        ];
+        let stats = stats(&scores, 7, &RulesConfig::default());
+        assert_eq!((stats.commits, stats.total), (6, 1_400));
+        assert!((stats.mean - 1_400.0 / 6.0).abs() < 1e-9);
+        assert_eq!(stats.median, 100.0);
+        assert_eq!(stats.rarest.unwrap().score, 900);
+        assert_eq!(
+            stats.dry_spell,
+            Some(DrySpell {
+                commits: 3,
+                from: 3,
+                until: 5
+            })
+        );
+
+        assert_eq!(stats.rules[0].name, "2 of a kind");
+        assert_eq!(stats.rules[0].hits, 2);
+        assert!((stats.rules[0].observed - 2.0 / 6.0).abs() < 1e-9);
+        let rule = |id: RuleId| stats.rules.iter().find(|r| r.id == id).unwrap();
+        let flush = rule(RuleId::Flush(FlushKind::Letters));
+        assert_eq!((flush.name.as_str(), flush.hits), ("Flush: all letters", 1));
+        assert!((flush.theoretical.unwrap() - 6f64.powi(7) / 16f64.powi(7)).abs() < 1e-12);
+
+        // rules nobody hit are listed too
+        let numbers = rule(RuleId::Flush(FlushKind::Numbers));
+        assert_eq!((numbers.hits, numbers.observed), (0, 0.0));
+        assert!((numbers.theoretical.unwrap() - 10f64.powi(7) / 16f64.powi(7)).abs() < 1e-12);
+        assert_eq!(rule(RuleId::Straight(7)).hits, 0);
+
+        // and so are rules the config no longer scores
+        let config = RulesConfig {
+            flush: false,
+            ..RulesConfig::default()
+        };
+        let stats = super::stats(&scores, 7, &config);
+        let flushes: Vec<_> = stats
+            .rules
+            .iter()
+            .filter(|r| r.name.starts_with("Flush"))
+            .collect();
+        assert_eq!(flushes.len(), 1);
+        assert_eq!(flushes[0].hits, 1);
+    }
+
+    #[test]
+    fn test_no_scores() {
+        let stats = stats(&[], 7, &RulesConfig::default());
+        assert_eq!(stats.commits, 0);
+        assert_eq!((stats.mean, stats.median), (0.0, 0.0));
+        assert!(stats.rarest.is_none() && stats.dry_spell.is_none());
+        assert_eq!(
+            stats.rules.len(),
+            RuleId::all(&RulesConfig::default(), 7).len()
+        );
+        assert!(stats.rules.iter().all(|r| r.hits == 0 && r.observed == 0.0));
+    }
+}
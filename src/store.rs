@@ -0,0 +1,329 @@
+use std::{collections::BTreeMap, fmt, path::PathBuf};
+
+use anyhow::anyhow;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::{
+    achievements::Unlocked, highscores::ScoreInfo, lotto::RuleId, scoring::legacy_score,
+    seasons::ArchivedSeason,
+};
+
+/// The version of the high score file this build writes.
+pub const CURRENT_VERSION: u32 = 9;
+
+/// Each migration upgrades a file from the version at its index + 1 to the
+/// next one.
+const MIGRATIONS: &[fn(Value) -> anyhow::Result<Value>] = &[
+    wrap_in_envelope,
+    add_rewrites,
+    add_flags,
+    add_mined,
+    add_lengths,
+    add_rule_ids,
+    add_full_hashes,
+    add_awards,
+];
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Envelope<S, A> {
+    version: u32,
+    scores: S,
+    #[serde(default)]
+    awards: A,
+}
+
+/// What a store keeps besides the scores, so it's shared the same way.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Awards {
+    /// The winners of each season that has ended, oldest first.
+    pub seasons: Vec<ArchivedSeason>,
+    /// Each author's unlocked achievements, by lowercased email.
+    pub achievements: BTreeMap<String, Vec<Unlocked>>,
+}
+
+impl Awards {
+    /// Add what `other` has that these don't, returning whether anything was
+    /// added.
+    pub fn add_missing(&mut self, other: Awards) -> bool {
+        let mut added = false;
+        for season in other.seasons {
+            if !self.seasons.iter().any(|s| s.name == season.name) {
+                self.seasons.push(season);
+                added = true;
+            }
+        }
+        self.seasons.sort_by_key(|s| s.end);
+        for (email, unlocked) in other.achievements {
+            let ours = self.achievements.entry(email).or_default();
+            for new in unlocked {
+                match ours.iter_mut().find(|u| u.achievement == new.achievement) {
+                    // whoever unlocked it first keeps it
+                    Some(old) if new.date < old.date => *old = new,
+                    Some(_) => continue,
+                    None => ours.push(new),
+                }
+                added = true;
+            }
+        }
+        added
+    }
+}
+
+/// What `commit-poker-util store check` reports about a store.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StoreStatus {
+    pub path: PathBuf,
+    /// The schema version on disk, 0 if nothing has been written yet.
+    pub version: u32,
+    pub current: u32,
+    pub scores: usize,
+    /// Whether the store had to be salvaged when read.
+    pub damaged: bool,
+}
+
+impl StoreStatus {
+    pub fn needs_migration(&self) -> bool {
+        self.version != 0 && self.version < self.current
+    }
+}
+
+impl fmt::Display for StoreStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}: version {} of {}, {} scores",
+            self.path.display(),
+            self.version,
+            self.current,
+            self.scores
+        )?;
+        if self.damaged {
+            write!(f, ", damaged")?;
+        }
+        if self.needs_migration() {
+            write!(f, ", needs migrating")?;
+        }
+        Ok(())
+    }
+}
+
+/// The version of a parsed file. Version 1 files are a bare array of scores.
+pub fn version(value: &Value) -> anyhow::Result<u32> {
+    match value {
+        Value::Array(_) => Ok(1),
+        Value::Object(object) => object
+            .get("version")
+            .and_then(Value::as_u64)
+            .filter(|v| *v > 0)
+            .map(|v| v as u32)
+            .ok_or(anyhow!("high score file has no version")),
+        _ => Err(anyhow!("high score file is not an array or an object")),
+    }
+}
+
+/// Fail if a file was written by a newer build, which shouldn't be touched.
+pub fn ensure_supported(value: &Value) -> anyhow::Result<()> {
+    match version(value) {
+        Ok(version) if version > CURRENT_VERSION => Err(anyhow!(
+            "high score file is version {}, but this commit-poker only understands up to {}",
+            version,
+            CURRENT_VERSION
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Upgrade a file of any version this build knows about, returning its
+/// scores and the version it was.
+pub fn parse(value: Value) -> anyhow::Result<(Vec<ScoreInfo>, u32)> {
+    parse_with_awards(value).map(|(scores, _, from)| (scores, from))
+}
+
+/// [`parse`], keeping the awards too.
+pub fn parse_with_awards(value: Value) -> anyhow::Result<(Vec<ScoreInfo>, Awards, u32)> {
+    ensure_supported(&value)?;
+    let from = version(&value)?;
+    let value = MIGRATIONS[from as usize - 1..]
+        .iter()
+        .try_fold(value, |value, migration| migration(value))?;
+    let envelope: Envelope<Vec<ScoreInfo>, Awards> = serde_json::from_value(value)?;
+    Ok((envelope.scores, envelope.awards, from))
+}
+
+pub fn to_writer(
+    writer: impl std::io::Write,
+    scores: &[ScoreInfo],
+    awards: &Awards,
+) -> anyhow::Result<()> {
+    let envelope = Envelope {
+        version: CURRENT_VERSION,
+        scores,
+        awards,
+    };
+    serde_json::to_writer(writer, &envelope)?;
+    Ok(())
+}
+
+fn wrap_in_envelope(value: Value) -> anyhow::Result<Value> {
+    Ok(json!({ "version": 2, "scores": value }))
+}
+
+/// Version 3 records amended and rebased commits. Older scores haven't been
+/// rewritten, so only the version changes.
+fn add_rewrites(mut value: Value) -> anyhow::Result<Value> {
+    value["version"] = json!(3);
+    Ok(value)
+}
+
+/// Version 4 flags suspicious scores. Older scores weren't checked.
+fn add_flags(mut value: Value) -> anyhow::Result<Value> {
+    value["version"] = json!(4);
+    Ok(value)
+}
+
+/// Version 5 tags mined commits. Older scores weren't mined.
+fn add_mined(mut value: Value) -> anyhow::Result<Value> {
+    value["version"] = json!(5);
+    Ok(value)
+}
+
+/// Version 6 records how much of the hash was scored. Older scores were
+/// scored on the short hash they were recorded with, with older points, so
+/// they are all scored again to be compared with new ones.
+fn add_lengths(mut value: Value) -> anyhow::Result<Value> {
+    let scores = value["scores"]
+        .as_array_mut()
+        .ok_or(anyhow!("high score file has no scores"))?;
+    for score in scores {
+        let commit = score["commit"].as_str().unwrap_or_default().to_string();
+        match legacy_score(&commit) {
+            Some(result) => {
+                score["score"] = json!(result.total_points());
+                score["rules"] = json!(result.rules.iter().map(|r| r.id()).collect::<Vec<_>>());
+                score["length"] = json!(result.hash.len());
+            }
+            None => score["length"] = json!(commit.len()),
+        };
+    }
+    value["version"] = json!(6);
+    Ok(value)
+}
+
+/// Version 7 records which rule a score hit by id instead of by name, so that
+/// stats don't depend on how rules are named.
+fn add_rule_ids(mut value: Value) -> anyhow::Result<Value> {
+    let scores = value["scores"]
+        .as_array_mut()
+        .ok_or(anyhow!("high score file has no scores"))?;
+    for score in scores {
+        let commit = score["commit"].as_str().unwrap_or_default().to_string();
+        let length = score["length"].as_u64().unwrap_or_default() as usize;
+        let rules: Vec<RuleId> = score["rules"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(Value::as_str)
+            .map(|name| RuleId::from_name(name, &commit, length))
+            .collect();
+        score["rules"] = json!(rules);
+    }
+    value["version"] = json!(7);
+    Ok(value)
+}
+
+/// Version 8 records the full hash. Older scores only have the short one.
+fn add_full_hashes(mut value: Value) -> anyhow::Result<Value> {
+    value["version"] = json!(8);
+    Ok(value)
+}
+
+/// Version 9 keeps the archived seasons and unlocked achievements with the
+/// scores. Older files have none, since they were kept on each machine.
+fn add_awards(mut value: Value) -> anyhow::Result<Value> {
+    value["version"] = json!(9);
+    Ok(value)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::combinatorics::FlushKind;
+
+    #[test]
+    fn test_migrates_bare_array() {
+        let old = json!([{"repo": "/r", "commit": "aaaaaaa", "score": 1, "date": 0, "rules": []}]);
+        let (scores, from) = parse(old).unwrap();
+        assert_eq!(from, 1);
+        assert_eq!(scores[0].commit, "aaaaaaa");
+        assert_eq!(scores[0].length, 7);
+
+        let awards = Awards {
+            seasons: vec![ArchivedSeason {
+                name: "Autumn".into(),
+                start: Default::default(),
+                end: Default::default(),
+                length: 7,
+                winners: scores.clone(),
+            }],
+            achievements: [(
+                "someone@example.com".into(),
+                vec![Unlocked {
+                    achievement: crate::achievements::Achievement::Commits,
+                    commit: "aaaaaaa".into(),
+                    repo: "/r".into(),
+                    date: 0,
+                }],
+            )]
+            .into(),
+        };
+        let mut written = vec![];
+        to_writer(&mut written, &scores, &awards).unwrap();
+        let value: Value = serde_json::from_slice(&written).unwrap();
+        assert_eq!(version(&value).unwrap(), CURRENT_VERSION);
+        let (read, read_awards, _) = parse_with_awards(value).unwrap();
+        assert_eq!((read.len(), read_awards), (1, awards));
+
+        assert!(parse(json!({"version": CURRENT_VERSION + 1, "scores": []})).is_err());
+    }
+
+    #[test]
+    fn test_rescores_legacy_scores() {
+        let old = json!({"version": 5, "scores": [
+            {"repo": "/r", "commit": "aaaabbbb", "score": 1, "date": 0, "rules": ["FULL HOUSE!!"]},
+            {"repo": "/r", "commit": "aaaabbb", "score": 2, "date": 0, "rules": ["FULL HOUSE!!"]},
+        ]});
+        let scores = parse(old).unwrap().0;
+        let rescored = legacy_score("aaaabbb").unwrap().total_points();
+        for score in &scores {
+            assert_eq!(score.length, 7);
+            assert_eq!(
+                score.rules,
+                vec![RuleId::FullHouse, RuleId::Flush(FlushKind::Letters)]
+            );
+            assert_eq!(score.score, rescored);
+        }
+    }
+
+    #[test]
+    fn test_rule_names_become_ids() {
+        let old = json!({"version": 6, "scores": [
+            {"repo": "/r", "commit": "1234567", "score": 1, "date": 0, "length": 7,
+             "rules": ["Straight", "Flush", "Poker hand: Straight"]},
+            {"repo": "/r", "commit": "1234a89", "score": 1, "date": 0, "length": 7,
+             "rules": ["2 pairs!", "Partial straight"]},
+        ]});
+        let scores = parse(old).unwrap().0;
+        assert_eq!(
+            scores[0].rules,
+            vec![
+                RuleId::Straight(7),
+                RuleId::Flush(FlushKind::Numbers),
+                // the deal it was scored with wasn't kept
+                RuleId::Legacy("Poker hand: Straight".into())
+            ]
+        );
+        assert_eq!(scores[1].rules, vec![RuleId::Pairs(2), RuleId::Straight(4)]);
+    }
+}
@@ -0,0 +1,51 @@
+use git2::{Oid, Repository, Signature};
+
+use crate::{git::Person, highscores::ScoreInfo};
+
+/// Commit the current index on top of HEAD as the given author.
+pub fn commit(repo: &Repository, email: &str, time: i64) -> Oid {
+    let sig = Signature::new("Someone", email, &git2::Time::new(time, 0)).unwrap();
+    let tree = repo
+        .find_tree(repo.index().unwrap().write_tree().unwrap())
+        .unwrap();
+    let parents: Vec<git2::Commit> = repo
+        .head()
+        .ok()
+        .map(|h| h.peel_to_commit().unwrap())
+        .into_iter()
+        .collect();
+    let parents: Vec<&git2::Commit> = parents.iter().collect();
+    repo.commit(Some("HEAD"), &sig, &sig, "commit", &tree, &parents)
+        .unwrap()
+}
+
+/// A score with everything but the commit and points left at a default,
+/// to be changed with struct update syntax.
+pub fn score_info(commit: &str, points: u64) -> ScoreInfo {
+    ScoreInfo {
+        repo: "/repo".into(),
+        root: None,
+        remote: None,
+        commit: commit.into(),
+        full_hash: None,
+        score: points,
+        date: 1_000,
+        rules: vec![],
+        length: 7,
+        author: Some(person("someone@example.com")),
+        committer: None,
+        rewritten_as: None,
+        superseded: false,
+        tree: None,
+        flags: vec![],
+        disqualified: false,
+        mined: false,
+    }
+}
+
+pub fn person(email: &str) -> Person {
+    Person {
+        name: "Someone".into(),
+        email: email.into(),
+    }
+}